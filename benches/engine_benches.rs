@@ -1,5 +1,7 @@
-use arimaa_engine_step::{take_actions, GameState};
+use arimaa_engine_step::{take_actions, ActionList, GameState};
 use criterion::{black_box, criterion_group, criterion_main, Bencher, Criterion};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 fn bench_valid_actions(b: &mut Bencher) {
     let game_state: GameState = "
@@ -70,8 +72,138 @@ fn bench_valid_actions(b: &mut Bencher) {
     });
 }
 
+const PLAYOUT_STEPS: usize = 200;
+
+fn playout_state() -> GameState {
+    "
+        1g
+         +-----------------+
+        8| h c d m e d c h |
+        7| r r r r r r r r |
+        6|     x     x     |
+        5|                 |
+        4|                 |
+        3|     x     x     |
+        2| R R R R R R R R |
+        1| H C D M E D C H |
+         +-----------------+
+           a b c d e f g h
+    "
+    .parse()
+    .unwrap()
+}
+
+/// The states reached by a fixed playout from the initial position, so both benchmarks generate actions for the same states.
+fn playout_states() -> Vec<GameState> {
+    let mut game_state = playout_state();
+    let mut states = Vec::with_capacity(PLAYOUT_STEPS);
+    for i in 0..PLAYOUT_STEPS {
+        if game_state.is_terminal().is_some() {
+            break;
+        }
+
+        let valid_actions = game_state.valid_actions();
+        let next_game_state = game_state.take_action(&valid_actions[i * 7 % valid_actions.len()]);
+        states.push(game_state);
+        game_state = next_game_state;
+    }
+
+    states
+}
+
+fn bench_playout_valid_actions(b: &mut Bencher) {
+    let states = black_box(playout_states());
+
+    b.iter(|| {
+        states
+            .iter()
+            .map(|game_state| game_state.valid_actions().len())
+            .sum::<usize>()
+    });
+}
+
+fn bench_playout_valid_actions_into(b: &mut Bencher) {
+    let states = black_box(playout_states());
+    let mut valid_actions = ActionList::new();
+
+    b.iter(|| {
+        states
+            .iter()
+            .map(|game_state| {
+                game_state.valid_actions_into(&mut valid_actions);
+                valid_actions.len()
+            })
+            .sum::<usize>()
+    });
+}
+
+/// Counts the allocations made through the global allocator, so the benchmarks can report what generating actions allocates.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocations<F: FnMut()>(mut f: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn report_playout_allocations() {
+    let states = playout_states();
+    let mut valid_actions = ActionList::new();
+
+    let allocations = count_allocations(|| {
+        for game_state in &states {
+            black_box(game_state.valid_actions());
+        }
+    });
+    println!(
+        "playout_valid_actions: {} allocations over {} states",
+        allocations,
+        states.len()
+    );
+
+    let allocations = count_allocations(|| {
+        for game_state in &states {
+            game_state.valid_actions_into(&mut valid_actions);
+            black_box(&valid_actions);
+        }
+    });
+    println!(
+        "playout_valid_actions_into: {} allocations over {} states",
+        allocations,
+        states.len()
+    );
+}
+
 fn bench_main(c: &mut Criterion) {
+    report_playout_allocations();
+
     c.bench_function("valid_actions", bench_valid_actions);
+    c.bench_function("playout_valid_actions", bench_playout_valid_actions);
+    c.bench_function(
+        "playout_valid_actions_into",
+        bench_playout_valid_actions_into,
+    );
 }
 
 criterion_group!(benches, bench_main);
@@ -1,4 +1,4 @@
-use super::{Direction, Piece, Square};
+use super::{BitBoard, Direction, Piece, Square};
 use anyhow::anyhow;
 use std::fmt;
 use std::str::FromStr;
@@ -54,19 +54,12 @@ impl FromStr for Action {
     }
 }
 
+#[deprecated(
+    since = "2.0.0",
+    note = "iterate a `BitBoard` instead, which does not allocate"
+)]
 pub fn map_bit_board_to_squares(board: u64) -> Vec<Square> {
-    let mut board = board;
-    let mut squares = Vec::with_capacity(board.count_ones() as usize);
-
-    while board != 0 {
-        let bit_idx = board.trailing_zeros();
-        let square = Square::from_index(bit_idx as u8);
-        squares.push(square);
-
-        board ^= 1 << bit_idx;
-    }
-
-    squares
+    BitBoard::new(board).iter().collect()
 }
 
 #[cfg(test)]
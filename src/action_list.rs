@@ -0,0 +1,202 @@
+use super::Action;
+use std::fmt;
+use std::ops::Deref;
+
+/// The most actions that can be valid in any state: a move in each direction from every square, plus a pass.
+pub const MAX_ACTIONS: usize = 64 * 4 + 1;
+
+/// A buffer that valid actions can be written into, such as with `GameState::valid_actions_into`.
+/// Implemented for `Vec<Action>` and the fixed capacity `ActionList`.
+pub trait ActionBuffer {
+    fn push(&mut self, action: Action);
+
+    fn clear(&mut self);
+
+    fn as_slice(&self) -> &[Action];
+
+    fn retain<F: FnMut(&Action) -> bool>(&mut self, f: F);
+}
+
+impl ActionBuffer for Vec<Action> {
+    fn push(&mut self, action: Action) {
+        Vec::push(self, action);
+    }
+
+    fn clear(&mut self) {
+        Vec::clear(self);
+    }
+
+    fn as_slice(&self) -> &[Action] {
+        self
+    }
+
+    fn retain<F: FnMut(&Action) -> bool>(&mut self, f: F) {
+        Vec::retain(self, f);
+    }
+}
+
+/// A list of actions stored inline with a capacity of `MAX_ACTIONS`, so that it never allocates.
+/// Dereferences to a slice of the actions.
+///
+/// No state has more than `MAX_ACTIONS` valid actions, so the list holds every action generated for a state.
+/// Pushing more than `MAX_ACTIONS` actions panics.
+#[derive(Clone)]
+pub struct ActionList {
+    actions: [Action; MAX_ACTIONS],
+    len: usize,
+}
+
+impl ActionList {
+    pub fn new() -> Self {
+        Self {
+            actions: [Action::Pass; MAX_ACTIONS],
+            len: 0,
+        }
+    }
+}
+
+impl Default for ActionList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActionBuffer for ActionList {
+    /// Panics if the list already holds `MAX_ACTIONS` actions.
+    fn push(&mut self, action: Action) {
+        debug_assert!(
+            self.len < MAX_ACTIONS,
+            "ActionList can hold at most MAX_ACTIONS ({}) actions",
+            MAX_ACTIONS
+        );
+        self.actions[self.len] = action;
+        self.len += 1;
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn as_slice(&self) -> &[Action] {
+        &self.actions[..self.len]
+    }
+
+    fn retain<F: FnMut(&Action) -> bool>(&mut self, mut f: F) {
+        let mut len = 0;
+
+        for i in 0..self.len {
+            let action = self.actions[i];
+            if f(&action) {
+                self.actions[len] = action;
+                len += 1;
+            }
+        }
+
+        self.len = len;
+    }
+}
+
+impl Deref for ActionList {
+    type Target = [Action];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<'a> IntoIterator for &'a ActionList {
+    type Item = &'a Action;
+    type IntoIter = std::slice::Iter<'a, Action>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl PartialEq for ActionList {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for ActionList {}
+
+impl fmt::Debug for ActionList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Direction, Square};
+    use super::*;
+
+    fn move_action(square: &str, direction: Direction) -> Action {
+        Action::Move(square.parse::<Square>().unwrap(), direction)
+    }
+
+    #[test]
+    fn test_action_list_push_and_clear() {
+        let mut actions = ActionList::new();
+        assert!(actions.is_empty());
+
+        actions.push(move_action("a1", Direction::Up));
+        actions.push(Action::Pass);
+
+        assert_eq!(actions.len(), 2);
+        assert_eq!(
+            &actions[..],
+            &[move_action("a1", Direction::Up), Action::Pass]
+        );
+        assert!(actions.contains(&Action::Pass));
+
+        actions.clear();
+        assert!(actions.is_empty());
+        assert_eq!(actions, ActionList::default());
+    }
+
+    #[test]
+    fn test_action_list_retain() {
+        let mut actions = ActionList::new();
+        for direction in Direction::ALL {
+            actions.push(move_action("d4", direction));
+        }
+        actions.push(Action::Pass);
+
+        actions
+            .retain(|action| !matches!(action, Action::Move(_, Direction::Up | Direction::Down)));
+
+        assert_eq!(
+            actions.iter().copied().collect::<Vec<_>>(),
+            vec![
+                move_action("d4", Direction::Right),
+                move_action("d4", Direction::Left),
+                Action::Pass
+            ]
+        );
+    }
+
+    #[test]
+    fn test_action_list_holds_max_actions() {
+        let mut actions = ActionList::new();
+        for index in 0..64 {
+            for direction in Direction::ALL {
+                actions.push(Action::Move(Square::from_index(index), direction));
+            }
+        }
+        actions.push(Action::Pass);
+
+        assert_eq!(actions.len(), MAX_ACTIONS);
+    }
+
+    #[test]
+    #[should_panic(expected = "ActionList can hold at most MAX_ACTIONS")]
+    #[cfg(debug_assertions)]
+    fn test_action_list_push_past_max_actions() {
+        let mut actions = ActionList::new();
+        for _ in 0..=MAX_ACTIONS {
+            actions.push(Action::Pass);
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use super::full_move::{convert_actions_to_move_string, try_convert_move_string_to_actions};
+use super::{Action, GameState, MoveGenOptions};
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// A search that can be run by the `AeiServer` to find the move to play.
+pub trait AeiSearcher: Send + 'static {
+    /// The name of the engine reported to the controller.
+    fn name(&self) -> String {
+        env!("CARGO_PKG_NAME").to_string()
+    }
+
+    /// The author of the engine reported to the controller.
+    fn author(&self) -> String {
+        env!("CARGO_PKG_AUTHORS").to_string()
+    }
+
+    /// Called when the controller starts a new game.
+    fn new_game(&mut self) {}
+
+    /// Called when the controller sets an option. Options which are not recognized should be ignored.
+    fn set_option(&mut self, _name: &str, _value: Option<&str>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Searches for the full move to play from the specified state.
+    /// The search should return its best move as soon as possible once `stop` has been set.
+    fn search(&mut self, game_state: &GameState, stop: &AtomicBool) -> Vec<Action>;
+}
+
+/// A searcher that plays the first valid move. Useful for testing controllers.
+#[derive(Default)]
+pub struct FirstMoveSearcher;
+
+impl AeiSearcher for FirstMoveSearcher {
+    fn search(&mut self, game_state: &GameState, _stop: &AtomicBool) -> Vec<Action> {
+        game_state
+            .valid_moves_iter(MoveGenOptions::default())
+            .next()
+            .map_or_else(Vec::new, |(actions, _)| actions)
+    }
+}
+
+/// Serves the Arimaa Engine Interface (AEI) protocol for a searcher.
+///
+/// Commands are read line by line from the input and responses are written to the output.
+/// Searches run on a separate thread so that the controller is able to `stop` them.
+pub struct AeiServer<S: AeiSearcher, W: Write + Send + 'static> {
+    searcher: Option<S>,
+    search: Option<JoinHandle<S>>,
+    stop: Arc<AtomicBool>,
+    game_state: GameState,
+    output: Arc<Mutex<W>>,
+}
+
+impl<S: AeiSearcher, W: Write + Send + 'static> AeiServer<S, W> {
+    pub fn new(searcher: S, output: W) -> Self {
+        AeiServer {
+            searcher: Some(searcher),
+            search: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            game_state: GameState::initial(),
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+
+    /// Returns the state of the game as set by the controller.
+    pub fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+
+    /// Processes commands from the input until `quit` is received or the input is closed.
+    pub fn run<R: BufRead>(&mut self, input: R) -> Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let quit = match self.handle_command(line) {
+                Ok(quit) => quit,
+                Err(err) => {
+                    self.write_line(&format!("log Error: {}", err))?;
+                    false
+                }
+            };
+
+            if quit {
+                return Ok(());
+            }
+        }
+
+        self.stop_search()
+    }
+
+    /// Handles a single command. Returns true if the server should quit.
+    pub fn handle_command(&mut self, line: &str) -> Result<bool> {
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        let args = args.trim();
+
+        match command {
+            "aei" => {
+                let searcher = self.searcher()?;
+                let name = searcher.name();
+                let author = searcher.author();
+                self.write_line("protocol-version 1")?;
+                self.write_line(&format!("id name {}", name))?;
+                self.write_line(&format!("id author {}", author))?;
+                self.write_line("aeiok")?;
+            }
+            "isready" => self.write_line("readyok")?,
+            "newgame" => {
+                self.searcher()?.new_game();
+                self.game_state = GameState::initial();
+            }
+            "setposition" => {
+                self.wait_for_search()?;
+                // The AEI position does not include the move number, so it is placed on the first move.
+                self.game_state = args.parse::<GameState>()?.with_move_number(1);
+            }
+            "setoption" => {
+                let (name, value) = parse_set_option(args)?;
+                self.searcher()?.set_option(name, value)?;
+            }
+            "makemove" => {
+                self.wait_for_search()?;
+                let actions = try_convert_move_string_to_actions(&self.game_state, args)?;
+                let mut game_state = self.game_state.clone();
+                for action in actions.iter() {
+                    game_state = game_state.take_action(action);
+                }
+                self.game_state = game_state;
+            }
+            "go" => {
+                // Pondering is optional in the protocol and is not supported.
+                if args != "ponder" {
+                    self.start_search()?;
+                }
+            }
+            "stop" => self.stop_search()?,
+            "quit" => {
+                self.stop_search()?;
+                self.write_line("log Quitting")?;
+                return Ok(true);
+            }
+            _ => return Err(anyhow!("Unrecognized command '{}'", command)),
+        }
+
+        Ok(false)
+    }
+
+    fn start_search(&mut self) -> Result<()> {
+        self.wait_for_search()?;
+        let mut searcher = self
+            .searcher
+            .take()
+            .expect("Searcher should be available once the search has completed");
+        let game_state = self.game_state.clone();
+        let stop = self.stop.clone();
+        let output = self.output.clone();
+
+        stop.store(false, Ordering::SeqCst);
+
+        self.search = Some(thread::spawn(move || {
+            let actions = searcher.search(&game_state, &stop);
+            let move_string = if actions.is_empty() {
+                String::new()
+            } else {
+                convert_actions_to_move_string(game_state, &actions)
+            };
+
+            let mut output = output.lock().unwrap();
+            let _ = writeln!(output, "bestmove {}", move_string);
+            let _ = output.flush();
+
+            searcher
+        }));
+
+        Ok(())
+    }
+
+    fn stop_search(&mut self) -> Result<()> {
+        self.wait_for_search()
+    }
+
+    /// Stops any running search and waits for it to report its move.
+    /// Searches such as `go infinite` only end once stopped, so any command that needs the searcher must stop it first.
+    fn wait_for_search(&mut self) -> Result<()> {
+        if let Some(search) = self.search.take() {
+            self.stop.store(true, Ordering::SeqCst);
+            let searcher = search
+                .join()
+                .map_err(|_| anyhow!("Search thread panicked"))?;
+            self.searcher = Some(searcher);
+        }
+
+        Ok(())
+    }
+
+    fn searcher(&mut self) -> Result<&mut S> {
+        self.wait_for_search()?;
+        Ok(self
+            .searcher
+            .as_mut()
+            .expect("Searcher should be available once the search has completed"))
+    }
+
+    fn write_line(&self, line: &str) -> Result<()> {
+        let mut output = self.output.lock().unwrap();
+        writeln!(output, "{}", line)?;
+        output.flush()?;
+        Ok(())
+    }
+}
+
+fn parse_set_option(args: &str) -> Result<(&str, Option<&str>)> {
+    let args = args
+        .strip_prefix("name ")
+        .ok_or_else(|| anyhow!("Expected 'setoption name <id> [value <x>]'"))?;
+
+    Ok(match args.split_once(" value ") {
+        Some((name, value)) => (name.trim(), Some(value.trim())),
+        None => (args.trim(), None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Action, BitBoard, GameState};
+    use super::{AeiSearcher, AeiServer, FirstMoveSearcher};
+    use anyhow::Result;
+    use std::io::{Cursor, Write};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedOutput {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|l| l.to_string())
+                .collect()
+        }
+    }
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run_script<S: AeiSearcher>(searcher: S, script: &str) -> (Vec<String>, GameState) {
+        let output = SharedOutput::default();
+        let mut server = AeiServer::new(searcher, output.clone());
+        server.run(Cursor::new(script)).unwrap();
+        (output.lines(), server.game_state().clone())
+    }
+
+    #[derive(Default)]
+    struct InfiniteSearcher {
+        options: Vec<(String, Option<String>)>,
+    }
+
+    impl AeiSearcher for InfiniteSearcher {
+        fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<()> {
+            self.options
+                .push((name.to_string(), value.map(|v| v.to_string())));
+            Ok(())
+        }
+
+        fn search(&mut self, game_state: &GameState, stop: &AtomicBool) -> Vec<Action> {
+            while !stop.load(Ordering::SeqCst) {
+                std::thread::yield_now();
+            }
+
+            let option_count = self.options.len();
+            let (actions, _) = game_state
+                .valid_moves()
+                .into_iter()
+                .nth(option_count)
+                .unwrap();
+            actions
+        }
+    }
+
+    #[test]
+    fn test_aei_handshake() {
+        let (lines, _) = run_script(FirstMoveSearcher, "aei\nisready\nquit\n");
+
+        assert_eq!(lines[0], "protocol-version 1");
+        assert_eq!(lines[1], "id name arimaa_engine_step");
+        assert!(lines[2].starts_with("id author "));
+        assert_eq!(lines[3], "aeiok");
+        assert_eq!(lines[4], "readyok");
+    }
+
+    #[test]
+    fn test_aei_setup_move() {
+        let (lines, _) = run_script(FirstMoveSearcher, "newgame\ngo\nquit\n");
+
+        assert_eq!(
+            lines[0],
+            "bestmove Ea2 Mb2 Hc2 Hd2 De2 Df2 Cg2 Ch2 Ra1 Rb1 Rc1 Rd1 Re1 Rf1 Rg1 Rh1"
+        );
+    }
+
+    #[test]
+    fn test_aei_makemove() {
+        let script = "newgame
+makemove Ra1 Rb1 Rc1 Rd1 Re1 Rf1 Rg1 Rh1 Ha2 Cb2 Dc2 Md2 Ee2 Df2 Cg2 Hh2
+makemove ha7 cb7 dc7 md7 ee7 df7 cg7 hh7 ra8 rb8 rc8 rd8 re8 rf8 rg8 rh8
+makemove Ee2n Ee3n Ee4n Ee5w
+quit
+";
+        let (lines, game_state) = run_script(FirstMoveSearcher, script);
+
+        assert_eq!(lines, vec!["log Quitting".to_string()]);
+        assert!(!game_state.is_p1_turn_to_move());
+        assert_eq!(game_state.move_number(), 2);
+        assert_eq!(
+            game_state.piece_board().elephants() & game_state.piece_board().p1_pieces(),
+            BitBoard::from("d5".parse::<super::super::Square>().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_aei_setposition_and_go() {
+        let script =
+            "setposition s [hcdmedchrrrrrrrr                                RRRRRRRRHCDMEDCH]
+go
+quit
+";
+        let (lines, _) = run_script(FirstMoveSearcher, script);
+
+        assert_eq!(lines[0], "bestmove ra7s ra6e rb6e rc6e");
+    }
+
+    #[test]
+    fn test_aei_stop() {
+        let script = "newgame
+setposition g [hcdmedchrrrrrrrr                                RRRRRRRRHCDMEDCH]
+setoption name tcmove value 30
+go
+stop
+isready
+quit
+";
+        let (lines, _) = run_script(InfiniteSearcher::default(), script);
+
+        assert!(lines[0].starts_with("bestmove "));
+        assert_eq!(lines[1], "readyok");
+    }
+
+    #[test]
+    fn test_aei_setposition_during_infinite_search() {
+        let script = "newgame
+setposition g [hcdmedchrrrrrrrr                                RRRRRRRRHCDMEDCH]
+go infinite
+setposition s [hcdmedchrrrrrrrr                        R       RRRR RRRHCDMEDCH]
+isready
+quit
+";
+        let (lines, game_state) = run_script(InfiniteSearcher::default(), script);
+
+        assert!(lines[0].starts_with("bestmove "));
+        assert_eq!(lines[1], "readyok");
+        assert!(!game_state.is_p1_turn_to_move());
+        assert!(game_state.is_play_phase());
+        assert_eq!(game_state.move_number(), 1);
+
+        let piece_board = game_state.piece_board();
+        let square = |square: &str| square.parse::<super::super::Square>().unwrap();
+        assert_eq!(
+            piece_board.piece_type_at_square(&square("a3")),
+            Some(super::super::Piece::Rabbit)
+        );
+        assert_eq!(piece_board.piece_type_at_square(&square("e2")), None);
+    }
+
+    #[test]
+    fn test_aei_invalid_makemove() {
+        let (lines, game_state) =
+            run_script(FirstMoveSearcher, "newgame\nmakemove Ra2 Rb2\nquit\n");
+
+        assert_eq!(lines[0], "log Error: Move is incomplete after step 2");
+        assert!(!game_state.is_play_phase());
+    }
+
+    #[test]
+    fn test_aei_unrecognized_command() {
+        let (lines, _) = run_script(FirstMoveSearcher, "foo\nquit\n");
+
+        assert_eq!(lines[0], "log Error: Unrecognized command 'foo'");
+    }
+}
@@ -0,0 +1,9 @@
+use arimaa_engine_step::aei::{AeiServer, FirstMoveSearcher};
+use std::io;
+
+fn main() -> anyhow::Result<()> {
+    let stdin = io::stdin();
+    let mut server = AeiServer::new(FirstMoveSearcher, io::stdout());
+
+    server.run(stdin.lock())
+}
@@ -0,0 +1,59 @@
+use anyhow::{anyhow, Result};
+use arimaa_engine_step::{take_actions, GameState};
+use std::env;
+use std::time::Instant;
+
+/// Counts the nodes of the full move tree for each depth up to the specified depth.
+///
+/// Usage: `perft <depth> [position] [--divide]`
+///
+/// The position is given in the extended notation of `GameState::to_extended_notation`.
+/// Without a position, the counts are for the start of the play phase after a standard setup.
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let divide = args.iter().any(|arg| arg == "--divide");
+    let mut args = args.iter().filter(|arg| *arg != "--divide");
+
+    let depth: usize = args
+        .next()
+        .ok_or_else(|| anyhow!("Usage: perft <depth> [position] [--divide]"))?
+        .parse()?;
+    let game_state = match args.next() {
+        Some(position) => GameState::from_extended_notation(position)?,
+        None => initial_play_state(),
+    };
+
+    println!("{}", game_state);
+
+    if divide {
+        for (move_string, result) in game_state.perft_divide(depth) {
+            println!(
+                "{}: moves {} steps {}",
+                move_string, result.moves, result.steps
+            );
+        }
+    }
+
+    for depth in 1..=depth {
+        let start = Instant::now();
+        let result = game_state.perft(depth);
+        println!(
+            "depth {}: moves {} steps {} ({:.3}s)",
+            depth,
+            result.moves,
+            result.steps,
+            start.elapsed().as_secs_f64()
+        );
+    }
+
+    Ok(())
+}
+
+fn initial_play_state() -> GameState {
+    let game_state = GameState::initial();
+    let game_state = take_actions!(game_state => r, r, r, r, r, r, r, r);
+    let game_state = take_actions!(game_state => c, d, h, c, e, h, d, c);
+
+    let game_state = take_actions!(game_state => c, d, h, c, e, h, d, c);
+    take_actions!(game_state => r, r, r, r, r, r, r, r)
+}
@@ -0,0 +1,881 @@
+use super::bit_mask::TRAP_MASK;
+use super::display::setup_state;
+use super::{Action, BitBoard, Direction, GameState, List, Phase, Piece, PieceBoard};
+use super::{PieceBoardState, PlayPhase, PushPullState, Square, Zobrist};
+use anyhow::{anyhow, Result};
+use std::io::{self, Read, Write};
+
+/// The version of the binary encoding written by `GameState::to_bytes`. It is the first byte of each encoded state.
+pub const ENCODING_VERSION: u8 = 1;
+
+const P1_TURN_TO_MOVE_FLAG: u8 = 0b0001;
+const PLAY_PHASE_FLAG: u8 = 0b0010;
+const STEP_SHIFT: u8 = 2;
+const STEP_MASK: u8 = 0b1100;
+
+const GOLD_PIECE_FLAG: u8 = 0b1000;
+const PIECE_MASK: u8 = 0b0111;
+
+impl Action {
+    /// Encodes the action in a single byte.
+    ///
+    /// Moves are encoded as `square * 4 + direction`. Moves north from the top row can't occur, so their values are used for the other actions:
+    /// a pass is encoded as `a8n` and placing a piece as `b8n` through `g8n`, from rabbit to elephant.
+    pub fn to_byte(&self) -> u8 {
+        match self {
+            Action::Move(square, direction) => (square.index() * 4 + direction.index()) as u8,
+            Action::Pass => 0,
+            Action::Place(piece) => ((piece.index() + 1) * 4) as u8,
+        }
+    }
+
+    /// Decodes an action encoded with `to_byte`. Returns None if the byte is not a valid encoding, such as for a move off of the board.
+    pub fn from_byte(byte: u8) -> Option<Action> {
+        let square = Square::from_index(byte / 4);
+        let direction = Direction::ALL[(byte % 4) as usize];
+
+        if square.row() == 8 && direction == Direction::Up {
+            return match byte / 4 {
+                0 => Some(Action::Pass),
+                index if index <= Piece::ALL.len() as u8 => {
+                    Some(Action::Place(Piece::ALL[index as usize - 1]))
+                }
+                _ => None,
+            };
+        }
+
+        let is_off_board = match direction {
+            Direction::Up => false,
+            Direction::Right => square.column_char() == 'h',
+            Direction::Down => square.row() == 1,
+            Direction::Left => square.column_char() == 'a',
+        };
+
+        if is_off_board {
+            None
+        } else {
+            Some(Action::Move(square, direction))
+        }
+    }
+}
+
+impl GameState {
+    /// Encodes the state in a compact binary form, which can be decoded with `from_bytes`.
+    ///
+    /// The encoding contains the version, the side to move, the phase, the step, the move number, the piece board at the start of the move
+    /// and a byte for each step taken so far this move. The push or pull state, the trapped pieces and the previous piece boards of the move
+    /// are restored by replaying the steps. The hash history of previous moves is not encoded, so a decoded state does not detect third time repetitions.
+    /// Positions take at most 24 bytes for the board, plus a few bytes for the header and steps.
+    ///
+    /// Returns an error if the piece boards of the previous steps of the move are missing or don't follow from a step each,
+    /// such as for a state created with `GameState::new` in the middle of a push without them.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(32);
+        self.write_bytes(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Appends the encoding of the state, as returned by `to_bytes`, to the buffer. Nothing is appended if the state can't be encoded.
+    pub fn write_bytes(&self, bytes: &mut Vec<u8>) -> Result<()> {
+        let (start_piece_board, steps) = match self.as_play_phase() {
+            Some(play_phase) => {
+                let previous_piece_boards = play_phase.previous_piece_boards();
+                if previous_piece_boards.is_empty()
+                    && (play_phase.push_pull_state() != PushPullState::None
+                        || play_phase.piece_trapped_this_turn())
+                {
+                    return Err(anyhow!(
+                        "Expected the piece boards of the previous steps of the move"
+                    ));
+                }
+
+                let start_piece_board = previous_piece_boards
+                    .first()
+                    .map_or(self.piece_board(), |piece_board| piece_board.piece_board());
+
+                (start_piece_board, self.steps_this_move()?)
+            }
+            None => (self.piece_board(), Vec::new()),
+        };
+
+        let mut flags = (steps.len() as u8) << STEP_SHIFT;
+        if self.is_p1_turn_to_move() {
+            flags |= P1_TURN_TO_MOVE_FLAG;
+        }
+        if self.is_play_phase() {
+            flags |= PLAY_PHASE_FLAG;
+        }
+
+        bytes.push(ENCODING_VERSION);
+        bytes.push(flags);
+        write_varint(self.move_number() as u64, bytes);
+        write_piece_board(start_piece_board, bytes);
+        bytes.extend(steps.iter().map(Action::to_byte));
+
+        Ok(())
+    }
+
+    /// Decodes a state encoded with `to_bytes`. Returns an error if the bytes are not a valid encoding or have trailing bytes,
+    /// including boards that can't occur in a game, such as with extra pieces or an unsupported piece on a trap.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader { bytes, position: 0 };
+        let game_state = reader.read_game_state()?;
+
+        if reader.position != bytes.len() {
+            return Err(anyhow!(
+                "Unexpected {} trailing bytes",
+                bytes.len() - reader.position
+            ));
+        }
+
+        Ok(game_state)
+    }
+
+    /// Finds the steps taken so far this move from the squares that changed between each of the piece boards of the move.
+    fn steps_this_move(&self) -> Result<Vec<Action>> {
+        let previous_piece_boards = self.unwrap_play_phase().previous_piece_boards();
+        let piece_boards: Vec<&PieceBoardState> = previous_piece_boards
+            .iter()
+            .map(|piece_board| piece_board.piece_board())
+            .chain(std::iter::once(self.piece_board()))
+            .collect();
+
+        piece_boards
+            .windows(2)
+            .map(|piece_boards| {
+                step_between(piece_boards[0], piece_boards[1]).ok_or_else(|| {
+                    anyhow!("Expected each piece board of the move to follow from a step")
+                })
+            })
+            .collect()
+    }
+}
+
+/// Finds the step between consecutive piece boards of a move. The moved piece left the vacated square next to the filled square.
+/// If it was captured on a trap, no square is filled and it moved onto the trap next to the vacated square.
+/// A piece captured after losing its support was next to the vacated square, so it is never next to the filled square.
+fn step_between(previous: &PieceBoardState, next: &PieceBoardState) -> Option<Action> {
+    let changed = previous.all_pieces() ^ next.all_pieces();
+    let vacated = changed & previous.all_pieces();
+    let filled = changed & next.all_pieces();
+
+    let (from, to) = match filled.first() {
+        Some(to) => ((vacated & BitBoard::from(to).neighbors()).first()?, to),
+        None => {
+            let from = vacated.first()?;
+            let trap = BitBoard::from(from).neighbors() & BitBoard::new(TRAP_MASK);
+            (from, trap.first()?)
+        }
+    };
+
+    Direction::ALL
+        .into_iter()
+        .find(|direction| BitBoard::from(from).shift(*direction).contains(to))
+        .map(|direction| Action::Move(from, direction))
+}
+
+/// Writes states to a stream of length prefixed records encoded with `GameState::to_bytes`.
+/// Writes are not buffered, so wrap files in a `BufWriter`.
+pub struct PositionWriter<W: Write> {
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> PositionWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: Vec::with_capacity(64),
+        }
+    }
+
+    /// Writes the state as a record. Returns an error if the state can't be encoded, without writing anything.
+    pub fn write(&mut self, game_state: &GameState) -> Result<()> {
+        self.buffer.clear();
+        self.buffer.push(0);
+        game_state.write_bytes(&mut self.buffer)?;
+        self.buffer[0] = (self.buffer.len() - 1) as u8;
+
+        self.writer.write_all(&self.buffer)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads states from a stream written by `PositionWriter`. Iterating yields each state in order, or an error if a record is invalid or cut short.
+/// Reads are not buffered, so wrap files in a `BufReader`.
+pub struct PositionReader<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> PositionReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::with_capacity(64),
+        }
+    }
+
+    /// Reads the next state, or returns None at the end of the stream.
+    pub fn read(&mut self) -> Result<Option<GameState>> {
+        let mut len = [0];
+        loop {
+            match self.reader.read(&mut len) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        self.buffer.resize(len[0] as usize, 0);
+        self.reader.read_exact(&mut self.buffer)?;
+
+        GameState::from_bytes(&self.buffer).map(Some)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for PositionReader<R> {
+    type Item = Result<GameState>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl ByteReader<'_> {
+    fn read_game_state(&mut self) -> Result<GameState> {
+        let version = self.read_u8()?;
+        if version != ENCODING_VERSION {
+            return Err(anyhow!("Unsupported encoding version {}", version));
+        }
+
+        let flags = self.read_u8()?;
+        if flags & !(P1_TURN_TO_MOVE_FLAG | PLAY_PHASE_FLAG | STEP_MASK) != 0 {
+            return Err(anyhow!("Invalid flags {:#04x}", flags));
+        }
+
+        let p1_turn_to_move = flags & P1_TURN_TO_MOVE_FLAG != 0;
+        let is_play_phase = flags & PLAY_PHASE_FLAG != 0;
+        let step = ((flags & STEP_MASK) >> STEP_SHIFT) as usize;
+        let move_number = usize::try_from(self.read_varint()?)
+            .ok()
+            .filter(|move_number| *move_number != 0)
+            .ok_or_else(|| anyhow!("Invalid move number"))?;
+        let piece_board = self.read_piece_board()?;
+
+        if !is_play_phase {
+            if step != 0 {
+                return Err(anyhow!("Unexpected steps in the setup phase"));
+            }
+
+            return setup_state(p1_turn_to_move, piece_board.piece_board())
+                .filter(|game_state| game_state.move_number() == move_number)
+                .ok_or_else(|| anyhow!("Invalid setup phase piece board"));
+        }
+
+        let mut game_state = state_at_start_of_move(p1_turn_to_move, move_number, &piece_board);
+        for _ in 0..step {
+            let byte = self.read_u8()?;
+            let action = Action::from_byte(byte)
+                .filter(|action| *action != Action::Pass)
+                .ok_or_else(|| anyhow!("Invalid step {:#04x}", byte))?;
+
+            game_state
+                .check_action(&action)
+                .map_err(|reason| anyhow!("Invalid step {}: {}", action, reason))?;
+            game_state = game_state.take_action(&action);
+        }
+
+        Ok(game_state)
+    }
+
+    fn read_piece_board(&mut self) -> Result<PieceBoard> {
+        let mut occupied = [0; 8];
+        occupied.copy_from_slice(self.read_bytes(8)?);
+        let occupied = u64::from_le_bytes(occupied);
+
+        let mut p1_pieces = 0;
+        let mut pieces = [0; 6];
+        let mut nibbles = self
+            .read_bytes(occupied.count_ones().div_ceil(2) as usize)?
+            .iter()
+            .flat_map(|byte| [byte & 0x0F, byte >> 4]);
+
+        let mut remaining = occupied;
+        while remaining != 0 {
+            let square_bit = remaining & remaining.wrapping_neg();
+            let nibble = nibbles.next().unwrap();
+
+            let piece_index = (nibble & PIECE_MASK) as usize;
+            if piece_index >= pieces.len() {
+                return Err(anyhow!("Invalid piece {:#03x}", nibble));
+            }
+
+            pieces[piece_index] |= square_bit;
+            if nibble & GOLD_PIECE_FLAG != 0 {
+                p1_pieces |= square_bit;
+            }
+
+            remaining ^= square_bit;
+        }
+
+        if nibbles.any(|nibble| nibble != 0) {
+            return Err(anyhow!("Invalid padding after the last piece"));
+        }
+
+        let [rabbits, cats, dogs, horses, camels, elephants] = pieces;
+        let piece_board =
+            PieceBoard::new(p1_pieces, elephants, camels, horses, dogs, cats, rabbits);
+        check_piece_board(piece_board.piece_board())?;
+
+        Ok(piece_board)
+    }
+
+    /// Reads a varint as written by `write_varint`. Only the shortest encoding of each value is accepted, and values must fit in 64 bits.
+    fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            let bits = (byte & 0x7F) as u64;
+
+            // A trailing zero byte pads the value, and the tenth byte only has room for the last bit.
+            if (shift > 0 && byte == 0) || bits > u64::MAX >> shift {
+                return Err(anyhow!("Overlong move number"));
+            }
+
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(anyhow!("Overlong move number"))
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&[u8]> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + len)
+            .ok_or_else(|| anyhow!("Unexpected end of bytes"))?;
+
+        self.position += len;
+        Ok(bytes)
+    }
+}
+
+/// Checks that the board could occur in a game. Neither player may have more pieces of a type than they start with,
+/// and no piece may stand on a trap without a friendly piece next to it.
+fn check_piece_board(piece_board: &PieceBoardState) -> Result<()> {
+    for is_p1 in [true, false] {
+        for piece in Piece::ALL {
+            if piece_board.bits_for_piece(piece, is_p1).count() > piece.initial_count() {
+                let player = if is_p1 { "gold" } else { "silver" };
+                return Err(anyhow!("Too many {} {:?} pieces", player, piece));
+            }
+        }
+    }
+
+    if !piece_board.trapped_piece_bits().is_empty() {
+        return Err(anyhow!("Unsupported piece on a trap"));
+    }
+
+    Ok(())
+}
+
+/// Writes a bit board of the occupied squares followed by a nibble for each piece in square order.
+/// The nibble holds the index of the piece with the high bit set for gold pieces.
+fn write_piece_board(piece_board: &PieceBoardState, bytes: &mut Vec<u8>) {
+    bytes.extend(piece_board.all_pieces().bits().to_le_bytes());
+
+    let mut nibbles = Vec::with_capacity(32);
+    for square in piece_board.all_pieces() {
+        let piece = piece_board.piece_type_at_square(&square).unwrap();
+        let color = if piece_board.p1_pieces().contains(square) {
+            GOLD_PIECE_FLAG
+        } else {
+            0
+        };
+
+        nibbles.push(piece.index() as u8 | color);
+    }
+
+    bytes.extend(
+        nibbles
+            .chunks(2)
+            .map(|pair| pair[0] | pair.get(1).map_or(0, |nibble| nibble << 4)),
+    );
+}
+
+fn write_varint(mut value: u64, bytes: &mut Vec<u8>) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+
+    bytes.push(value as u8);
+}
+
+fn state_at_start_of_move(
+    p1_turn_to_move: bool,
+    move_number: usize,
+    piece_board: &PieceBoard,
+) -> GameState {
+    let hash = Zobrist::from_piece_board(piece_board.piece_board(), p1_turn_to_move, 0);
+
+    GameState::new(
+        p1_turn_to_move,
+        move_number,
+        Phase::PlayPhase(PlayPhase::initial(hash, List::new())),
+        piece_board.clone(),
+        hash,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::{initial_play_state, random_positions, to_piece_board};
+    use super::super::{action, take_actions, GameState};
+    use super::*;
+    use std::io::Cursor;
+
+    fn push_into_trap() -> GameState {
+        let game_state: GameState = "
+             12s
+              +-----------------+
+             8|               r |
+             7|                 |
+             6|     x     x     |
+             5|     R     d     |
+             4|     e     C     |
+             3|     x     x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        take_actions!(game_state => c5n, c4n, f4w)
+    }
+
+    fn with_previous_piece_boards(
+        game_state: &GameState,
+        previous_piece_boards: Vec<PieceBoard>,
+    ) -> GameState {
+        let play_phase = game_state.unwrap_play_phase();
+        let piece_board = game_state.piece_board();
+        let hash = Zobrist::from_piece_board(
+            piece_board,
+            game_state.is_p1_turn_to_move(),
+            game_state.current_step(),
+        );
+
+        GameState::new(
+            game_state.is_p1_turn_to_move(),
+            game_state.move_number(),
+            Phase::PlayPhase(PlayPhase::new(
+                hash,
+                List::new(),
+                previous_piece_boards,
+                play_phase.push_pull_state(),
+                play_phase.piece_trapped_this_turn(),
+            )),
+            to_piece_board(piece_board),
+            hash,
+        )
+    }
+
+    fn assert_round_trip(game_state: &GameState) -> GameState {
+        let bytes = game_state.to_bytes().unwrap();
+        let decoded = GameState::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.to_string(), game_state.to_string());
+        assert_eq!(decoded.move_number(), game_state.move_number());
+        assert_eq!(
+            decoded.is_p1_turn_to_move(),
+            game_state.is_p1_turn_to_move()
+        );
+        assert_eq!(
+            decoded.transposition_hash(),
+            game_state.transposition_hash()
+        );
+        assert_eq!(decoded.valid_actions(), game_state.valid_actions());
+        assert_eq!(decoded.to_bytes().unwrap(), bytes);
+
+        if let Some(play_phase) = game_state.as_play_phase() {
+            let decoded_play_phase = decoded.unwrap_play_phase();
+            assert_eq!(decoded_play_phase.step(), play_phase.step());
+            assert_eq!(
+                decoded_play_phase.push_pull_state(),
+                play_phase.push_pull_state()
+            );
+            assert_eq!(
+                decoded_play_phase.piece_trapped_this_turn(),
+                play_phase.piece_trapped_this_turn()
+            );
+            assert_eq!(decoded.can_pass(false), game_state.can_pass(false));
+        }
+
+        decoded
+    }
+
+    #[test]
+    fn test_action_byte_round_trip() {
+        let actions: Vec<Action> = (0..=u8::MAX).filter_map(Action::from_byte).collect();
+
+        // Each square has four moves less the 32 off of the edges, plus the pass and placements.
+        assert_eq!(actions.len(), 64 * 4 - 32 + 1 + 6);
+
+        for action in actions {
+            assert_eq!(Action::from_byte(action.to_byte()), Some(action));
+        }
+
+        assert_eq!(action!(p).to_byte(), 0);
+        assert_eq!(action!(a8e).to_byte(), 1);
+        assert_eq!(action!(h1w).to_byte(), 255);
+        assert_eq!(Action::from_byte(action!(r).to_byte()), Some(action!(r)));
+        assert_eq!(Action::from_byte(action!(e).to_byte()), Some(action!(e)));
+        assert_eq!(Action::from_byte(7 * 4), None);
+        assert_eq!(Action::from_byte(action!(a8s).to_byte() + 1), None);
+    }
+
+    #[test]
+    fn test_to_bytes_initial_play_state() {
+        let game_state = initial_play_state();
+        let bytes = game_state.to_bytes().unwrap();
+
+        // Version, flags, move number, occupied squares and a nibble for each of the 32 pieces.
+        assert_eq!(bytes.len(), 1 + 1 + 1 + 8 + 16);
+        assert_eq!(bytes[0], ENCODING_VERSION);
+
+        assert_round_trip(&game_state);
+    }
+
+    #[test]
+    fn test_to_bytes_each_step_of_moves() {
+        let mut game_state = initial_play_state();
+
+        for action in [
+            "d2n", "d3n", "e2n", "p", "d7s", "d6s", "e7s", "e6s", "a2n", "d4w", "d1n", "b2n",
+        ] {
+            game_state = game_state.take_action(&action.parse().unwrap());
+            assert_round_trip(&game_state);
+        }
+
+        assert_eq!(game_state.move_number(), 3);
+    }
+
+    #[test]
+    fn test_to_bytes_mid_push_with_capture() {
+        let game_state: GameState = "
+             12s
+              +-----------------+
+             8|               r |
+             7|                 |
+             6|     x     x     |
+             5|     R     d     |
+             4|     e     C     |
+             3|     x     x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let game_state = take_actions!(game_state => c5n);
+        assert!(game_state.unwrap_play_phase().piece_trapped_this_turn());
+
+        let decoded = assert_round_trip(&game_state);
+        assert!(matches!(
+            decoded.unwrap_play_phase().push_pull_state(),
+            PushPullState::MustCompletePush(_, _)
+        ));
+        assert_eq!(decoded.valid_actions(), vec![action!(c4n)]);
+
+        let game_state = push_into_trap();
+        assert_eq!(game_state.current_step(), 3);
+        assert_eq!(game_state.move_number(), 12);
+        assert_round_trip(&game_state);
+    }
+
+    #[test]
+    fn test_to_bytes_steps_from_random_positions() {
+        for (i, mut game_state) in random_positions(40).into_iter().enumerate() {
+            for step in 0..3 {
+                let actions = game_state.valid_actions();
+                if actions.is_empty() {
+                    break;
+                }
+
+                let action = actions[(i * 7 + step * 3) % actions.len()];
+                if action == Action::Pass {
+                    break;
+                }
+
+                game_state = game_state.take_action(&action);
+                assert_round_trip(&game_state);
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_bytes_capture_of_unsupported_piece() {
+        let game_state: GameState = "
+             5g
+              +-----------------+
+             8|               r |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     C     x     |
+             2|     D           |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let game_state = take_actions!(game_state => c2w);
+        assert!(game_state.unwrap_play_phase().piece_trapped_this_turn());
+
+        let bytes = game_state.to_bytes().unwrap();
+        assert_eq!(bytes.last(), Some(&action!(c2w).to_byte()));
+        assert_round_trip(&game_state);
+    }
+
+    #[test]
+    fn test_to_bytes_without_previous_piece_boards() {
+        let game_state = push_into_trap();
+        let play_phase = game_state.unwrap_play_phase();
+
+        let without_piece_boards = with_previous_piece_boards(&game_state, Vec::new());
+        assert!(without_piece_boards.to_bytes().is_err());
+
+        let mut buffer = vec![0];
+        assert!(without_piece_boards.write_bytes(&mut buffer).is_err());
+        assert_eq!(buffer, vec![0]);
+
+        let mut unrelated_piece_boards = play_phase.previous_piece_boards().to_vec();
+        unrelated_piece_boards[1] = unrelated_piece_boards[0].clone();
+        assert!(
+            with_previous_piece_boards(&game_state, unrelated_piece_boards)
+                .to_bytes()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_to_bytes_setup_phase() {
+        assert_round_trip(&GameState::initial());
+        assert_round_trip(&take_actions!(GameState::initial() => e, m, h));
+        assert_round_trip(
+            &take_actions!(GameState::initial() => r, r, r, r, r, r, r, r, c, d, h, m, e, h, d, c, c),
+        );
+    }
+
+    #[test]
+    fn test_to_bytes_large_move_number() {
+        let game_state: GameState = "
+             300g
+              +-----------------+
+             8|               r |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let decoded = assert_round_trip(&game_state);
+        assert_eq!(decoded.move_number(), 300);
+        assert_eq!(game_state.to_bytes().unwrap().len(), 1 + 1 + 2 + 8 + 1);
+    }
+
+    #[test]
+    fn test_from_bytes_invalid() {
+        let bytes = push_into_trap().to_bytes().unwrap();
+
+        assert!(GameState::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(GameState::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = ENCODING_VERSION + 1;
+        assert!(GameState::from_bytes(&wrong_version).is_err());
+
+        let mut illegal_step = bytes.clone();
+        let last = illegal_step.len() - 1;
+        illegal_step[last] = action!(a1n).to_byte();
+        assert!(GameState::from_bytes(&illegal_step).is_err());
+
+        assert!(GameState::from_bytes(&[]).is_err());
+    }
+
+    fn encode_piece_board(piece_board: &PieceBoard) -> Vec<u8> {
+        let mut bytes = vec![ENCODING_VERSION, PLAY_PHASE_FLAG | P1_TURN_TO_MOVE_FLAG];
+        write_varint(2, &mut bytes);
+        write_piece_board(piece_board.piece_board(), &mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn test_from_bytes_unreachable_piece_board() {
+        let a1 = Square::new('a', 1).as_bit_board();
+        let b1 = Square::new('b', 1).as_bit_board();
+        let h8 = Square::new('h', 8).as_bit_board();
+        let c3 = Square::new('c', 3).as_bit_board();
+        let c2 = Square::new('c', 2).as_bit_board();
+
+        let valid = PieceBoard::new(a1, a1, 0, 0, 0, 0, h8);
+        assert!(GameState::from_bytes(&encode_piece_board(&valid)).is_ok());
+
+        let two_elephants = PieceBoard::new(a1 | b1, a1 | b1, 0, 0, 0, 0, h8);
+        assert!(GameState::from_bytes(&encode_piece_board(&two_elephants)).is_err());
+
+        let nine_rabbits = PieceBoard::new(0, 0, 0, 0, 0, 0, 0x1FF);
+        assert!(GameState::from_bytes(&encode_piece_board(&nine_rabbits)).is_err());
+
+        let unsupported_on_trap = PieceBoard::new(a1 | c3, a1, 0, 0, 0, c3, h8);
+        assert!(GameState::from_bytes(&encode_piece_board(&unsupported_on_trap)).is_err());
+
+        let supported_on_trap = PieceBoard::new(a1 | c3 | c2, a1, 0, 0, c2, c3, h8);
+        assert!(GameState::from_bytes(&encode_piece_board(&supported_on_trap)).is_ok());
+    }
+
+    #[test]
+    fn test_from_bytes_invalid_move_number() {
+        let bytes = initial_play_state().to_bytes().unwrap();
+        let with_move_number =
+            |move_number: &[u8]| [&bytes[..2], move_number, &bytes[3..]].concat();
+
+        assert!(GameState::from_bytes(&with_move_number(&[2])).is_ok());
+        assert!(GameState::from_bytes(&with_move_number(&[0])).is_err());
+        assert!(GameState::from_bytes(&with_move_number(&[0x82, 0x00])).is_err());
+
+        let mut too_large = vec![0xFF; 9];
+        too_large.push(0x02);
+        assert!(GameState::from_bytes(&with_move_number(&too_large)).is_err());
+
+        let mut largest = vec![0xFF; 9];
+        largest.push(0x01);
+        let mut expected = Vec::new();
+        write_varint(u64::MAX, &mut expected);
+        assert_eq!(largest, expected);
+        assert!(GameState::from_bytes(&with_move_number(&largest)).is_ok());
+    }
+
+    #[test]
+    fn test_position_reader_writer() {
+        let game_states = vec![
+            GameState::initial(),
+            initial_play_state(),
+            take_actions!(initial_play_state() => d2n, d3n),
+            push_into_trap(),
+        ];
+
+        let mut writer = PositionWriter::new(Vec::new());
+        for game_state in &game_states {
+            writer.write(game_state).unwrap();
+        }
+        let bytes = writer.into_inner();
+
+        let decoded = PositionReader::new(Cursor::new(&bytes))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(decoded.len(), game_states.len());
+        for (decoded, game_state) in decoded.iter().zip(&game_states) {
+            assert_eq!(decoded.to_string(), game_state.to_string());
+            assert_eq!(decoded.valid_actions(), game_state.valid_actions());
+        }
+
+        let mut reader = PositionReader::new(Cursor::new(&bytes[..bytes.len() - 1]));
+        assert!(reader.nth(3).unwrap().is_err());
+    }
+
+    /// Reads at most one byte at a time, failing with an interruption before each byte.
+    struct InterruptingReader<R: Read> {
+        reader: R,
+        interrupt: bool,
+    }
+
+    impl<R: Read> Read for InterruptingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+
+            let len = buf.len().min(1);
+            self.reader.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_position_reader_retries_interrupted_reads() {
+        let game_states = vec![initial_play_state(), push_into_trap()];
+
+        let mut writer = PositionWriter::new(Vec::new());
+        for game_state in &game_states {
+            writer.write(game_state).unwrap();
+        }
+
+        let reader = PositionReader::new(InterruptingReader {
+            reader: Cursor::new(writer.into_inner()),
+            interrupt: false,
+        });
+        let decoded = reader.collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(decoded.len(), game_states.len());
+        for (decoded, game_state) in decoded.iter().zip(&game_states) {
+            assert_eq!(decoded.to_string(), game_state.to_string());
+        }
+    }
+
+    #[test]
+    fn test_position_writer_rejects_states_without_previous_piece_boards() {
+        let game_state = push_into_trap();
+        let mut writer = PositionWriter::new(Vec::new());
+
+        assert!(writer
+            .write(&with_previous_piece_boards(&game_state, Vec::new()))
+            .is_err());
+        writer.write(&game_state).unwrap();
+
+        let bytes = writer.into_inner();
+        let mut reader = PositionReader::new(Cursor::new(&bytes));
+        assert_eq!(
+            reader.next().unwrap().unwrap().to_string(),
+            game_state.to_string()
+        );
+        assert!(reader.next().is_none());
+    }
+}
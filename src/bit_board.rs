@@ -0,0 +1,323 @@
+use super::constants::{BOARD_HEIGHT, BOARD_WIDTH};
+use super::engine::{influenced_squares, shift_pieces_in_direction};
+use super::{Direction, Square};
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+/// A set of squares, one bit per square.
+/// Bits are in the same order as `PieceBoardState`: the first bit is a8, the second is b8, and the last is h1.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct BitBoard(u64);
+
+impl BitBoard {
+    pub const EMPTY: BitBoard = BitBoard(0);
+    pub const FULL: BitBoard = BitBoard(u64::MAX);
+
+    pub const fn new(bits: u64) -> Self {
+        BitBoard(bits)
+    }
+
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the number of squares in the set.
+    pub const fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn contains(self, square: Square) -> bool {
+        self.0 & square.as_bit_board() != 0
+    }
+
+    /// Returns the first square in bit order, closest to a8.
+    pub fn first(self) -> Option<Square> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(Square::from_index(self.0.trailing_zeros() as u8))
+        }
+    }
+
+    /// Moves each square one step in the direction. Squares which would move off of the board are removed.
+    pub fn shift(self, direction: Direction) -> Self {
+        BitBoard(shift_pieces_in_direction(self.0, &direction))
+    }
+
+    /// Returns the squares next to any of the squares in the set, which may include squares in the set.
+    pub fn neighbors(self) -> Self {
+        BitBoard(influenced_squares(self.0))
+    }
+
+    /// Returns the squares in the set along with the squares next to them.
+    pub fn expand(self) -> Self {
+        self | self.neighbors()
+    }
+
+    /// Iterates over the squares in the set in bit order without allocating.
+    pub fn iter(self) -> BitBoardIter {
+        BitBoardIter(self.0)
+    }
+}
+
+impl From<u64> for BitBoard {
+    fn from(bits: u64) -> Self {
+        BitBoard(bits)
+    }
+}
+
+impl From<BitBoard> for u64 {
+    fn from(bit_board: BitBoard) -> Self {
+        bit_board.0
+    }
+}
+
+impl From<Square> for BitBoard {
+    fn from(square: Square) -> Self {
+        BitBoard(square.as_bit_board())
+    }
+}
+
+impl FromIterator<Square> for BitBoard {
+    fn from_iter<I: IntoIterator<Item = Square>>(squares: I) -> Self {
+        squares
+            .into_iter()
+            .fold(BitBoard::EMPTY, |bit_board, square| {
+                bit_board | BitBoard::from(square)
+            })
+    }
+}
+
+impl IntoIterator for BitBoard {
+    type Item = Square;
+    type IntoIter = BitBoardIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the squares of a `BitBoard`, created by `BitBoard::iter`.
+#[derive(Clone, Debug)]
+pub struct BitBoardIter(u64);
+
+impl Iterator for BitBoardIter {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let square = Square::from_index(self.0.trailing_zeros() as u8);
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for BitBoardIter {}
+
+impl FusedIterator for BitBoardIter {}
+
+impl BitOr for BitBoard {
+    type Output = BitBoard;
+
+    fn bitor(self, rhs: BitBoard) -> Self::Output {
+        BitBoard(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for BitBoard {
+    fn bitor_assign(&mut self, rhs: BitBoard) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for BitBoard {
+    type Output = BitBoard;
+
+    fn bitand(self, rhs: BitBoard) -> Self::Output {
+        BitBoard(self.0 & rhs.0)
+    }
+}
+
+impl BitAndAssign for BitBoard {
+    fn bitand_assign(&mut self, rhs: BitBoard) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitXor for BitBoard {
+    type Output = BitBoard;
+
+    fn bitxor(self, rhs: BitBoard) -> Self::Output {
+        BitBoard(self.0 ^ rhs.0)
+    }
+}
+
+impl BitXorAssign for BitBoard {
+    fn bitxor_assign(&mut self, rhs: BitBoard) {
+        self.0 ^= rhs.0;
+    }
+}
+
+impl Not for BitBoard {
+    type Output = BitBoard;
+
+    fn not(self) -> Self::Output {
+        BitBoard(!self.0)
+    }
+}
+
+/// Displays the set as a grid in the same layout as a board, with `*` for the squares in the set.
+impl fmt::Display for BitBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, " +-----------------+")?;
+
+        for row in 0..BOARD_HEIGHT {
+            write!(f, "{}|", BOARD_HEIGHT - row)?;
+
+            for column in 0..BOARD_WIDTH {
+                let square = Square::from_index((row * BOARD_WIDTH + column) as u8);
+                let c = if self.contains(square) { '*' } else { '.' };
+                write!(f, " {}", c)?;
+            }
+
+            writeln!(f, " |")?;
+        }
+
+        writeln!(f, " +-----------------+")?;
+        write!(f, "   a b c d e f g h")
+    }
+}
+
+impl fmt::Debug for BitBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BitBoard({:#018x})", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(square: &str) -> Square {
+        square.parse().unwrap()
+    }
+
+    fn bit_board(squares: &[&str]) -> BitBoard {
+        squares.iter().map(|s| square(s)).collect()
+    }
+
+    #[test]
+    fn test_bit_board_from_squares() {
+        let board = bit_board(&["a8", "h1", "d4"]);
+
+        assert_eq!(board.count(), 3);
+        assert!(board.contains(square("a8")));
+        assert!(board.contains(square("d4")));
+        assert!(board.contains(square("h1")));
+        assert!(!board.contains(square("a1")));
+        assert_eq!(board.bits(), 1 | (1 << 35) | (1 << 63));
+        assert_eq!(BitBoard::from(square("a8")), BitBoard::new(1));
+        assert_eq!(u64::from(board), board.bits());
+    }
+
+    #[test]
+    fn test_bit_board_iter_in_bit_order() {
+        let board = bit_board(&["h1", "c3", "a8", "e5"]);
+        let iter = board.iter();
+
+        assert_eq!(iter.len(), 4);
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![square("a8"), square("e5"), square("c3"), square("h1")]
+        );
+        assert_eq!(board.first(), Some(square("a8")));
+        assert_eq!(BitBoard::EMPTY.first(), None);
+        assert_eq!(BitBoard::EMPTY.iter().next(), None);
+        assert_eq!(BitBoard::FULL.iter().count(), 64);
+    }
+
+    #[test]
+    fn test_bit_board_operators() {
+        let a = bit_board(&["a1", "b2", "c3"]);
+        let b = bit_board(&["b2", "c3", "d4"]);
+
+        assert_eq!(a | b, bit_board(&["a1", "b2", "c3", "d4"]));
+        assert_eq!(a & b, bit_board(&["b2", "c3"]));
+        assert_eq!(a ^ b, bit_board(&["a1", "d4"]));
+        assert_eq!((!a).count(), 61);
+        assert_eq!(!BitBoard::EMPTY, BitBoard::FULL);
+
+        let mut c = a;
+        c |= b;
+        c &= !bit_board(&["c3"]);
+        c ^= bit_board(&["a1", "e5"]);
+        assert_eq!(c, bit_board(&["b2", "d4", "e5"]));
+    }
+
+    #[test]
+    fn test_bit_board_shift() {
+        let board = bit_board(&["a8", "d4", "h1"]);
+
+        assert_eq!(board.shift(Direction::Up), bit_board(&["d5", "h2"]));
+        assert_eq!(board.shift(Direction::Right), bit_board(&["b8", "e4"]));
+        assert_eq!(board.shift(Direction::Down), bit_board(&["a7", "d3"]));
+        assert_eq!(board.shift(Direction::Left), bit_board(&["c4", "g1"]));
+    }
+
+    #[test]
+    fn test_bit_board_neighbors() {
+        let board = bit_board(&["a8", "d4"]);
+
+        assert_eq!(
+            board.neighbors(),
+            bit_board(&["b8", "a7", "d5", "c4", "e4", "d3"])
+        );
+        assert_eq!(
+            board.expand(),
+            bit_board(&["a8", "b8", "a7", "d4", "d5", "c4", "e4", "d3"])
+        );
+        assert_eq!(BitBoard::EMPTY.neighbors(), BitBoard::EMPTY);
+    }
+
+    #[test]
+    fn test_bit_board_display() {
+        let board = bit_board(&["a8", "c6", "f3", "h1"]);
+
+        assert_eq!(
+            format!("{}", board),
+            " +-----------------+
+8| * . . . . . . . |
+7| . . . . . . . . |
+6| . . * . . . . . |
+5| . . . . . . . . |
+4| . . . . . . . . |
+3| . . . . . * . . |
+2| . . . . . . . . |
+1| . . . . . . . * |
+ +-----------------+
+   a b c d e f g h"
+        );
+        assert_eq!(
+            format!("{:?}", BitBoard::new(1)),
+            "BitBoard(0x0000000000000001)"
+        );
+    }
+}
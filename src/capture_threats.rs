@@ -0,0 +1,558 @@
+use super::bit_mask::TRAP_MASK;
+use super::engine::{influenced_squares, lesser_pieces, threatened_pieces};
+use super::goal_search::{MoveState, STEPS_PER_MOVE};
+use super::{
+    Action, BitBoard, Direction, GameState, Piece, PieceBoardState, PushPullState, Square,
+};
+use std::collections::HashSet;
+
+/// An opponent piece which can be captured by the player to move.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaptureThreat {
+    /// The square the piece is on before any of the actions are taken.
+    pub square: Square,
+    pub piece: Piece,
+    /// The trap the piece is captured on.
+    pub trap: Square,
+    /// The steps which capture the piece, including the step completing a push into the trap. The pass needed to end the move
+    /// when fewer than four steps were taken is not included.
+    pub actions: Vec<Action>,
+}
+
+/// Searches for the opponent pieces which can be captured in a move of at most `max_steps` steps, as if the player were to move
+/// at the start of a move on the board. Useful for checking which pieces the opponent of the player to move threatens.
+/// See `GameState::capture_threats`.
+pub fn find_capture_threats(
+    piece_board: &PieceBoardState,
+    p1_to_move: bool,
+    max_steps: usize,
+) -> Vec<CaptureThreat> {
+    let move_state = MoveState::new(piece_board.clone(), p1_to_move, PushPullState::None);
+
+    CaptureSearch::find(&move_state, max_steps.min(STEPS_PER_MOVE))
+}
+
+impl GameState {
+    /// Returns each opponent piece which the current player can capture in at most `max_steps` steps, along with the shortest sequence of steps found
+    /// which captures it. Only the steps remaining in the move from `current_step()` are available. Each sequence leaves the move in a state which
+    /// can be passed or ends the move, so the capture is never undone by an illegal move.
+    ///
+    /// Threats are ordered by the number of steps needed and then by square.
+    pub fn capture_threats(&self, max_steps: usize) -> Vec<CaptureThreat> {
+        let play_phase = match self.as_play_phase() {
+            Some(play_phase) if self.is_terminal().is_none() => play_phase,
+            _ => return Vec::new(),
+        };
+
+        let remaining_steps = max_steps.min(STEPS_PER_MOVE - play_phase.step());
+        let move_state = MoveState::new(
+            self.piece_board().clone(),
+            self.is_p1_turn_to_move(),
+            play_phase.push_pull_state(),
+        );
+
+        CaptureSearch::find(&move_state, remaining_steps)
+    }
+}
+
+/// The square each opponent piece started the search on, indexed by the square it is on now.
+type Origins = [Option<Square>; 64];
+
+/// A captured piece by its starting square, along with its type and the trap it was captured on.
+type Capture = (Square, Piece, Square);
+
+struct CaptureSearch {
+    threats: Vec<CaptureThreat>,
+    /// Which piece is on a square depends on the steps taken to reach a state, so the origins and the captures so far are part of the key.
+    visited: HashSet<(MoveState, usize, Origins, Vec<Capture>)>,
+    actions: Vec<Action>,
+    captures: Vec<Capture>,
+}
+
+impl CaptureSearch {
+    fn find(move_state: &MoveState, max_steps: usize) -> Vec<CaptureThreat> {
+        let mut origins = [None; 64];
+        for square in move_state
+            .piece_board
+            .player_piece_mask(!move_state.p1_to_move)
+        {
+            origins[square.index()] = Some(square);
+        }
+
+        let mut search = CaptureSearch {
+            threats: Vec::new(),
+            visited: HashSet::new(),
+            actions: Vec::with_capacity(max_steps),
+            captures: Vec::new(),
+        };
+
+        // Iterative deepening finds the shortest capture of each piece. A state visited with a lower depth can't be reused by the next depth.
+        for max_depth in 1..=max_steps {
+            search.visited.clear();
+            search.search(move_state, &mut origins, max_depth);
+        }
+
+        search
+            .threats
+            .sort_by_key(|threat| (threat.actions.len(), threat.square));
+        search.threats
+    }
+
+    fn search(&mut self, move_state: &MoveState, origins: &mut Origins, remaining_steps: usize) {
+        // A capture always changes the board, so the move can end as soon as no push is waiting to be completed.
+        if !self.captures.is_empty() && move_state.can_end_move() {
+            self.record_captures();
+        }
+
+        if remaining_steps == 0 {
+            return;
+        }
+
+        let has_pending_capture = self
+            .captures
+            .iter()
+            .any(|(square, _, _)| !self.is_recorded(*square));
+        if !has_pending_capture && self.capturable_pieces(move_state, origins, remaining_steps) == 0
+        {
+            return;
+        }
+
+        if !self.visited.insert((
+            move_state.clone(),
+            remaining_steps,
+            *origins,
+            self.captures.clone(),
+        )) {
+            return;
+        }
+
+        for action in move_state.steps(remaining_steps) {
+            let (square, direction, destination) = match action {
+                Action::Move(square, direction) => match destination(square, direction) {
+                    Some(destination) => (square, direction, destination),
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            let next_move_state = move_state.take_step(square, direction);
+            let prev_origins = *origins;
+            let prev_captures_len = self.captures.len();
+
+            origins[destination.index()] = origins[square.index()].take();
+
+            // An opponent piece on a trap after the step which is no longer on the board was captured.
+            let opp_pieces = next_move_state
+                .piece_board
+                .player_pieces(!move_state.p1_to_move);
+            for trap in BitBoard::new(TRAP_MASK & !opp_pieces) {
+                if let Some(origin) = origins[trap.index()].take() {
+                    let square = if trap == destination { square } else { trap };
+                    let piece = move_state
+                        .piece_board
+                        .piece_type_at_square(&square)
+                        .unwrap();
+                    self.captures.push((origin, piece, trap));
+                }
+            }
+
+            self.actions.push(action);
+
+            self.search(&next_move_state, origins, remaining_steps - 1);
+
+            self.actions.pop();
+            self.captures.truncate(prev_captures_len);
+            *origins = prev_origins;
+        }
+    }
+
+    fn record_captures(&mut self) {
+        for (square, piece, trap) in self.captures.clone() {
+            if !self.is_recorded(square) {
+                self.threats.push(CaptureThreat {
+                    square,
+                    piece,
+                    trap,
+                    actions: self.actions.clone(),
+                });
+            }
+        }
+    }
+
+    fn is_recorded(&self, square: Square) -> bool {
+        self.threats.iter().any(|threat| threat.square == square)
+    }
+
+    /// Returns the opponent pieces which are not yet known to be capturable and could be captured in the remaining steps.
+    ///
+    /// A piece is captured on a trap once it has no friendly piece next to it. Each square it is pushed or pulled towards the trap
+    /// takes two steps, as does moving away each of the other pieces next to the trap. Only pieces weaker than one of the player's
+    /// pieces can be moved, and one which no stronger piece threatens takes a step more to start moving. A pending push or pull
+    /// saves a step.
+    fn capturable_pieces(
+        &self,
+        move_state: &MoveState,
+        origins: &Origins,
+        remaining_steps: usize,
+    ) -> u64 {
+        let piece_board = &move_state.piece_board;
+        let p1_to_move = move_state.p1_to_move;
+        let opp_pieces = piece_board.player_pieces(!p1_to_move);
+        let movable_pieces = Piece::ALL
+            .iter()
+            .rev()
+            .find(|piece| !piece_board.bits_for_piece(**piece, p1_to_move).is_empty())
+            .map_or(0, |piece| lesser_pieces(*piece, piece_board) & opp_pieces);
+        let threatened_pieces = threatened_pieces(
+            piece_board.non_frozen_piece_bits(p1_to_move),
+            opp_pieces,
+            piece_board,
+        );
+        let pending_steps = usize::from(move_state.push_pull_state != PushPullState::None);
+
+        let can_capture_on_trap = |square: Square, trap: Square| {
+            let square_bit = square.as_bit_board();
+            let distance = distance(square, trap);
+            let supporters = opp_pieces & influenced_squares(trap.as_bit_board()) & !square_bit;
+            let must_move_pieces = supporters | if distance > 0 { square_bit } else { 0 };
+            let start_step = usize::from(
+                distance > 0
+                    && supporters == 0
+                    && pending_steps == 0
+                    && threatened_pieces & square_bit == 0,
+            );
+
+            must_move_pieces & !movable_pieces == 0
+                && 2 * (distance + supporters.count_ones() as usize) + start_step
+                    <= remaining_steps + pending_steps
+        };
+
+        BitBoard::new(opp_pieces)
+            .iter()
+            .filter(|square| {
+                origins[square.index()].is_some_and(|origin| !self.is_recorded(origin))
+            })
+            .filter(|square| {
+                BitBoard::new(TRAP_MASK)
+                    .iter()
+                    .any(|trap| can_capture_on_trap(*square, trap))
+            })
+            .fold(0, |mask, square| mask | square.as_bit_board())
+    }
+}
+
+/// Returns the square that a step in the direction moves to, or None if the step would leave the board.
+fn destination(square: Square, direction: Direction) -> Option<Square> {
+    BitBoard::from(square).shift(direction).first()
+}
+
+/// Returns the number of steps between the squares, moving along rows and columns.
+fn distance(from: Square, to: Square) -> usize {
+    let (from, to) = (from.index(), to.index());
+    (from / 8).abs_diff(to / 8) + (from % 8).abs_diff(to % 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::{initial_play_state, random_positions, start_of_move};
+    use super::super::{action, take_actions, GameState};
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn rabbit_next_to_trap() -> GameState {
+        "
+             2g
+              +-----------------+
+             8|               r |
+             7|                 |
+             6|     x     x     |
+             5|     r           |
+             4|     E           |
+             3|     x     x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    fn supported_cat_on_trap() -> GameState {
+        "
+             2g
+              +-----------------+
+             8|               r |
+             7|                 |
+             6|     c d E x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_capture_threats() {
+        let game_state = rabbit_next_to_trap();
+
+        assert_eq!(
+            game_state.capture_threats(4),
+            vec![CaptureThreat {
+                square: Square::new('c', 5),
+                piece: Piece::Rabbit,
+                trap: Square::new('c', 6),
+                actions: vec![action!(c5n), action!(c4n)]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_capture_threats_removes_supporter() {
+        let game_state = supported_cat_on_trap();
+        let threats = game_state.capture_threats(4);
+
+        assert_eq!(threats.len(), 2);
+        assert_eq!(threats[0].square, Square::new('c', 6));
+        assert_eq!(threats[0].piece, Piece::Cat);
+        assert_eq!(threats[0].actions.len(), 2);
+
+        // Pulling the dog off of the cat leaves it next to the other trap.
+        assert_eq!(threats[1].square, Square::new('d', 6));
+        assert_eq!(threats[1].piece, Piece::Dog);
+        assert_eq!(threats[1].trap, Square::new('f', 6));
+        assert_eq!(threats[1].actions.len(), 4);
+
+        for threat in &threats {
+            let game_state = threat
+                .actions
+                .iter()
+                .fold(game_state.clone(), |game_state, action| {
+                    game_state.take_action(action)
+                });
+            let silver_pieces = game_state.piece_board().player_piece_mask(false);
+
+            assert!(!silver_pieces.contains(threat.trap));
+            assert!(!silver_pieces.contains(threat.square));
+        }
+
+        assert_eq!(game_state.capture_threats(3).len(), 1);
+        assert_eq!(game_state.capture_threats(1), vec![]);
+    }
+
+    #[test]
+    fn test_capture_threats_uses_remaining_steps() {
+        let game_state = take_actions!(rabbit_next_to_trap() => a1n, a2n);
+        assert_eq!(game_state.capture_threats(4).len(), 1);
+
+        let game_state = take_actions!(game_state => a3n);
+        assert_eq!(game_state.capture_threats(4), vec![]);
+    }
+
+    #[test]
+    fn test_capture_threats_initial_position() {
+        assert_eq!(initial_play_state().capture_threats(4), vec![]);
+    }
+
+    #[test]
+    fn test_find_capture_threats_for_opponent() {
+        let game_state = rabbit_next_to_trap();
+
+        // Silver's rabbit is too far from gold's pieces to capture any of them.
+        assert_eq!(
+            find_capture_threats(game_state.piece_board(), false, 4),
+            vec![]
+        );
+
+        let threats = find_capture_threats(game_state.piece_board(), true, 4);
+        assert_eq!(threats, game_state.capture_threats(4));
+    }
+
+    #[test]
+    fn test_capture_threats_transposed_move_orders() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|                 |
+             7|   H r           |
+             6|     c d E x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        // Pulling away the dog and the rabbit in either order reaches the same state, where the cat is no longer supported.
+        let dog_first = take_actions!(game_state.clone() => e6n, d6e, b7w, c7w);
+        let rabbit_first = take_actions!(game_state.clone() => b7w, c7w, e6n, d6e);
+        assert_eq!(dog_first.piece_board(), rabbit_first.piece_board());
+        assert!(!dog_first
+            .piece_board()
+            .player_piece_mask(false)
+            .contains(Square::new('c', 6)));
+
+        let threats = game_state.capture_threats(4);
+        let cat_threat = threats
+            .iter()
+            .find(|threat| threat.square == Square::new('c', 6))
+            .unwrap();
+        assert_eq!(cat_threat.piece, Piece::Cat);
+        assert_eq!(cat_threat.actions.len(), 4);
+
+        assert_captures_match_valid_actions(&game_state, 4);
+        assert!(game_state
+            .capture_threats(3)
+            .iter()
+            .all(|threat| threat.square != Square::new('c', 6)));
+    }
+
+    /// Finds the fewest steps which capture each opponent piece by trying every valid action of the state, which the search on the masks
+    /// is checked against. Pieces are identified by the square they started on.
+    fn shortest_captures_by_valid_actions(
+        game_state: &GameState,
+        max_steps: usize,
+    ) -> BTreeMap<Square, usize> {
+        let mut origins = [None; 64];
+        for square in game_state
+            .piece_board()
+            .player_piece_mask(!game_state.is_p1_turn_to_move())
+        {
+            origins[square.index()] = Some(square);
+        }
+
+        let mut shortest_captures = BTreeMap::new();
+        if game_state.is_terminal().is_none() {
+            let remaining_steps = max_steps.min(STEPS_PER_MOVE - game_state.current_step());
+            add_captures_by_valid_actions(
+                game_state,
+                origins,
+                &mut Vec::new(),
+                0,
+                remaining_steps,
+                &mut shortest_captures,
+            );
+        }
+
+        shortest_captures
+    }
+
+    fn add_captures_by_valid_actions(
+        game_state: &GameState,
+        origins: Origins,
+        captures: &mut Vec<Square>,
+        steps: usize,
+        remaining_steps: usize,
+        shortest_captures: &mut BTreeMap<Square, usize>,
+    ) {
+        let move_ended = steps > 0 && game_state.current_step() == 0;
+        if move_ended || game_state.can_pass(true) {
+            for square in captures.iter() {
+                let shortest = shortest_captures.entry(*square).or_insert(steps);
+                *shortest = steps.min(*shortest);
+            }
+        }
+
+        if move_ended || remaining_steps == 0 {
+            return;
+        }
+
+        for action in game_state.valid_actions() {
+            let (square, direction) = match action {
+                Action::Move(square, direction) => (square, direction),
+                _ => continue,
+            };
+
+            let mut origins = origins;
+            let captures_len = captures.len();
+            let destination = destination(square, direction).unwrap();
+            origins[destination.index()] = origins[square.index()].take();
+            if let Some((trap, _, _)) = game_state.trapped_animal_for_action(&action) {
+                captures.extend(origins[trap.index()].take());
+            }
+
+            add_captures_by_valid_actions(
+                &game_state.take_action(&action),
+                origins,
+                captures,
+                steps + 1,
+                remaining_steps - 1,
+                shortest_captures,
+            );
+            captures.truncate(captures_len);
+        }
+    }
+
+    fn assert_captures_match_valid_actions(game_state: &GameState, max_steps: usize) -> usize {
+        let threats = game_state.capture_threats(max_steps);
+        let p1_to_move = game_state.is_p1_turn_to_move();
+
+        assert_eq!(
+            threats
+                .iter()
+                .map(|threat| (threat.square, threat.actions.len()))
+                .collect::<BTreeMap<_, _>>(),
+            shortest_captures_by_valid_actions(game_state, max_steps),
+            "{}",
+            game_state
+        );
+
+        for threat in &threats {
+            let game_state = threat
+                .actions
+                .iter()
+                .fold(game_state.clone(), |game_state, action| {
+                    game_state.take_action(action)
+                });
+            let opp_pieces = game_state.piece_board().player_piece_mask(!p1_to_move);
+
+            assert!(!opp_pieces.contains(threat.trap));
+            assert!(game_state.current_step() == 0 || game_state.can_pass(true));
+        }
+
+        threats.len()
+    }
+
+    #[test]
+    fn test_capture_threats_match_valid_actions() {
+        let mut threats = 0;
+
+        for game_state in random_positions(20) {
+            let mid_move_state = game_state
+                .valid_actions()
+                .first()
+                .map(|action| game_state.take_action(action));
+
+            for game_state in std::iter::once(game_state).chain(mid_move_state) {
+                threats += assert_captures_match_valid_actions(&game_state, 4);
+            }
+        }
+
+        assert!(threats > 5, "Only {} threats were found", threats);
+    }
+
+    #[test]
+    fn test_find_capture_threats_matches_valid_actions() {
+        for game_state in random_positions(10) {
+            let p1_to_move = !game_state.is_p1_turn_to_move();
+            let opponent_state = start_of_move(game_state.piece_board(), p1_to_move);
+
+            assert_eq!(
+                find_capture_threats(game_state.piece_board(), p1_to_move, 4),
+                opponent_state.capture_threats(4)
+            );
+            assert_captures_match_valid_actions(&opponent_state, 4);
+        }
+    }
+
+    #[test]
+    fn test_capture_threats_setup_phase() {
+        assert_eq!(GameState::initial().capture_threats(4), vec![]);
+    }
+}
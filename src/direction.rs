@@ -2,6 +2,7 @@ use anyhow::anyhow;
 use std::fmt;
 use std::str::FromStr;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
 pub enum Direction {
     Up,
@@ -17,6 +18,16 @@ impl Direction {
         Direction::Down,
         Direction::Left,
     ];
+
+    /// Returns the position of the direction in `Direction::ALL`.
+    pub const fn index(self) -> usize {
+        match self {
+            Direction::Up => 0,
+            Direction::Right => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+        }
+    }
 }
 
 impl fmt::Display for Direction {
@@ -57,3 +68,15 @@ impl FromStr for Direction {
         Err(anyhow!("Invalid value for direction"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_index_matches_all() {
+        for (index, direction) in Direction::ALL.iter().enumerate() {
+            assert_eq!(direction.index(), index);
+        }
+    }
+}
@@ -1,11 +1,11 @@
 use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
-use anyhow::{Error, Result};
+use anyhow::{anyhow, Error, Result};
 
 use super::zobrist::Zobrist;
-use super::{GameState, Phase, PieceBoard, PieceBoardState, PlayPhase};
-use super::{List, Piece, Square};
+use super::{Action, List, Piece, Square};
+use super::{GameState, Phase, PieceBoard, PieceBoardState, PlayPhase, PushPullState};
 use super::{BOARD_HEIGHT, BOARD_WIDTH};
 
 impl Display for GameState {
@@ -50,32 +50,7 @@ impl FromStr for GameState {
         let short_regex = regex::Regex::new(r#"^([gsbw]) \[(.{64})\]$"#).unwrap();
         let long_regex = regex::Regex::new(r"^\s*(\d+)([gswb])").unwrap();
 
-        let mut p1_pieces = 0;
-        let mut elephants = 0;
-        let mut camels = 0;
-        let mut horses = 0;
-        let mut dogs = 0;
-        let mut cats = 0;
-        let mut rabbits = 0;
-
-        let mut put_piece_at = |square: Square, c: char| {
-            if let Some((piece, is_p1)) = convert_char_to_piece(c) {
-                let square_bit = square.as_bit_board();
-
-                match piece {
-                    Piece::Elephant => elephants |= square_bit,
-                    Piece::Camel => camels |= square_bit,
-                    Piece::Horse => horses |= square_bit,
-                    Piece::Dog => dogs |= square_bit,
-                    Piece::Cat => cats |= square_bit,
-                    Piece::Rabbit => rabbits |= square_bit,
-                }
-
-                if is_p1 {
-                    p1_pieces |= square_bit;
-                }
-            }
-        };
+        let mut squares = Vec::with_capacity(BOARD_WIDTH * BOARD_HEIGHT);
 
         let (move_number, p1_turn_to_move) = if let Some(captures) = short_regex.captures(s) {
             let p1_turn_to_move = match captures.get(1).unwrap().as_str() {
@@ -85,8 +60,7 @@ impl FromStr for GameState {
             };
 
             for (i, c) in captures.get(2).unwrap().as_str().chars().enumerate() {
-                let square = Square::from_index(i as u8);
-                put_piece_at(square, c)
+                squares.push((Square::from_index(i as u8), c));
             }
 
             (0, p1_turn_to_move)
@@ -116,16 +90,14 @@ impl FromStr for GameState {
                     .enumerate()
                 {
                     let idx = (row_idx * BOARD_WIDTH + col_idx) as u8;
-                    let square = Square::from_index(idx);
-                    put_piece_at(square, c)
+                    squares.push((Square::from_index(idx), c));
                 }
             }
 
             (move_number, p1_turn_to_move)
         };
 
-        let piece_board =
-            PieceBoard::new(p1_pieces, elephants, camels, horses, dogs, cats, rabbits);
+        let piece_board = piece_board_from_chars(squares);
         let hash = Zobrist::from_piece_board(piece_board.piece_board(), p1_turn_to_move, 0);
         let hash_history = List::new();
         let hash_history = hash_history.append(hash);
@@ -140,6 +112,301 @@ impl FromStr for GameState {
     }
 }
 
+impl GameState {
+    /// Returns a single line notation of the state which, unlike `Display`, also captures the current step of the move.
+    /// The state can be restored exactly with `from_extended_notation`.
+    ///
+    /// The notation consists of space separated fields:
+    /// - The move number and the player to move, e.g. `14g`.
+    /// - The number of steps taken so far this move.
+    /// - The board as 64 characters in brackets, starting at a8, e.g. `[rrrrrrrr  ...]`.
+    /// - The push/pull state: `-`, `pull:Dd4` for a possible pull after the player's dog moved from d4, or `push:rd4` for a pushed rabbit that moved from d4.
+    /// - `1` if a piece has been trapped this move, otherwise `0`.
+    /// - The boards prior to each step of the move in the same format as the board, or `-` if none.
+    /// - The comma separated hexadecimal hashes of the positions used for detecting repetitions, oldest first, or `-` if none.
+    ///   Each hash is followed by `:` and the hash of the mirrored position when it is known.
+    ///
+    /// States in the setup phase are written as the move number, the player to move, `setup` and the board.
+    pub fn to_extended_notation(&self) -> String {
+        let curr_player = if self.is_p1_turn_to_move() { "g" } else { "s" };
+        if !self.is_play_phase() {
+            return format!(
+                "{}{} setup [{}]",
+                self.move_number(),
+                curr_player,
+                short_board(self.piece_board())
+            );
+        }
+
+        let play_phase = self.unwrap_play_phase();
+
+        let push_pull_state = match play_phase.push_pull_state() {
+            PushPullState::None => "-".to_string(),
+            PushPullState::PossiblePull(square, piece) => format!(
+                "pull:{}{}",
+                convert_piece_to_letter(&piece, self.is_p1_turn_to_move()),
+                square
+            ),
+            PushPullState::MustCompletePush(square, piece) => format!(
+                "push:{}{}",
+                convert_piece_to_letter(&piece, !self.is_p1_turn_to_move()),
+                square
+            ),
+        };
+
+        let previous_piece_boards = if play_phase.previous_piece_boards().is_empty() {
+            "-".to_string()
+        } else {
+            play_phase
+                .previous_piece_boards()
+                .iter()
+                .map(|piece_board| format!("[{}]", short_board(piece_board.piece_board())))
+                .collect()
+        };
+
+        let mut hash_history = play_phase
+            .hash_history()
+            .iter()
+            .map(|hash| match hash.mirrored_hash() {
+                Some(mirrored_hash) => {
+                    format!("{:016x}:{:016x}", hash.board_state_hash(), mirrored_hash)
+                }
+                None => format!("{:016x}", hash.board_state_hash()),
+            })
+            .collect::<Vec<_>>();
+        hash_history.reverse();
+        let hash_history = if hash_history.is_empty() {
+            "-".to_string()
+        } else {
+            hash_history.join(",")
+        };
+
+        format!(
+            "{}{} {} [{}] {} {} {} {}",
+            self.move_number(),
+            curr_player,
+            play_phase.step(),
+            short_board(self.piece_board()),
+            push_pull_state,
+            if play_phase.piece_trapped_this_turn() {
+                1
+            } else {
+                0
+            },
+            previous_piece_boards,
+            hash_history
+        )
+    }
+
+    /// Parses a board in the setup phase, in any of the formats accepted by `FromStr`, such as the output of `Display`.
+    /// The pieces on the board are placed in order for the player to move, so the resulting state is the same as taking each `Action::Place`.
+    /// Unlike `FromStr`, which always returns a state in the play phase, this returns an error if the board could not have been reached during the setup phase.
+    pub fn from_setup_board(s: &str) -> Result<Self> {
+        let game_state: GameState = s.parse()?;
+
+        setup_state(game_state.is_p1_turn_to_move(), game_state.piece_board())
+            .ok_or_else(|| anyhow!("Board is not a valid position in the setup phase"))
+    }
+
+    /// Parses a state written with `to_extended_notation`.
+    pub fn from_extended_notation(s: &str) -> Result<Self> {
+        let setup_regex =
+            regex::Regex::new(r"^\s*(\d+)([gswb]) setup \[([ EMHDCRemhdcr]{64})\]\s*$").unwrap();
+
+        if let Some(captures) = setup_regex.captures(s) {
+            let p1_turn_to_move = matches!(captures.get(2).unwrap().as_str(), "g" | "w");
+            let piece_board = parse_short_board(captures.get(3).unwrap().as_str());
+
+            return setup_state(p1_turn_to_move, piece_board.piece_board())
+                .ok_or_else(|| anyhow!("Board is not a valid position in the setup phase"));
+        }
+
+        let regex = regex::Regex::new(
+            r"^\s*(\d+)([gswb]) (\d) \[([ EMHDCRemhdcr]{64})\] (-|(?:pull|push):[EMHDCRemhdcr][a-h][1-8]) ([01]) (-|(?:\[[ EMHDCRemhdcr]{64}\])+) (-|[0-9a-f]{16}(?::[0-9a-f]{16})?(?:,[0-9a-f]{16}(?::[0-9a-f]{16})?)*)\s*$",
+        )
+        .unwrap();
+
+        let captures = regex
+            .captures(s)
+            .ok_or_else(|| anyhow!("Invalid extended notation '{}'", s.trim()))?;
+        let field = |i: usize| captures.get(i).unwrap().as_str();
+
+        let move_number: usize = field(1).parse()?;
+        let p1_turn_to_move = field(2) == "g" || field(2) == "w";
+        let step: usize = field(3).parse()?;
+        let piece_board = parse_short_board(field(4));
+        let piece_trapped_this_turn = field(6) == "1";
+
+        let previous_piece_boards = match field(7) {
+            "-" => vec![],
+            boards => boards
+                .as_bytes()
+                .chunks(66)
+                .map(|board| parse_short_board(std::str::from_utf8(&board[1..65]).unwrap()))
+                .collect(),
+        };
+
+        if previous_piece_boards.len() != step || step > 3 {
+            return Err(anyhow!(
+                "Step {} does not match the {} previous boards",
+                step,
+                previous_piece_boards.len()
+            ));
+        }
+
+        let push_pull_state = match field(5) {
+            "-" => PushPullState::None,
+            push_pull_state => {
+                let (kind, piece_and_square) = push_pull_state.split_once(':').unwrap();
+                let mut chars = piece_and_square.chars();
+                let (piece, is_p1) = convert_char_to_piece(chars.next().unwrap()).unwrap();
+                let square: Square = chars.as_str().parse()?;
+
+                match kind {
+                    "pull" if is_p1 == p1_turn_to_move && piece != Piece::Rabbit => {
+                        PushPullState::PossiblePull(square, piece)
+                    }
+                    "push" if is_p1 != p1_turn_to_move && piece != Piece::Elephant => {
+                        PushPullState::MustCompletePush(square, piece)
+                    }
+                    _ => return Err(anyhow!("Invalid push/pull state '{}'", push_pull_state)),
+                }
+            }
+        };
+
+        if step == 0 && (push_pull_state != PushPullState::None || piece_trapped_this_turn) {
+            return Err(anyhow!(
+                "A push/pull state or trapped piece requires at least one step"
+            ));
+        }
+
+        let hash_history = match field(8) {
+            "-" => List::new(),
+            hashes => hashes.split(',').try_fold(List::new(), |list, hash| {
+                let hash = match hash.split_once(':') {
+                    Some((hash, mirrored_hash)) => Zobrist::from_hashes(
+                        u64::from_str_radix(hash, 16)?,
+                        u64::from_str_radix(mirrored_hash, 16)?,
+                    ),
+                    None => Zobrist::from_hash(u64::from_str_radix(hash, 16)?),
+                };
+
+                Ok::<_, std::num::ParseIntError>(list.append(hash))
+            })?,
+        };
+
+        let initial_piece_board = previous_piece_boards.first().unwrap_or(&piece_board);
+        let initial_hash_of_move =
+            Zobrist::from_piece_board(initial_piece_board.piece_board(), p1_turn_to_move, 0);
+        let hash = Zobrist::from_piece_board(piece_board.piece_board(), p1_turn_to_move, step);
+
+        Ok(GameState::new(
+            p1_turn_to_move,
+            move_number,
+            Phase::PlayPhase(PlayPhase::new(
+                initial_hash_of_move,
+                hash_history,
+                previous_piece_boards,
+                push_pull_state,
+                piece_trapped_this_turn,
+            )),
+            piece_board,
+            hash,
+        ))
+    }
+}
+
+/// Reconstructs a state in the setup phase by replaying the placement of each piece on the board.
+/// Returns None if the board could not have been reached during the setup phase with the specified player to move.
+pub(crate) fn setup_state(
+    p1_turn_to_move: bool,
+    piece_board: &PieceBoardState,
+) -> Option<GameState> {
+    let mut game_state = GameState::initial();
+
+    while !game_state.is_play_phase() {
+        let placement_bit = game_state.piece_board().placement_bit().bits();
+        if piece_board.all_pieces().bits() & placement_bit == 0 {
+            break;
+        }
+
+        let square = Square::from_bit_board(placement_bit);
+        let piece = piece_board.piece_type_at_square(&square).unwrap();
+        let is_p1 = is_p1_piece(placement_bit, piece_board);
+        let action = Action::Place(piece);
+
+        if is_p1 != game_state.is_p1_turn_to_move() || game_state.check_action(&action).is_err() {
+            return None;
+        }
+
+        game_state = game_state.take_action(&action);
+    }
+
+    let is_setup_state = !game_state.is_play_phase()
+        && game_state.is_p1_turn_to_move() == p1_turn_to_move
+        && game_state.piece_board().all_pieces() == piece_board.all_pieces();
+
+    if is_setup_state {
+        Some(game_state)
+    } else {
+        None
+    }
+}
+
+fn short_board(piece_board: &PieceBoardState) -> String {
+    (0..(BOARD_WIDTH * BOARD_HEIGHT) as u8)
+        .map(|idx| {
+            let square = Square::from_index(idx);
+            piece_board.piece_type_at_square(&square).map_or_else(
+                || " ".to_string(),
+                |piece| {
+                    convert_piece_to_letter(&piece, is_p1_piece(square.as_bit_board(), piece_board))
+                },
+            )
+        })
+        .collect()
+}
+
+fn parse_short_board(board: &str) -> PieceBoard {
+    piece_board_from_chars(
+        board
+            .chars()
+            .enumerate()
+            .map(|(i, c)| (Square::from_index(i as u8), c)),
+    )
+}
+
+fn piece_board_from_chars<I: IntoIterator<Item = (Square, char)>>(squares: I) -> PieceBoard {
+    let mut p1_pieces = 0;
+    let mut elephants = 0;
+    let mut camels = 0;
+    let mut horses = 0;
+    let mut dogs = 0;
+    let mut cats = 0;
+    let mut rabbits = 0;
+
+    for (square, c) in squares {
+        if let Some((piece, is_p1)) = convert_char_to_piece(c) {
+            let square_bit = square.as_bit_board();
+
+            match piece {
+                Piece::Elephant => elephants |= square_bit,
+                Piece::Camel => camels |= square_bit,
+                Piece::Horse => horses |= square_bit,
+                Piece::Dog => dogs |= square_bit,
+                Piece::Cat => cats |= square_bit,
+                Piece::Rabbit => rabbits |= square_bit,
+            }
+
+            if is_p1 {
+                p1_pieces |= square_bit;
+            }
+        }
+    }
+
+    PieceBoard::new(p1_pieces, elephants, camels, horses, dogs, cats, rabbits)
+}
+
 pub fn convert_char_to_piece(c: char) -> Option<(Piece, bool)> {
     let is_p1 = c.is_uppercase();
 
@@ -157,8 +424,7 @@ pub fn convert_char_to_piece(c: char) -> Option<(Piece, bool)> {
 }
 
 fn is_p1_piece(square_bit: u64, piece_board: &PieceBoardState) -> bool {
-    let p1_piece_mask = piece_board.player_piece_mask(true);
-    (square_bit & p1_piece_mask) != 0
+    (square_bit & piece_board.p1_pieces().bits()) != 0
 }
 
 pub fn convert_piece_to_letter(piece: &Piece, is_p1: bool) -> String {
@@ -180,7 +446,7 @@ pub fn convert_piece_to_letter(piece: &Piece, is_p1: bool) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::super::{take_actions, GameState, Piece};
+    use super::super::{action, take_actions, GameState, Piece};
 
     fn place_major_pieces(game_state: GameState) -> GameState {
         take_actions!(game_state => h, c, d, m, e, d, c, h)
@@ -218,52 +484,52 @@ mod tests {
 
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Rabbit, true),
+            piece_board.bits_for_piece(Piece::Rabbit, true).bits(),
             0b__01011010__00000000__01011010__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Cat, true),
+            piece_board.bits_for_piece(Piece::Cat, true).bits(),
             0b__00000000__00100100__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Dog, true),
+            piece_board.bits_for_piece(Piece::Dog, true).bits(),
             0b__00000000__10000001__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Horse, true),
+            piece_board.bits_for_piece(Piece::Horse, true).bits(),
             0b__00000000__00000000__00100000__00000100__00000000__00000000__00000000__00000000
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Camel, true),
+            piece_board.bits_for_piece(Piece::Camel, true).bits(),
             0b__00000000__00000000__00000000__10000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Elephant, true),
+            piece_board.bits_for_piece(Piece::Elephant, true).bits(),
             0b__00000000__00000000__00000000__00000001__00000000__00000000__00000000__00000000
         );
 
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Rabbit, false),
+            piece_board.bits_for_piece(Piece::Rabbit, false).bits(),
             0b__00000000__00000000__00000000__00000000__00000000__01011010__00000000__01011010
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Cat, false),
+            piece_board.bits_for_piece(Piece::Cat, false).bits(),
             0b__00000000__00000000__00000000__00000000__00100000__00000000__10000000__00000000
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Dog, false),
+            piece_board.bits_for_piece(Piece::Dog, false).bits(),
             0b__00000000__00000000__00000000__00000000__10000100__00000000__00000000__00000000
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Horse, false),
+            piece_board.bits_for_piece(Piece::Horse, false).bits(),
             0b__00000000__00000000__00000000__00000000__00000001__00000000__00000100__00000000
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Camel, false),
+            piece_board.bits_for_piece(Piece::Camel, false).bits(),
             0b__00000000__00000000__00000000__00000000__00000000__00000000__00000001__00000000
         );
         assert_eq!(
-            piece_board.bits_for_piece(Piece::Elephant, false),
+            piece_board.bits_for_piece(Piece::Elephant, false).bits(),
             0b__00000000__00000000__00000000__00000000__00000000__00000000__00100000__00000000
         );
     }
@@ -395,4 +661,392 @@ mod tests {
 
         assert_eq!(new_str, orig_str);
     }
+
+    fn assert_extended_notation_round_trips(game_state: &GameState) {
+        let notation = game_state.to_extended_notation();
+        let parsed = GameState::from_extended_notation(&notation).unwrap();
+
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", game_state));
+        assert_eq!(parsed.to_extended_notation(), notation);
+    }
+
+    fn assert_extended_notation_round_trips_to_depth(game_state: &GameState, depth: usize) {
+        assert_extended_notation_round_trips(game_state);
+
+        if depth > 0 {
+            for action in game_state.valid_actions() {
+                assert_extended_notation_round_trips_to_depth(
+                    &game_state.take_action(&action),
+                    depth - 1,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_extended_notation_round_trip() {
+        let game_state: GameState = "
+             5s
+              +-----------------+
+             8| r               |
+             7|                 |
+             6|     x     x     |
+             5|       e         |
+             4|     E R         |
+             3|     x c   x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        assert_extended_notation_round_trips_to_depth(&game_state, 3);
+
+        assert_extended_notation_round_trips(&initial_play_state());
+    }
+
+    #[test]
+    fn test_extended_notation_mid_move() {
+        let game_state: GameState = "
+             5s
+              +-----------------+
+             8| r               |
+             7|                 |
+             6|     x     x     |
+             5|       e         |
+             4|     E R         |
+             3|     x c   x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        let game_state = take_actions!(game_state => d4e);
+        let notation = game_state.to_extended_notation();
+
+        assert!(notation.starts_with("5s 1 [r       "));
+        assert!(notation.contains(" push:Rd4 0 [r       "));
+
+        let parsed = GameState::from_extended_notation(&notation).unwrap();
+        assert_eq!(parsed.valid_actions(), game_state.valid_actions());
+        assert_eq!(parsed.transposition_hash(), game_state.transposition_hash());
+    }
+
+    #[test]
+    fn test_extended_notation_hash_history_without_mirrored_hash() {
+        let game_state = initial_play_state();
+        let notation = game_state.to_extended_notation();
+        let hash = game_state.transposition_hash();
+
+        assert!(notation.ends_with(&format!(
+            " {:016x}:{:016x}",
+            hash,
+            game_state
+                .unwrap_play_phase()
+                .hash_history()
+                .iter()
+                .next()
+                .unwrap()
+                .mirrored_hash()
+                .unwrap()
+        )));
+
+        // Hashes written without the mirrored hash are still accepted, but their mirrored hash is unknown.
+        let (notation, _) = notation.rsplit_once(':').unwrap();
+        let parsed = GameState::from_extended_notation(notation).unwrap();
+        let parsed_hash = parsed
+            .unwrap_play_phase()
+            .hash_history()
+            .iter()
+            .next()
+            .unwrap();
+
+        assert_eq!(parsed_hash.board_state_hash(), hash);
+        assert_eq!(parsed_hash.canonical_hash(), None);
+        assert_eq!(parsed.valid_actions(), game_state.valid_actions());
+        assert_eq!(parsed.canonical_hash(), game_state.canonical_hash());
+    }
+
+    #[test]
+    fn test_extended_notation_invalid() {
+        let board = "[r                                                              R]";
+        let hash = "0123456789abcdef";
+
+        assert!(
+            GameState::from_extended_notation(&format!("5s 0 {} - 0 - {}", board, hash)).is_ok()
+        );
+        assert!(
+            GameState::from_extended_notation(&format!("5s 1 {} - 0 - {}", board, hash)).is_err()
+        );
+        assert!(GameState::from_extended_notation(&format!(
+            "5s 0 {} pull:ra8 0 - {}",
+            board, hash
+        ))
+        .is_err());
+        assert!(GameState::from_extended_notation(&format!(
+            "5s 1 {} pull:ha8 0 {} {}",
+            board, board, hash
+        ))
+        .is_ok());
+        assert!(GameState::from_extended_notation(&format!(
+            "5s 1 {} pull:Ha8 0 {} {}",
+            board, board, hash
+        ))
+        .is_err());
+        assert!(GameState::from_extended_notation(&format!(
+            "5s 1 {} push:ea8 0 {} {}",
+            board, board, hash
+        ))
+        .is_err());
+        assert!(GameState::from_extended_notation(&format!("5s 0 {} - 0 -", board)).is_err());
+        assert!(GameState::from_extended_notation("5s").is_err());
+    }
+
+    #[test]
+    fn test_gamestate_from_setup_board() {
+        let game_state = GameState::from_setup_board(
+            "
+            1g
+            +-----------------+
+            8|                 |
+            7|                 |
+            6|     x     x     |
+            5|                 |
+            4|                 |
+            3|     x     x     |
+            2| R R R R R R R R |
+            1| H C D           |
+            +-----------------+
+               a b c d e f g h",
+        )
+        .unwrap();
+
+        let expected = take_actions!(GameState::initial() => r, r, r, r, r, r, r, r, h, c, d);
+
+        assert!(!game_state.is_play_phase());
+        assert!(game_state.is_p1_turn_to_move());
+        assert_eq!(format!("{:?}", game_state), format!("{:?}", expected));
+        assert_eq!(game_state.valid_actions(), expected.valid_actions());
+    }
+
+    #[test]
+    fn test_gamestate_from_setup_board_silver() {
+        let game_state = GameState::from_setup_board(
+            "
+            1s
+            +-----------------+
+            8| h c d m e       |
+            7|                 |
+            6|     x     x     |
+            5|                 |
+            4|                 |
+            3|     x     x     |
+            2| R R R R R R R R |
+            1| H C D M E D C H |
+            +-----------------+
+               a b c d e f g h",
+        )
+        .unwrap();
+
+        let expected = take_actions!(GameState::initial() => r, r, r, r, r, r, r, r);
+        let expected = place_major_pieces(expected);
+        let expected = take_actions!(expected => h, c, d, m, e);
+
+        assert!(!game_state.is_play_phase());
+        assert!(!game_state.is_p1_turn_to_move());
+        assert_eq!(format!("{:?}", game_state), format!("{:?}", expected));
+
+        let game_state = take_actions!(game_state => d, c, h);
+        let game_state = place_8_rabbits(game_state);
+        assert_eq!(
+            format!("{:?}", game_state),
+            format!("{:?}", initial_play_state())
+        );
+    }
+
+    #[test]
+    fn test_gamestate_from_setup_board_empty() {
+        let empty_board = "1g
+ +-----------------+
+8|                 |
+7|                 |
+6|     x     x     |
+5|                 |
+4|                 |
+3|     x     x     |
+2|                 |
+1|                 |
+ +-----------------+
+   a b c d e f g h
+";
+        let game_state = GameState::from_setup_board(empty_board).unwrap();
+        assert_eq!(
+            format!("{:?}", game_state),
+            format!("{:?}", GameState::initial())
+        );
+
+        let short_board = format!("g [{}]", " ".repeat(64));
+        let short_game_state = GameState::from_setup_board(&short_board).unwrap();
+        assert_eq!(
+            format!("{:?}", short_game_state),
+            format!("{:?}", GameState::initial())
+        );
+
+        // Parsing the same boards with FromStr still results in a position in the play phase.
+        let game_state: GameState = empty_board.parse().unwrap();
+        assert!(game_state.is_play_phase());
+        let game_state: GameState = short_board.parse().unwrap();
+        assert!(game_state.is_play_phase());
+    }
+
+    #[test]
+    fn test_gamestate_from_setup_board_not_setup_phase() {
+        // Pieces are not in the order they would have been placed.
+        assert!(GameState::from_setup_board(
+            "
+            1g
+            +-----------------+
+            8|                 |
+            7|                 |
+            6|     x     x     |
+            5|                 |
+            4|                 |
+            3|     x     x     |
+            2|   R             |
+            1|                 |
+            +-----------------+
+               a b c d e f g h"
+        )
+        .is_err());
+
+        // Gold has placed more than a single elephant.
+        assert!(GameState::from_setup_board(
+            "
+            1g
+            +-----------------+
+            8|                 |
+            7|                 |
+            6|     x     x     |
+            5|                 |
+            4|                 |
+            3|     x     x     |
+            2| E E             |
+            1|                 |
+            +-----------------+
+               a b c d e f g h"
+        )
+        .is_err());
+
+        // Silver placed a piece before gold completed the setup.
+        assert!(GameState::from_setup_board(
+            "
+            1g
+            +-----------------+
+            8| r               |
+            7|                 |
+            6|     x     x     |
+            5|                 |
+            4|                 |
+            3|     x     x     |
+            2| R               |
+            1|                 |
+            +-----------------+
+               a b c d e f g h"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_gamestate_fromstr_setup_prefix_is_play_phase() {
+        let board = "
+            1g
+            +-----------------+
+            8|                 |
+            7|                 |
+            6|     x     x     |
+            5|                 |
+            4|                 |
+            3|     x     x     |
+            2| R R R R R R R R |
+            1| H C D           |
+            +-----------------+
+               a b c d e f g h";
+
+        let game_state: GameState = board.parse().unwrap();
+        assert!(game_state.is_play_phase());
+        assert_eq!(game_state.move_number(), 1);
+        assert!(game_state
+            .valid_actions()
+            .iter()
+            .all(|action| matches!(action, super::super::Action::Move(..))));
+
+        assert!(!GameState::from_setup_board(board).unwrap().is_play_phase());
+    }
+
+    #[test]
+    fn test_gamestate_setup_phase_to_str_and_from_setup_board() {
+        let mut game_state = GameState::initial();
+
+        for action in initial_play_state_actions() {
+            let parsed = GameState::from_setup_board(&format!("{}", game_state)).unwrap();
+            assert_eq!(format!("{:?}", parsed), format!("{:?}", game_state));
+            assert_extended_notation_round_trips(&game_state);
+
+            game_state = game_state.take_action(&action);
+        }
+
+        assert!(game_state.is_play_phase());
+    }
+
+    fn initial_play_state_actions() -> Vec<super::super::Action> {
+        let rabbits = [action!(r); 8];
+        let major_pieces = [
+            action!(h),
+            action!(c),
+            action!(d),
+            action!(m),
+            action!(e),
+            action!(d),
+            action!(c),
+            action!(h),
+        ];
+
+        rabbits
+            .iter()
+            .chain(major_pieces.iter())
+            .chain(major_pieces.iter())
+            .chain(rabbits.iter())
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_extended_notation_setup_phase() {
+        let game_state = take_actions!(GameState::initial() => e, m);
+
+        assert_eq!(
+            game_state.to_extended_notation(),
+            format!("1g setup [{}EM{}]", " ".repeat(48), " ".repeat(14))
+        );
+        assert!(GameState::from_extended_notation(&format!(
+            "1g setup [{}ME{}]",
+            " ".repeat(48),
+            " ".repeat(14)
+        ))
+        .is_ok());
+        assert!(GameState::from_extended_notation(&format!(
+            "1s setup [{}EM{}]",
+            " ".repeat(48),
+            " ".repeat(14)
+        ))
+        .is_err());
+        assert!(GameState::from_extended_notation(&format!(
+            "1g setup [{}EM{}]",
+            " ".repeat(49),
+            " ".repeat(13)
+        ))
+        .is_err());
+    }
 }
@@ -1,9 +1,12 @@
 use super::bit_manip::first_set_bit;
 use super::bit_mask::*;
-use super::{map_bit_board_to_squares, Action, Direction, Piece, Square};
-use super::{List, Terminal, Zobrist};
+use super::{Action, ActionBuffer, ActionList, BitBoard, Direction};
+use super::{IllegalReason, List, Terminal, TerminalReason, Zobrist};
+use super::{Piece, Square};
 use std::hash::{Hash, Hasher};
+use std::mem;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
 pub enum PushPullState {
     ///  Either:
@@ -17,6 +20,7 @@ pub enum PushPullState {
     MustCompletePush(Square, Piece),
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug)]
 pub struct PlayPhase {
     previous_piece_boards_this_move: Vec<PieceBoard>,
@@ -26,6 +30,7 @@ pub struct PlayPhase {
     piece_trapped_this_turn: bool,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug)]
 pub enum Phase {
     PlacePhase,
@@ -38,52 +43,116 @@ pub enum Phase {
 /// - First bit is the top left square (A8).
 /// - Second bit is to the right. (B8).
 /// - Last bit being the bottom right (H1).
-#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct PieceBoardState {
+    #[deprecated(
+        since = "2.0.0",
+        note = "use the `p1_pieces()` accessor, which returns a `BitBoard`"
+    )]
     pub p1_pieces: u64,
+    #[deprecated(
+        since = "2.0.0",
+        note = "use the `all_pieces()` accessor, which returns a `BitBoard`"
+    )]
     pub all_pieces: u64,
+    #[deprecated(
+        since = "2.0.0",
+        note = "use the `elephants()` accessor, which returns a `BitBoard`"
+    )]
     pub elephants: u64,
+    #[deprecated(
+        since = "2.0.0",
+        note = "use the `camels()` accessor, which returns a `BitBoard`"
+    )]
     pub camels: u64,
+    #[deprecated(
+        since = "2.0.0",
+        note = "use the `horses()` accessor, which returns a `BitBoard`"
+    )]
     pub horses: u64,
+    #[deprecated(
+        since = "2.0.0",
+        note = "use the `dogs()` accessor, which returns a `BitBoard`"
+    )]
     pub dogs: u64,
+    #[deprecated(
+        since = "2.0.0",
+        note = "use the `cats()` accessor, which returns a `BitBoard`"
+    )]
     pub cats: u64,
+    #[deprecated(
+        since = "2.0.0",
+        note = "use the `rabbits()` accessor, which returns a `BitBoard`"
+    )]
     pub rabbits: u64,
 }
 
 impl PieceBoardState {
+    /// Returns the squares of player 1's pieces.
+    pub fn p1_pieces(&self) -> BitBoard {
+        BitBoard::new(self.p1_pieces)
+    }
+
+    /// Returns the squares of all pieces of both players.
+    pub fn all_pieces(&self) -> BitBoard {
+        BitBoard::new(self.all_pieces)
+    }
+
+    /// Returns the squares of the elephants of both players.
+    pub fn elephants(&self) -> BitBoard {
+        BitBoard::new(self.elephants)
+    }
+
+    /// Returns the squares of the camels of both players.
+    pub fn camels(&self) -> BitBoard {
+        BitBoard::new(self.camels)
+    }
+
+    /// Returns the squares of the horses of both players.
+    pub fn horses(&self) -> BitBoard {
+        BitBoard::new(self.horses)
+    }
+
+    /// Returns the squares of the dogs of both players.
+    pub fn dogs(&self) -> BitBoard {
+        BitBoard::new(self.dogs)
+    }
+
+    /// Returns the squares of the cats of both players.
+    pub fn cats(&self) -> BitBoard {
+        BitBoard::new(self.cats)
+    }
+
+    /// Returns the squares of the rabbits of both players.
+    pub fn rabbits(&self) -> BitBoard {
+        BitBoard::new(self.rabbits)
+    }
+
     /// Returns the bits for a specific piece type for a specific player.
-    pub fn bits_for_piece(&self, piece: Piece, p1_pieces: bool) -> u64 {
-        let player_piece_mask = if p1_pieces {
-            self.p1_pieces
-        } else {
-            !self.p1_pieces & self.all_pieces
-        };
-        self.bits_by_piece_type(piece) & player_piece_mask
+    pub fn bits_for_piece(&self, piece: Piece, p1_pieces: bool) -> BitBoard {
+        BitBoard::new(self.bits_by_piece_type(piece).bits() & self.player_pieces(p1_pieces))
     }
 
     /// Returns a bit board where each bit represents a current player's piece on that square
-    pub fn player_piece_mask(&self, p1_pieces: bool) -> u64 {
-        if p1_pieces {
-            self.p1_pieces
-        } else {
-            !self.p1_pieces & self.all_pieces
-        }
+    pub fn player_piece_mask(&self, p1_pieces: bool) -> BitBoard {
+        BitBoard::new(self.player_pieces(p1_pieces))
     }
 
     // Returns a bit board by piece type. This includes both gold and silver pieces of that type.
-    pub fn bits_by_piece_type(&self, piece: Piece) -> u64 {
-        match piece {
+    pub fn bits_by_piece_type(&self, piece: Piece) -> BitBoard {
+        BitBoard::new(match piece {
             Piece::Elephant => self.elephants,
             Piece::Camel => self.camels,
             Piece::Horse => self.horses,
             Piece::Dog => self.dogs,
             Piece::Cat => self.cats,
             Piece::Rabbit => self.rabbits,
-        }
+        })
     }
 
     // Returns a bit representing the square where the next piece to be placed will be. This is only relevant during the setup phase.
-    pub fn placement_bit(&self) -> u64 {
+    pub fn placement_bit(&self) -> BitBoard {
         let placement_mask = if self.p1_pieces & P1_PLACEMENT_MASK == P1_PLACEMENT_MASK {
             P2_PLACEMENT_MASK
         } else {
@@ -91,20 +160,85 @@ impl PieceBoardState {
         };
         let squares_to_place = !self.all_pieces & placement_mask;
 
-        first_set_bit(squares_to_place)
+        BitBoard::new(first_set_bit(squares_to_place))
     }
 
     // Returns a bit representing a piece that will be trapped. The piece is on a trap square and is not supported by any friendly pieces.
-    // If no pieces will be trapped, then 0.
-    pub fn trapped_piece_bits(&self) -> u64 {
+    // If no pieces will be trapped, then empty.
+    pub fn trapped_piece_bits(&self) -> BitBoard {
         let animal_is_on_trap = animal_is_on_trap(self);
 
-        if animal_is_on_trap {
+        BitBoard::new(if animal_is_on_trap {
             let unsupported_piece_bits = both_player_unsupported_piece_bits(self);
             unsupported_piece_bits & TRAP_MASK
         } else {
             0
-        }
+        })
+    }
+
+    // Returns a bit board of the specified player's pieces which are frozen. A piece is frozen when it is next to a stronger opponent piece and has no friendly piece next to it.
+    pub fn frozen_pieces(&self, p1_pieces: bool) -> BitBoard {
+        BitBoard::new(self.frozen_piece_bits(p1_pieces))
+    }
+
+    // Returns a bit board of the specified player's pieces which have a friendly piece next to them. Supported pieces can't be frozen and are not captured on a trap.
+    pub fn supported_pieces(&self, p1_pieces: bool) -> BitBoard {
+        BitBoard::new(supported_pieces(self.player_pieces(p1_pieces)))
+    }
+
+    // Returns a bit board of the opponent pieces anywhere on the board which are weaker than the piece on the specified square.
+    // These are the pieces which the piece could push, pull or freeze when next to them. If the square is empty, then empty.
+    pub fn dominated_by(&self, square: &Square) -> BitBoard {
+        BitBoard::new(match self.piece_type_at_square(square) {
+            Some(piece) => {
+                let is_p1_piece = self.p1_pieces & square.as_bit_board() != 0;
+                lesser_pieces(piece, self) & self.player_pieces(!is_p1_piece)
+            }
+            None => 0,
+        })
+    }
+
+    // Returns a bit board of the specified player's pieces which can take a step on their own. A piece is mobile when it is not frozen
+    // and has an empty square next to it, not counting the square behind a rabbit.
+    pub fn mobile_pieces(&self, p1_pieces: bool) -> BitBoard {
+        let backward_direction = if p1_pieces {
+            Direction::Down
+        } else {
+            Direction::Up
+        };
+
+        let movable_pieces = Direction::ALL.iter().fold(0, |movable_pieces, direction| {
+            let can_move = can_move_in_direction(direction, self);
+            let can_move = if *direction == backward_direction {
+                can_move & !self.rabbits
+            } else {
+                can_move
+            };
+
+            movable_pieces | can_move
+        });
+
+        BitBoard::new(self.non_frozen_piece_bits(p1_pieces) & movable_pieces)
+    }
+
+    // Returns a bit board of the opponent pieces which the specified player can push. The piece must be next to a stronger
+    // non-frozen piece of the player and have an empty square next to it to be pushed into.
+    pub fn pushable_targets(&self, p1_pieces: bool) -> BitBoard {
+        let non_frozen_pieces = self.non_frozen_piece_bits(p1_pieces);
+        let opp_piece_mask = self.player_pieces(!p1_pieces);
+        let threatened_pieces = threatened_pieces(non_frozen_pieces, opp_piece_mask, self);
+
+        BitBoard::new(threatened_pieces & influenced_squares(!self.all_pieces))
+    }
+
+    // Returns a bit board of the opponent pieces which the specified player can pull. The piece must be next to a stronger
+    // non-frozen piece of the player which has an empty square next to it to step into.
+    pub fn pullable_targets(&self, p1_pieces: bool) -> BitBoard {
+        let pulling_pieces =
+            self.non_frozen_piece_bits(p1_pieces) & influenced_squares(!self.all_pieces);
+        let opp_piece_mask = self.player_pieces(!p1_pieces);
+
+        BitBoard::new(threatened_pieces(pulling_pieces, opp_piece_mask, self))
     }
 
     // Gets the piece type at the specified square.
@@ -116,8 +250,37 @@ impl PieceBoardState {
             None
         }
     }
+
+    pub(crate) fn player_pieces(&self, p1_pieces: bool) -> u64 {
+        if p1_pieces {
+            self.p1_pieces
+        } else {
+            !self.p1_pieces & self.all_pieces
+        }
+    }
+
+    pub(crate) fn frozen_piece_bits(&self, p1_pieces: bool) -> u64 {
+        let player_piece_mask = self.player_pieces(p1_pieces);
+        let opp_piece_mask = self.player_pieces(!p1_pieces);
+        let threatened_pieces = threatened_pieces(opp_piece_mask, player_piece_mask, self);
+
+        threatened_pieces & !supported_pieces(player_piece_mask)
+    }
+
+    pub(crate) fn non_frozen_piece_bits(&self, p1_pieces: bool) -> u64 {
+        self.player_pieces(p1_pieces) & !self.frozen_piece_bits(p1_pieces)
+    }
+
+    /// Returns the board after the piece on the square steps in the direction and any pieces left unsupported on a trap are removed.
+    pub(crate) fn take_step(&self, square: &Square, direction: &Direction) -> PieceBoardState {
+        let mut piece_board_state = self.clone();
+        PieceBoard::move_piece(&mut piece_board_state, square, direction);
+        PieceBoard::remove_trapped_pieces(&mut piece_board_state);
+        piece_board_state
+    }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug)]
 pub struct PieceBoard(PieceBoardState);
 
@@ -193,7 +356,7 @@ impl PieceBoard {
     /// When called, updates the piece board to remove any currently trapped pieces.
     /// This function should be called after every action.
     fn remove_trapped_pieces(piece_board_state: &mut PieceBoardState) -> bool {
-        let trapped_animal_bits = piece_board_state.trapped_piece_bits();
+        let trapped_animal_bits = piece_board_state.trapped_piece_bits().bits();
         let animal_is_trapped = trapped_animal_bits != 0;
 
         if animal_is_trapped {
@@ -212,6 +375,35 @@ impl PieceBoard {
     }
 }
 
+/// The information required to revert an action applied in place with `GameState::apply_action`.
+#[derive(Clone, Debug)]
+pub struct UndoInfo {
+    p1_turn_to_move: bool,
+    move_number: usize,
+    piece_board: PieceBoard,
+    hash: Zobrist,
+    phase: UndoPhase,
+}
+
+#[derive(Clone, Debug)]
+enum UndoPhase {
+    PlacePhase,
+    PlayPhase {
+        push_pull_state: PushPullState,
+        initial_hash_of_move: Zobrist,
+        hash_history: List<Zobrist>,
+        piece_trapped_this_turn: bool,
+        /// Set when the action completed the move, as the piece boards of the move are cleared.
+        /// Otherwise the piece board before the action was appended and only needs to be removed.
+        previous_piece_boards_this_move: Option<Vec<PieceBoard>>,
+    },
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "crate::serialization::GameStateFields")
+)]
 #[derive(Clone, Debug)]
 pub struct GameState {
     p1_turn_to_move: bool,
@@ -224,7 +416,6 @@ pub struct GameState {
 impl Hash for GameState {
     fn hash<H: Hasher>(&self, state: &mut H) {
         state.write_u64(self.hash.board_state_hash());
-        state.finish();
     }
 }
 
@@ -250,6 +441,7 @@ impl GameState {
     }
 
     /// Creates a new game state from the given information.
+    /// The mirrored hash is recomputed from the piece board, so the hash may be created with `Zobrist::from_hash`.
     pub fn new(
         p1_turn_to_move: bool,
         move_number: usize,
@@ -257,6 +449,8 @@ impl GameState {
         piece_board: PieceBoard,
         hash: Zobrist,
     ) -> Self {
+        let hash = hash.with_mirrored_hash_from(piece_board.piece_board());
+
         GameState {
             p1_turn_to_move,
             move_number,
@@ -266,6 +460,12 @@ impl GameState {
         }
     }
 
+    /// Returns the state with the move number replaced, for positions that were parsed without a move number.
+    pub(crate) fn with_move_number(mut self, move_number: usize) -> Self {
+        self.move_number = move_number;
+        self
+    }
+
     /// Takes an action and returns a new game state.
     #[must_use = "This function does not modify the given state. You must use the resultant state."]
     pub fn take_action(&self, action: &Action) -> Self {
@@ -276,6 +476,71 @@ impl GameState {
         }
     }
 
+    /// Takes an action by modifying the state in place.
+    /// Returns the information required to revert the action with `undo_action`.
+    ///
+    /// This avoids cloning the state on every step, which is useful when searching the game tree.
+    pub fn apply_action(&mut self, action: &Action) -> UndoInfo {
+        let mut undo_info = UndoInfo {
+            p1_turn_to_move: self.p1_turn_to_move,
+            move_number: self.move_number,
+            piece_board: self.piece_board.clone(),
+            hash: self.hash,
+            phase: match &self.phase {
+                Phase::PlacePhase => UndoPhase::PlacePhase,
+                Phase::PlayPhase(play_phase) => UndoPhase::PlayPhase {
+                    push_pull_state: play_phase.push_pull_state,
+                    initial_hash_of_move: play_phase.initial_hash_of_move,
+                    hash_history: play_phase.hash_history.clone(),
+                    piece_trapped_this_turn: play_phase.piece_trapped_this_turn,
+                    previous_piece_boards_this_move: None,
+                },
+            },
+        };
+
+        match action {
+            Action::Pass => self.apply_pass(&mut undo_info),
+            Action::Place(piece) => *self = self.place(*piece),
+            Action::Move(square, direction) => {
+                self.apply_move_piece(square, direction, &mut undo_info)
+            }
+        }
+
+        undo_info
+    }
+
+    /// Reverts an action previously taken with `apply_action`.
+    /// Actions must be undone in the reverse order that they were applied.
+    pub fn undo_action(&mut self, undo_info: UndoInfo) {
+        self.p1_turn_to_move = undo_info.p1_turn_to_move;
+        self.move_number = undo_info.move_number;
+        self.piece_board = undo_info.piece_board;
+        self.hash = undo_info.hash;
+
+        match undo_info.phase {
+            UndoPhase::PlacePhase => self.phase = Phase::PlacePhase,
+            UndoPhase::PlayPhase {
+                push_pull_state,
+                initial_hash_of_move,
+                hash_history,
+                piece_trapped_this_turn,
+                previous_piece_boards_this_move,
+            } => {
+                let play_phase = self.unwrap_play_phase_mut();
+                play_phase.push_pull_state = push_pull_state;
+                play_phase.initial_hash_of_move = initial_hash_of_move;
+                play_phase.hash_history = hash_history;
+                play_phase.piece_trapped_this_turn = piece_trapped_this_turn;
+
+                if let Some(previous_piece_boards_this_move) = previous_piece_boards_this_move {
+                    play_phase.previous_piece_boards_this_move = previous_piece_boards_this_move;
+                } else {
+                    play_phase.previous_piece_boards_this_move.pop();
+                }
+            }
+        }
+    }
+
     /// Returns Some(Terminal) if the current state is terminal.
     /// Returns None if the current state is not terminal.
     ///
@@ -306,38 +571,70 @@ impl GameState {
         })
     }
 
+    /// Returns the reason the game has ended, or None if the state is not terminal.
+    /// The winner is given by `is_terminal`.
+    pub fn terminal_reason(&self) -> Option<TerminalReason> {
+        let play_phase = self.as_play_phase()?;
+        let piece_board = &self.piece_board();
+
+        if play_phase.step() == 0 {
+            if self.rabbit_at_goal(piece_board).is_some() {
+                return Some(TerminalReason::Goal);
+            }
+
+            if self.lost_all_rabbits(piece_board).is_some() {
+                return Some(TerminalReason::Elimination);
+            }
+        }
+
+        self.has_move(piece_board)?;
+
+        // The player to move has run out of moves. Distinguish between positions where every remaining action
+        // would repeat a position for the third time and positions where the pieces are unable to make progress.
+        let is_repetition = self
+            .valid_actions_no_rep()
+            .iter()
+            .any(|action| self.check_action(action) == Err(IllegalReason::ThirdTimeRepetition));
+
+        Some(if is_repetition {
+            TerminalReason::Repetition
+        } else {
+            TerminalReason::Immobilization
+        })
+    }
+
     /// Returns None if the player to move has a valid action.
     /// Returns Some(Terminal) if the player to move has no valid actions.
     ///
     /// This is equivalent to game_state.valid_actions().len() == 0 but is more performant and will short-circuit early.
     #[allow(
-        clippy::blocks_in_if_conditions,
+        clippy::blocks_in_conditions,
         clippy::if_same_then_else,
         clippy::needless_bool
     )]
     pub fn has_move(&self, piece_board: &PieceBoardState) -> Option<Terminal> {
         let has_move = if let Phase::PlayPhase(play_phase) = &self.phase {
+            let mut valid_actions = ActionList::new();
             if play_phase.push_pull_state.is_must_complete_push() {
-                let valid_actions = self.must_complete_push_actions(piece_board);
-                self.has_non_passing_like_action(valid_actions)
+                self.extend_with_must_complete_push_actions(&mut valid_actions, piece_board);
+                self.has_non_passing_like_action(&valid_actions)
             } else if self.can_pass(true) {
                 true
             } else if {
-                let mut valid_actions = Vec::new();
                 self.extend_with_valid_curr_player_piece_moves(&mut valid_actions, piece_board);
-                self.has_non_passing_like_action(valid_actions)
+                self.has_non_passing_like_action(&valid_actions)
             } {
                 true
             } else if {
-                let mut valid_actions = Vec::new();
+                valid_actions.clear();
                 self.extend_with_pull_piece_actions(&mut valid_actions, piece_board);
-                self.has_non_passing_like_action(valid_actions)
+                self.has_non_passing_like_action(&valid_actions)
             } {
                 true
             } else if {
-                let mut valid_actions = Vec::new();
+                valid_actions.clear();
                 self.extend_with_push_piece_actions(&mut valid_actions, piece_board);
-                self.has_non_passing_like_action(valid_actions)
+                self.has_non_passing_like_action(&valid_actions)
             } {
                 true
             } else {
@@ -370,6 +667,12 @@ impl GameState {
         self.valid_actions_(false)
     }
 
+    /// Writes the set of valid actions into the buffer, replacing its contents.
+    /// This is the same as `valid_actions` but reuses the buffer, and with an `ActionList` it does not allocate at all.
+    pub fn valid_actions_into<B: ActionBuffer>(&self, valid_actions: &mut B) {
+        self.valid_actions_into_(valid_actions, true)
+    }
+
     /// Returns the piece board for a specified step number.
     /// This may be useful when you are on step n but need the state from an earlier step.
     pub fn piece_board_for_step(&self, step: usize) -> &PieceBoardState {
@@ -414,12 +717,11 @@ impl GameState {
         let mut piece_board_state = self.piece_board().clone();
         if let Action::Move(square, direction) = action {
             PieceBoard::move_piece(&mut piece_board_state, square, direction);
-            let trapped_animal_bits = piece_board_state.trapped_piece_bits();
-            if trapped_animal_bits != 0 {
-                let square = Square::from_bit_board(trapped_animal_bits);
+            if let Some(square) = piece_board_state.trapped_piece_bits().first() {
                 let piece = piece_board_state.piece_type_at_square(&square).unwrap();
-                let is_p1_piece =
-                    piece_board_state.bits_for_piece(piece, true) & square.as_bit_board() != 0;
+                let is_p1_piece = piece_board_state
+                    .bits_for_piece(piece, true)
+                    .contains(square);
 
                 return Some((square, piece, is_p1_piece));
             }
@@ -435,17 +737,32 @@ impl GameState {
     /// - The current board state does not match the state at the beginning of the move.
     /// - This would not be the third time that this state has occured at the end of a move.
     pub fn can_pass(&self, check_repititions: bool) -> bool {
-        self.as_play_phase().map_or(false, |play_phase| {
-            play_phase.step() >= 1
-                && !play_phase.push_pull_state.is_must_complete_push()
-                && (!check_repititions
-                    || ((self.unwrap_play_phase().initial_hash_of_move
-                        != self.hash.exclude_step(play_phase.step()))
-                        && !hash_history_contains_hash_twice(
-                            &play_phase.hash_history,
-                            &self.hash.pass(play_phase.step()),
-                        )))
-        })
+        self.is_play_phase() && self.pass_illegal_reason(check_repititions).is_none()
+    }
+
+    /// Returns Ok if the action is valid in the current state.
+    /// Otherwise returns the reason that the action is not valid.
+    ///
+    /// This is equivalent to game_state.valid_actions().contains(action) but explains why an action was excluded.
+    pub fn check_action(&self, action: &Action) -> Result<(), IllegalReason> {
+        let illegal_reason = match (&self.phase, action) {
+            (Phase::PlacePhase, Action::Place(piece)) => {
+                let mut valid_placement = ActionList::new();
+                self.extend_with_valid_placement(&mut valid_placement);
+                if valid_placement.contains(action) {
+                    None
+                } else {
+                    Some(IllegalReason::NoPiecesRemaining(*piece))
+                }
+            }
+            (Phase::PlayPhase(_), Action::Pass) => self.pass_illegal_reason(true),
+            (Phase::PlayPhase(_), Action::Move(square, direction)) => {
+                self.move_illegal_reason(square, direction)
+            }
+            _ => Some(IllegalReason::WrongPhase),
+        };
+
+        illegal_reason.map_or(Ok(()), Err)
     }
 
     /// Returns the Zobrist hash of the current state.
@@ -458,36 +775,52 @@ impl GameState {
         }
     }
 
+    /// Returns a hash of the current state which is the same for the state mirrored left to right, such as with `mirror_horizontal`.
+    /// Like `transposition_hash`, it is maintained incrementally as actions are taken.
+    pub fn canonical_hash(&self) -> u64 {
+        let canonical_hash = match &self.phase {
+            Phase::PlayPhase(play_phase) => self
+                .hash
+                .canonical_hash_with_push_pull_state(play_phase.push_pull_state),
+            Phase::PlacePhase => self.hash.canonical_hash(),
+        };
+
+        canonical_hash
+            .expect("The mirrored hash of a state is always known, as GameState::new recomputes it")
+    }
+
     fn valid_actions_(&self, check_repititions: bool) -> Vec<Action> {
+        let mut valid_actions = Vec::with_capacity(50);
+        self.valid_actions_into_(&mut valid_actions, check_repititions);
+        valid_actions
+    }
+
+    fn valid_actions_into_<B: ActionBuffer>(&self, valid_actions: &mut B, check_repititions: bool) {
+        valid_actions.clear();
+
         if let Phase::PlayPhase(play_phase) = &self.phase {
             let piece_board = self.piece_board();
-            let mut valid_actions = if play_phase.push_pull_state.is_must_complete_push() {
-                self.must_complete_push_actions(piece_board)
+            if play_phase.push_pull_state.is_must_complete_push() {
+                self.extend_with_must_complete_push_actions(valid_actions, piece_board);
             } else {
-                let mut valid_actions = Vec::with_capacity(50);
-                self.extend_with_push_piece_actions(&mut valid_actions, piece_board);
-                self.extend_with_pull_piece_actions(&mut valid_actions, piece_board);
-                self.extend_with_valid_curr_player_piece_moves(&mut valid_actions, piece_board);
+                self.extend_with_push_piece_actions(valid_actions, piece_board);
+                self.extend_with_pull_piece_actions(valid_actions, piece_board);
+                self.extend_with_valid_curr_player_piece_moves(valid_actions, piece_board);
 
                 if self.can_pass(check_repititions) {
                     valid_actions.push(Action::Pass);
                 }
-
-                valid_actions
-            };
+            }
 
             if check_repititions {
-                self.remove_passing_like_actions(&mut valid_actions);
+                self.remove_passing_like_actions(valid_actions);
             }
-
-            valid_actions
         } else {
-            self.valid_placement()
+            self.extend_with_valid_placement(valid_actions);
         }
     }
 
-    fn valid_placement(&self) -> Vec<Action> {
-        let mut actions = Vec::with_capacity(6);
+    fn extend_with_valid_placement<B: ActionBuffer>(&self, actions: &mut B) {
         let piece_board = &self.piece_board();
         let curr_player_pieces = self.curr_player_piece_mask(piece_board);
 
@@ -509,13 +842,11 @@ impl GameState {
         if (piece_board.rabbits & curr_player_pieces).count_ones() < 8 {
             actions.push(Action::Place(Piece::Rabbit));
         }
-
-        actions
     }
 
-    fn extend_with_valid_curr_player_piece_moves(
+    fn extend_with_valid_curr_player_piece_moves<B: ActionBuffer>(
         &self,
-        valid_actions: &mut Vec<Action>,
+        valid_actions: &mut B,
         piece_board: &PieceBoardState,
     ) {
         let non_frozen_pieces = self.curr_player_non_frozen_pieces(piece_board);
@@ -526,22 +857,21 @@ impl GameState {
             let valid_curr_piece_moves =
                 unoccupied_directions & non_frozen_pieces & !invalid_rabbit_moves;
 
-            if valid_curr_piece_moves != 0 {
-                let squares = map_bit_board_to_squares(valid_curr_piece_moves);
-                valid_actions.extend(squares.into_iter().map(|s| Action::Move(s, *direction)));
+            for square in BitBoard::new(valid_curr_piece_moves) {
+                valid_actions.push(Action::Move(square, *direction));
             }
         }
     }
 
-    fn extend_with_pull_piece_actions(
+    fn extend_with_pull_piece_actions<B: ActionBuffer>(
         &self,
-        valid_actions: &mut Vec<Action>,
+        valid_actions: &mut B,
         piece_board: &PieceBoardState,
     ) {
         if let Some(play_phase) = self.as_play_phase() {
             if let Some((square, piece)) = play_phase.push_pull_state.as_possible_pull() {
                 let opp_piece_mask = self.opponent_piece_mask(piece_board);
-                let lesser_opp_pieces = self.lesser_pieces(piece, piece_board) & opp_piece_mask;
+                let lesser_opp_pieces = lesser_pieces(piece, piece_board) & opp_piece_mask;
                 let square_bit = square.as_bit_board();
 
                 for direction in Direction::ALL.iter() {
@@ -550,7 +880,7 @@ impl GameState {
                             shift_pieces_in_opp_direction(square_bit, direction),
                         );
                         let action = Action::Move(source_opp_piece_square, *direction);
-                        if !valid_actions.contains(&action) {
+                        if !valid_actions.as_slice().contains(&action) {
                             valid_actions.push(action);
                         }
                     }
@@ -559,9 +889,9 @@ impl GameState {
         }
     }
 
-    fn extend_with_push_piece_actions(
+    fn extend_with_push_piece_actions<B: ActionBuffer>(
         &self,
-        valid_actions: &mut Vec<Action>,
+        valid_actions: &mut B,
         piece_board: &PieceBoardState,
     ) {
         if let Some(play_phase) = self.as_play_phase() {
@@ -569,17 +899,15 @@ impl GameState {
                 let predator_piece_mask = self.curr_player_non_frozen_pieces(piece_board);
                 let opp_piece_mask = self.opponent_piece_mask(piece_board);
                 let opp_threatened_pieces =
-                    self.threatened_pieces(predator_piece_mask, opp_piece_mask, piece_board);
+                    threatened_pieces(predator_piece_mask, opp_piece_mask, piece_board);
 
                 if opp_threatened_pieces != 0 {
                     for direction in Direction::ALL.iter() {
                         let unoccupied_directions = can_move_in_direction(direction, piece_board);
                         let valid_push_moves = unoccupied_directions & opp_threatened_pieces;
 
-                        if valid_push_moves != 0 {
-                            let squares = map_bit_board_to_squares(valid_push_moves);
-                            valid_actions
-                                .extend(squares.into_iter().map(|s| Action::Move(s, *direction)));
+                        for square in BitBoard::new(valid_push_moves) {
+                            valid_actions.push(Action::Move(square, *direction));
                         }
                     }
                 }
@@ -587,14 +915,17 @@ impl GameState {
         }
     }
 
-    fn must_complete_push_actions(&self, piece_board: &PieceBoardState) -> Vec<Action> {
+    fn extend_with_must_complete_push_actions<B: ActionBuffer>(
+        &self,
+        valid_actions: &mut B,
+        piece_board: &PieceBoardState,
+    ) {
         let play_phase = self.unwrap_play_phase();
         let (square, pushed_piece) = play_phase.push_pull_state.unwrap_must_complete_push();
 
         let curr_player_non_frozen_piece_mask = self.curr_player_non_frozen_pieces(piece_board);
         let square_bit = square.as_bit_board();
 
-        let mut valid_actions = vec![];
         for direction in Direction::ALL.iter() {
             let pushing_piece_bit = shift_pieces_in_opp_direction(square_bit, direction)
                 & curr_player_non_frozen_piece_mask;
@@ -607,13 +938,11 @@ impl GameState {
                 ));
             }
         }
-
-        valid_actions
     }
 
     fn place(&self, piece: Piece) -> Self {
         let piece_board = &self.piece_board();
-        let placement_bit = piece_board.placement_bit();
+        let placement_bit = piece_board.placement_bit().bits();
 
         let mut new_elephants = piece_board.elephants;
         let mut new_camels = piece_board.camels;
@@ -755,6 +1084,91 @@ impl GameState {
         }
     }
 
+    fn apply_pass(&mut self, undo_info: &mut UndoInfo) {
+        let hash = self.hash.pass(self.current_step());
+        let play_phase = self.unwrap_play_phase_mut();
+        let new_hash_history = if play_phase.piece_trapped_this_turn {
+            List::new()
+        } else {
+            play_phase.hash_history.clone()
+        };
+
+        set_previous_piece_boards_for_undo(
+            undo_info,
+            mem::take(&mut play_phase.previous_piece_boards_this_move),
+        );
+        play_phase.push_pull_state = PushPullState::None;
+        play_phase.initial_hash_of_move = hash;
+        play_phase.hash_history = new_hash_history.append(hash);
+        play_phase.piece_trapped_this_turn = false;
+
+        self.move_number += if self.p1_turn_to_move { 0 } else { 1 };
+        self.p1_turn_to_move = !self.p1_turn_to_move;
+        self.hash = hash;
+    }
+
+    fn apply_move_piece(
+        &mut self,
+        square: &Square,
+        direction: &Direction,
+        undo_info: &mut UndoInfo,
+    ) {
+        let curr_step = self.current_step();
+        let is_last_step = curr_step >= 3;
+        let new_action = Action::Move(*square, *direction);
+        let (new_piece_board_state, new_animal_was_trapped) =
+            self.piece_board.take_action(&new_action);
+        let new_p1_turn_to_move = if is_last_step {
+            !self.p1_turn_to_move
+        } else {
+            self.p1_turn_to_move
+        };
+        let new_step = if is_last_step { 0 } else { curr_step + 1 };
+        let new_move_number = self.move_number
+            + if is_last_step && new_p1_turn_to_move {
+                1
+            } else {
+                0
+            };
+        let new_hash =
+            self.hash
+                .move_piece(self, &new_piece_board_state, new_step, new_p1_turn_to_move);
+        let new_push_pull_state = if is_last_step {
+            PushPullState::None
+        } else {
+            self.next_push_pull_state(square, direction)
+        };
+
+        let prev_piece_board =
+            mem::replace(&mut self.piece_board, PieceBoard(new_piece_board_state));
+        let play_phase = self.unwrap_play_phase_mut();
+
+        if new_animal_was_trapped {
+            play_phase.hash_history = List::new();
+        }
+
+        if is_last_step {
+            set_previous_piece_boards_for_undo(
+                undo_info,
+                mem::take(&mut play_phase.previous_piece_boards_this_move),
+            );
+            play_phase.hash_history = play_phase.hash_history.append(new_hash);
+            play_phase.initial_hash_of_move = new_hash;
+            play_phase.piece_trapped_this_turn = false;
+        } else {
+            play_phase
+                .previous_piece_boards_this_move
+                .push(prev_piece_board);
+            play_phase.piece_trapped_this_turn |= new_animal_was_trapped;
+        }
+
+        play_phase.push_pull_state = new_push_pull_state;
+
+        self.p1_turn_to_move = new_p1_turn_to_move;
+        self.move_number = new_move_number;
+        self.hash = new_hash;
+    }
+
     pub fn unwrap_play_phase(&self) -> &PlayPhase {
         self.as_play_phase()
             .expect("Expected phase to be PlayPhase")
@@ -767,6 +1181,13 @@ impl GameState {
         }
     }
 
+    fn unwrap_play_phase_mut(&mut self) -> &mut PlayPhase {
+        match &mut self.phase {
+            Phase::PlayPhase(play_phase) => play_phase,
+            _ => panic!("Expected phase to be PlayPhase"),
+        }
+    }
+
     fn next_piece_boards_this_move(&self) -> Vec<PieceBoard> {
         let play_phase = self.unwrap_play_phase();
         let step = play_phase.step();
@@ -830,39 +1251,11 @@ impl GameState {
         let opp_piece_mask = self.opponent_piece_mask(piece_board);
         let curr_player_piece_mask = !opp_piece_mask & piece_board.all_pieces;
         let threatened_pieces =
-            self.threatened_pieces(opp_piece_mask, curr_player_piece_mask, piece_board);
+            threatened_pieces(opp_piece_mask, curr_player_piece_mask, piece_board);
 
         curr_player_piece_mask & (!threatened_pieces | supported_pieces(curr_player_piece_mask))
     }
 
-    fn threatened_pieces(
-        &self,
-        predator_piece_mask: u64,
-        prey_piece_mask: u64,
-        piece_board: &PieceBoardState,
-    ) -> u64 {
-        let predator_elephant_influence =
-            influenced_squares(piece_board.elephants & predator_piece_mask);
-        let predator_camel_influence = influenced_squares(piece_board.camels & predator_piece_mask);
-        let predator_horse_influence = influenced_squares(piece_board.horses & predator_piece_mask);
-        let predator_dog_influence = influenced_squares(piece_board.dogs & predator_piece_mask);
-        let predator_cat_influence = influenced_squares(piece_board.cats & predator_piece_mask);
-
-        let camel_threats = predator_elephant_influence;
-        let horse_threats = camel_threats | predator_camel_influence;
-        let dog_threats = horse_threats | predator_horse_influence;
-        let cat_threats = dog_threats | predator_dog_influence;
-        let rabbit_threats = cat_threats | predator_cat_influence;
-
-        let threatened_pieces = (piece_board.camels & camel_threats)
-            | (piece_board.horses & horse_threats)
-            | (piece_board.dogs & dog_threats)
-            | (piece_board.cats & cat_threats)
-            | (piece_board.rabbits & rabbit_threats);
-
-        threatened_pieces & prey_piece_mask
-    }
-
     fn curr_player_piece_mask(&self, piece_board: &PieceBoardState) -> u64 {
         if self.p1_turn_to_move {
             piece_board.p1_pieces
@@ -951,26 +1344,7 @@ impl GameState {
         }
     }
 
-    fn lesser_pieces(&self, piece: Piece, piece_board: &PieceBoardState) -> u64 {
-        match piece {
-            Piece::Rabbit => 0,
-            Piece::Cat => piece_board.rabbits,
-            Piece::Dog => piece_board.rabbits | piece_board.cats,
-            Piece::Horse => piece_board.rabbits | piece_board.cats | piece_board.dogs,
-            Piece::Camel => {
-                piece_board.rabbits | piece_board.cats | piece_board.dogs | piece_board.horses
-            }
-            Piece::Elephant => {
-                piece_board.rabbits
-                    | piece_board.cats
-                    | piece_board.dogs
-                    | piece_board.horses
-                    | piece_board.camels
-            }
-        }
-    }
-
-    fn remove_passing_like_actions(&self, valid_actions: &mut Vec<Action>) {
+    fn remove_passing_like_actions<B: ActionBuffer>(&self, valid_actions: &mut B) {
         let play_phase = self.unwrap_play_phase();
         if play_phase.step() == 3 && !play_phase.piece_trapped_this_turn {
             valid_actions.retain(|action| !self.is_passing_like_action(action));
@@ -978,6 +1352,12 @@ impl GameState {
     }
 
     fn is_passing_like_action(&self, action: &Action) -> bool {
+        self.passing_like_action_illegal_reason(action).is_some()
+    }
+
+    /// Checks if the action on the last step of the move would result in the same position as the start of the move,
+    /// or in a third time repetition.
+    fn passing_like_action_illegal_reason(&self, action: &Action) -> Option<IllegalReason> {
         let play_phase = self.unwrap_play_phase();
         let initial_hash_of_move = play_phase.initial_hash_of_move;
         let hash_history = &play_phase.hash_history;
@@ -991,17 +1371,130 @@ impl GameState {
                 self.hash
                     .move_piece(self, new_piece_board, 0, !self.is_p1_turn_to_move());
 
-            if new_hash_no_player_switch == initial_hash_of_move
-                || hash_history_contains_hash_twice(hash_history, &new_hash_switch_players)
-            {
-                return true;
+            if new_hash_no_player_switch == initial_hash_of_move {
+                return Some(IllegalReason::SamePositionAsStartOfMove);
+            }
+
+            if hash_history_contains_hash_twice(hash_history, &new_hash_switch_players) {
+                return Some(IllegalReason::ThirdTimeRepetition);
             }
         }
 
-        false
+        None
     }
 
-    fn has_non_passing_like_action(&self, valid_actions: Vec<Action>) -> bool {
+    fn pass_illegal_reason(&self, check_repititions: bool) -> Option<IllegalReason> {
+        let play_phase = match self.as_play_phase() {
+            Some(play_phase) => play_phase,
+            None => return Some(IllegalReason::WrongPhase),
+        };
+
+        if play_phase.step() == 0 {
+            Some(IllegalReason::PassOnFirstStep)
+        } else if play_phase.push_pull_state.is_must_complete_push() {
+            Some(IllegalReason::MustCompletePush)
+        } else if !check_repititions {
+            None
+        } else if play_phase.initial_hash_of_move == self.hash.exclude_step(play_phase.step()) {
+            Some(IllegalReason::SamePositionAsStartOfMove)
+        } else if hash_history_contains_hash_twice(
+            &play_phase.hash_history,
+            &self.hash.pass(play_phase.step()),
+        ) {
+            Some(IllegalReason::ThirdTimeRepetition)
+        } else {
+            None
+        }
+    }
+
+    fn move_illegal_reason(&self, square: &Square, direction: &Direction) -> Option<IllegalReason> {
+        let play_phase = self.unwrap_play_phase();
+        let piece_board = self.piece_board();
+        let square_bit = square.as_bit_board();
+        let target_square_bit = shift_pieces_in_direction(square_bit, direction);
+
+        if square_bit & piece_board.all_pieces == 0 {
+            return Some(IllegalReason::NoPieceAtSquare);
+        }
+
+        if target_square_bit == 0 {
+            return Some(IllegalReason::OffBoard);
+        }
+
+        if target_square_bit & piece_board.all_pieces != 0 {
+            return Some(IllegalReason::SquareOccupied);
+        }
+
+        let piece = piece_type_at_bit(square_bit, piece_board);
+        let is_their_piece = self.is_their_piece(square_bit, piece_board);
+        let is_frozen = square_bit & self.curr_player_non_frozen_pieces(piece_board) == 0;
+
+        if let PushPullState::MustCompletePush(pushed_square, pushed_piece) =
+            play_phase.push_pull_state
+        {
+            if is_their_piece || target_square_bit != pushed_square.as_bit_board() {
+                return Some(IllegalReason::MustCompletePush);
+            } else if piece <= pushed_piece {
+                return Some(IllegalReason::PushRequiresStrongerPiece);
+            } else if is_frozen {
+                return Some(IllegalReason::PieceFrozen);
+            }
+        } else if !is_their_piece {
+            if is_frozen {
+                return Some(IllegalReason::PieceFrozen);
+            } else if square_bit & self.invalid_rabbit_moves(direction, piece_board) != 0 {
+                return Some(IllegalReason::RabbitMovingBackwards);
+            }
+        } else if !self.move_can_be_counted_as_pull(square_bit, direction, piece_board) {
+            let predator_piece_mask = self.curr_player_non_frozen_pieces(piece_board);
+            let opp_piece_mask = self.opponent_piece_mask(piece_board);
+            let can_be_pushed = threatened_pieces(predator_piece_mask, opp_piece_mask, piece_board)
+                & square_bit
+                != 0;
+
+            if can_be_pushed && play_phase.step() >= 3 {
+                return Some(IllegalReason::NotEnoughStepsForPush);
+            } else if !can_be_pushed {
+                return Some(
+                    if self.pull_follows_push(piece, target_square_bit, piece_board) {
+                        IllegalReason::PullNotAllowedAfterPush
+                    } else {
+                        IllegalReason::NoStrongerPieceToPushOrPull
+                    },
+                );
+            }
+        }
+
+        if play_phase.step() == 3 && !play_phase.piece_trapped_this_turn {
+            return self.passing_like_action_illegal_reason(&Action::Move(*square, *direction));
+        }
+
+        None
+    }
+
+    /// True if a stronger friendly piece moved out of the square in the previous step but could not be followed by a pull.
+    /// Steps by a friendly piece other than a rabbit only prevent a pull when they completed a push.
+    fn pull_follows_push(
+        &self,
+        their_piece: Piece,
+        target_square_bit: u64,
+        piece_board: &PieceBoardState,
+    ) -> bool {
+        let step = self.current_step();
+        if step == 0 {
+            return false;
+        }
+
+        let prev_piece_board = self.piece_board_for_step(step - 1);
+        let prev_curr_player_piece_mask = self.curr_player_piece_mask(prev_piece_board);
+        let vacated_square_bit =
+            target_square_bit & prev_curr_player_piece_mask & !piece_board.all_pieces;
+
+        vacated_square_bit != 0
+            && piece_type_at_bit(vacated_square_bit, prev_piece_board) > their_piece
+    }
+
+    fn has_non_passing_like_action(&self, valid_actions: &[Action]) -> bool {
         if valid_actions.is_empty() {
             return false;
         }
@@ -1021,7 +1514,7 @@ impl GameState {
     }
 }
 
-fn piece_type_at_bit(square_bit: u64, piece_board: &PieceBoardState) -> Piece {
+pub(crate) fn piece_type_at_bit(square_bit: u64, piece_board: &PieceBoardState) -> Piece {
     if piece_board.rabbits & square_bit != 0 {
         Piece::Rabbit
     } else if piece_board.elephants & square_bit != 0 {
@@ -1041,13 +1534,59 @@ fn animal_is_on_trap(piece_board: &PieceBoardState) -> bool {
     (piece_board.all_pieces & TRAP_MASK) != 0
 }
 
-fn influenced_squares(piece_board: u64) -> u64 {
+pub(crate) fn influenced_squares(piece_board: u64) -> u64 {
     shift_pieces_up!(piece_board)
         | shift_pieces_right!(piece_board)
         | shift_pieces_down!(piece_board)
         | shift_pieces_left!(piece_board)
 }
 
+pub(crate) fn threatened_pieces(
+    predator_piece_mask: u64,
+    prey_piece_mask: u64,
+    piece_board: &PieceBoardState,
+) -> u64 {
+    let predator_elephant_influence =
+        influenced_squares(piece_board.elephants & predator_piece_mask);
+    let predator_camel_influence = influenced_squares(piece_board.camels & predator_piece_mask);
+    let predator_horse_influence = influenced_squares(piece_board.horses & predator_piece_mask);
+    let predator_dog_influence = influenced_squares(piece_board.dogs & predator_piece_mask);
+    let predator_cat_influence = influenced_squares(piece_board.cats & predator_piece_mask);
+
+    let camel_threats = predator_elephant_influence;
+    let horse_threats = camel_threats | predator_camel_influence;
+    let dog_threats = horse_threats | predator_horse_influence;
+    let cat_threats = dog_threats | predator_dog_influence;
+    let rabbit_threats = cat_threats | predator_cat_influence;
+
+    let threatened_pieces = (piece_board.camels & camel_threats)
+        | (piece_board.horses & horse_threats)
+        | (piece_board.dogs & dog_threats)
+        | (piece_board.cats & cat_threats)
+        | (piece_board.rabbits & rabbit_threats);
+
+    threatened_pieces & prey_piece_mask
+}
+
+pub(crate) fn lesser_pieces(piece: Piece, piece_board: &PieceBoardState) -> u64 {
+    match piece {
+        Piece::Rabbit => 0,
+        Piece::Cat => piece_board.rabbits,
+        Piece::Dog => piece_board.rabbits | piece_board.cats,
+        Piece::Horse => piece_board.rabbits | piece_board.cats | piece_board.dogs,
+        Piece::Camel => {
+            piece_board.rabbits | piece_board.cats | piece_board.dogs | piece_board.horses
+        }
+        Piece::Elephant => {
+            piece_board.rabbits
+                | piece_board.cats
+                | piece_board.dogs
+                | piece_board.horses
+                | piece_board.camels
+        }
+    }
+}
+
 fn both_player_unsupported_piece_bits(piece_board: &PieceBoardState) -> u64 {
     piece_board.all_pieces & !both_player_supported_pieces(piece_board)
 }
@@ -1059,7 +1598,7 @@ fn both_player_supported_pieces(piece_board: &PieceBoardState) -> u64 {
     supported_pieces(p1_pieces) | supported_pieces(p2_pieces)
 }
 
-fn supported_pieces(piece_bits: u64) -> u64 {
+pub(crate) fn supported_pieces(piece_bits: u64) -> u64 {
     let up_supported_pieces = piece_bits & shift_pieces_up!(piece_bits);
     let right_supported_pieces = piece_bits & shift_pieces_right!(piece_bits);
     let down_supported_pieces = piece_bits & shift_pieces_down!(piece_bits);
@@ -1068,7 +1607,7 @@ fn supported_pieces(piece_bits: u64) -> u64 {
     up_supported_pieces | right_supported_pieces | down_supported_pieces | left_supported_pieces
 }
 
-fn can_move_in_direction(direction: &Direction, piece_board: &PieceBoardState) -> u64 {
+pub(crate) fn can_move_in_direction(direction: &Direction, piece_board: &PieceBoardState) -> u64 {
     let empty_squares = !piece_board.all_pieces;
     shift_pieces_in_opp_direction(empty_squares, direction)
 }
@@ -1082,7 +1621,7 @@ fn shift_piece_in_direction(
         | piece_board & !source_square_bit
 }
 
-fn shift_pieces_in_opp_direction(bits: u64, direction: &Direction) -> u64 {
+pub(crate) fn shift_pieces_in_opp_direction(bits: u64, direction: &Direction) -> u64 {
     match direction {
         Direction::Up => shift_pieces_down!(bits),
         Direction::Right => shift_pieces_left!(bits),
@@ -1091,7 +1630,7 @@ fn shift_pieces_in_opp_direction(bits: u64, direction: &Direction) -> u64 {
     }
 }
 
-fn shift_pieces_in_direction(bits: u64, direction: &Direction) -> u64 {
+pub(crate) fn shift_pieces_in_direction(bits: u64, direction: &Direction) -> u64 {
     match direction {
         Direction::Up => shift_pieces_up!(bits),
         Direction::Right => shift_pieces_right!(bits),
@@ -1109,6 +1648,16 @@ fn shift_in_direction(bits: u64, direction: &Direction) -> u64 {
     }
 }
 
+fn set_previous_piece_boards_for_undo(undo_info: &mut UndoInfo, piece_boards: Vec<PieceBoard>) {
+    if let UndoPhase::PlayPhase {
+        previous_piece_boards_this_move,
+        ..
+    } = &mut undo_info.phase
+    {
+        *previous_piece_boards_this_move = Some(piece_boards);
+    }
+}
+
 fn hash_history_contains_hash_twice(hash_history: &List<Zobrist>, hash: &Zobrist) -> bool {
     hash_history.iter().filter(|h| *h == hash).count() >= 2
 }
@@ -1184,4 +1733,8 @@ impl PlayPhase {
     pub fn hash_history(&self) -> &List<Zobrist> {
         &self.hash_history
     }
+
+    pub(crate) fn initial_hash_of_move(&self) -> Zobrist {
+        self.initial_hash_of_move
+    }
 }
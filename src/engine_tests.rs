@@ -1,7 +1,10 @@
 #[cfg(test)]
 mod tests {
-    use super::super::{take_actions, PushPullState, Terminal};
-    use super::super::{GameState, Piece, Square};
+    use super::super::{
+        action, take_actions, Action, Direction, IllegalReason, PushPullState, Terminal,
+        TerminalReason,
+    };
+    use super::super::{ActionList, BitBoard, GameState, Piece, PieceBoardState, Square, Zobrist};
     use std::str::FromStr;
 
     fn place_major_pieces(game_state: GameState) -> GameState {
@@ -44,31 +47,31 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.cats,
+            piece_board.cats().bits(),
             0b__01000010__00000000__00000000__00000000__00000000__00000000__00000000__01000010
         );
         assert_eq!(
-            piece_board.dogs,
+            piece_board.dogs().bits(),
             0b__00100100__00000000__00000000__00000000__00000000__00000000__00000000__00100100
         );
         assert_eq!(
-            piece_board.horses,
+            piece_board.horses().bits(),
             0b__10000001__00000000__00000000__00000000__00000000__00000000__00000000__10000001
         );
         assert_eq!(
-            piece_board.camels,
+            piece_board.camels().bits(),
             0b__00001000__00000000__00000000__00000000__00000000__00000000__00000000__00001000
         );
         assert_eq!(
-            piece_board.elephants,
+            piece_board.elephants().bits(),
             0b__00010000__00000000__00000000__00000000__00000000__00000000__00000000__00010000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
 
@@ -87,11 +90,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111110__00000001__00000000__00000000__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111110__00000001__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 1);
@@ -100,11 +103,11 @@ mod tests {
         let game_state = take_actions!(game_state => a3n);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111110__00000000__00000001__00000000__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111110__00000000__00000001__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 2);
@@ -113,11 +116,11 @@ mod tests {
         let game_state = take_actions!(game_state => a4n);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111110__00000000__00000000__00000001__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111110__00000000__00000000__00000001__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 3);
@@ -126,11 +129,11 @@ mod tests {
         let game_state = take_actions!(game_state => b2n);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111100__00000010__00000000__00000001__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111100__00000010__00000000__00000001__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 0);
@@ -144,11 +147,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__00001000__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 1);
@@ -157,11 +160,11 @@ mod tests {
         let game_state = take_actions!(game_state => d6s);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00001000__00000000__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 2);
@@ -170,11 +173,11 @@ mod tests {
         let game_state = take_actions!(game_state => d5s);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00001000__00000000__00000000__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 3);
@@ -183,11 +186,11 @@ mod tests {
         let game_state = take_actions!(game_state => d4s);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00001000__00000000__00000000__00000000__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 0);
@@ -201,11 +204,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__00001000__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 1);
@@ -214,11 +217,11 @@ mod tests {
         let game_state = take_actions!(game_state => d6w);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__00000100__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 2);
@@ -227,11 +230,11 @@ mod tests {
         let game_state = take_actions!(game_state => c6w);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__00000010__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 3);
@@ -240,11 +243,11 @@ mod tests {
         let game_state = take_actions!(game_state => b6w);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__00000001__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 0);
@@ -258,11 +261,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__00001000__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 1);
@@ -271,11 +274,11 @@ mod tests {
         let game_state = take_actions!(game_state => d6e);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__00010000__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 2);
@@ -284,11 +287,11 @@ mod tests {
         let game_state = take_actions!(game_state => e6e);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__00100000__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 3);
@@ -297,11 +300,11 @@ mod tests {
         let game_state = take_actions!(game_state => f6e);
         let piece_board = game_state.piece_board();
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111111__00000000__00000000__00000000__01000000__11110111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111111__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 0);
@@ -315,11 +318,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111011__00000000__00000000__00000000__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111011__00000000__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 1);
@@ -334,11 +337,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111001__00000110__00000000__00000000__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111001__00000110__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 2);
@@ -353,11 +356,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11110011__00001100__00000000__00000000__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11110011__00001100__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 2);
@@ -372,11 +375,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111101__00000100__00000000__00000000__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111101__00000100__00000000__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 2);
@@ -393,11 +396,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111001__00000100__00000100__00000000__00000000__11111111__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111001__00000100__00000100__00000000__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 0);
@@ -416,11 +419,11 @@ mod tests {
         let piece_board = game_state.piece_board();
 
         assert_eq!(
-            piece_board.rabbits,
+            piece_board.rabbits().bits(),
             0b__00000000__11111001__00000010__00000000__00000100__00000000__11111011__00000000
         );
         assert_eq!(
-            piece_board.p1_pieces,
+            piece_board.p1_pieces().bits(),
             0b__11111111__11111001__00000010__00000000__00000100__00000000__00000000__00000000
         );
         assert_eq!(game_state.unwrap_play_phase().step(), 1);
@@ -1662,6 +1665,66 @@ mod tests {
         );
     }
 
+    fn assert_valid_actions_into(game_state: &GameState, action_list: &mut ActionList) {
+        let mut valid_actions = vec![Action::Pass];
+
+        game_state.valid_actions_into(action_list);
+        game_state.valid_actions_into(&mut valid_actions);
+
+        assert_eq!(&action_list[..], &game_state.valid_actions()[..]);
+        assert_eq!(valid_actions, game_state.valid_actions());
+    }
+
+    #[test]
+    fn test_valid_actions_into() {
+        let mut action_list = ActionList::new();
+        let mut game_state = GameState::initial();
+
+        for i in 0..400 {
+            if game_state.is_terminal().is_some() {
+                break;
+            }
+
+            assert_valid_actions_into(&game_state, &mut action_list);
+
+            let action = action_list[(i * 7) % action_list.len()];
+            game_state = game_state.take_action(&action);
+        }
+    }
+
+    #[test]
+    fn test_valid_actions_into_push_pull() {
+        let mut action_list = ActionList::new();
+        let game_state: GameState = "
+              +-----------------+
+             8|   r   r r   r   |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|     E r         |
+             3|     x     x     |
+             2|                 |
+             1|   R   R R   R   |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        assert_valid_actions_into(&game_state, &mut action_list);
+        assert!(action_list.contains(&action!(d4e)));
+
+        let pull_state = take_actions!(game_state.clone() => c4n);
+        assert_valid_actions_into(&pull_state, &mut action_list);
+        assert!(action_list.contains(&action!(d4w)));
+
+        let push_state = take_actions!(game_state => d4e);
+        assert_eq!(
+            push_state.unwrap_play_phase().push_pull_state(),
+            PushPullState::MustCompletePush(Square::new('d', 4), Piece::Rabbit)
+        );
+        assert_valid_actions_into(&push_state, &mut action_list);
+        assert_eq!(&action_list[..], &[action!(c4e)]);
+    }
+
     #[test]
     fn test_valid_actions_2() {
         let game_state: GameState = "
@@ -2603,4 +2666,831 @@ mod tests {
             game_state_final.transposition_hash()
         );
     }
+
+    fn assert_states_identical(game_state: &GameState, expected: &GameState) {
+        assert_eq!(format!("{:?}", game_state), format!("{:?}", expected));
+        assert_eq!(
+            game_state.transposition_hash(),
+            expected.transposition_hash()
+        );
+        assert_eq!(game_state.is_terminal(), expected.is_terminal());
+    }
+
+    fn assert_round_trip_identical(game_state: &GameState, original: &GameState) {
+        fn hashes(game_state: &GameState) -> Vec<Zobrist> {
+            game_state
+                .unwrap_play_phase()
+                .hash_history()
+                .iter()
+                .copied()
+                .collect()
+        }
+
+        fn previous_piece_boards(game_state: &GameState) -> Vec<PieceBoardState> {
+            game_state
+                .unwrap_play_phase()
+                .previous_piece_boards()
+                .iter()
+                .map(|piece_board| piece_board.piece_board().clone())
+                .collect()
+        }
+
+        assert_states_identical(game_state, original);
+        assert_eq!(game_state.canonical_hash(), original.canonical_hash());
+        assert_eq!(game_state.piece_board(), original.piece_board());
+        assert_eq!(game_state.move_number(), original.move_number());
+        assert_eq!(
+            game_state.is_p1_turn_to_move(),
+            original.is_p1_turn_to_move()
+        );
+        assert_eq!(game_state.is_play_phase(), original.is_play_phase());
+
+        if original.is_play_phase() {
+            let play_phase = game_state.unwrap_play_phase();
+            let original_play_phase = original.unwrap_play_phase();
+            assert_eq!(
+                play_phase.push_pull_state(),
+                original_play_phase.push_pull_state()
+            );
+            assert_eq!(
+                play_phase.piece_trapped_this_turn(),
+                original_play_phase.piece_trapped_this_turn()
+            );
+            assert_eq!(hashes(game_state), hashes(original));
+            assert_eq!(
+                previous_piece_boards(game_state),
+                previous_piece_boards(original)
+            );
+        }
+    }
+
+    /// Applies and then undoes every action, including the passing like actions that are excluded on the last step,
+    /// and checks that the state is restored exactly.
+    fn assert_apply_undo_round_trip(game_state: &GameState, depth: usize) {
+        if depth == 0 || game_state.is_terminal().is_some() {
+            return;
+        }
+
+        let mut applied_game_state = game_state.clone();
+        for action in game_state.valid_actions_no_rep() {
+            let undo_info = applied_game_state.apply_action(&action);
+            assert_round_trip_identical(&applied_game_state, &game_state.take_action(&action));
+
+            assert_apply_undo_round_trip(&applied_game_state, depth - 1);
+
+            applied_game_state.undo_action(undo_info);
+            assert_round_trip_identical(&applied_game_state, game_state);
+        }
+    }
+
+    fn assert_apply_action_matches_take_action(game_state: &GameState, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+
+        let mut applied_game_state = game_state.clone();
+        for action in game_state.valid_actions() {
+            let expected = game_state.take_action(&action);
+            let undo_info = applied_game_state.apply_action(&action);
+            assert_states_identical(&applied_game_state, &expected);
+
+            assert_apply_action_matches_take_action(&applied_game_state, depth - 1);
+
+            applied_game_state.undo_action(undo_info);
+            assert_states_identical(&applied_game_state, game_state);
+        }
+    }
+
+    #[test]
+    fn test_apply_action_placing_pieces() {
+        let actions = ["r"; 8]
+            .iter()
+            .chain(["h", "c", "d", "m", "e", "d", "c", "h"].iter())
+            .chain(["h", "c", "d", "m", "e", "d", "c", "h"].iter())
+            .chain(["r"; 8].iter())
+            .map(|a| a.parse().unwrap())
+            .collect::<Vec<Action>>();
+
+        let mut game_state = GameState::initial();
+        let mut expected = GameState::initial();
+        let mut undo_infos = vec![];
+        let mut expected_states = vec![];
+
+        for action in actions.iter() {
+            expected_states.push(expected.clone());
+            expected = expected.take_action(action);
+            undo_infos.push(game_state.apply_action(action));
+            assert_states_identical(&game_state, &expected);
+        }
+
+        assert!(game_state.is_play_phase());
+        assert_states_identical(&game_state, &initial_play_state());
+
+        while let Some(undo_info) = undo_infos.pop() {
+            game_state.undo_action(undo_info);
+            assert_states_identical(&game_state, &expected_states.pop().unwrap());
+        }
+
+        assert!(!game_state.is_play_phase());
+    }
+
+    fn push_and_pull_state() -> GameState {
+        "
+             1s
+              +-----------------+
+             8| r               |
+             7|                 |
+             6|     x     x     |
+             5|       e         |
+             4|     E R         |
+             3|     x c   x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    fn trapped_pieces_state() -> GameState {
+        "
+             1g
+              +-----------------+
+             8|                 |
+             7|                 |
+             6|     x     x     |
+             5|       e         |
+             4|     R M         |
+             3|     x C   x     |
+             2|     r     E     |
+             1|               R |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    const REPEATED_POSITIONS_ACTIONS: [&str; 18] = [
+        "d3n", "p", "h7s", "p", "d4s", "p", "h6n", "p", "d3n", "p", "h7s", "p", "d4s", "d3s", "p",
+        "h6n", "p", "d2n",
+    ];
+
+    fn repeated_positions_state() -> GameState {
+        "
+             1g
+              +-----------------+
+             8|               r |
+             7|               d |
+             6|     x     x     |
+             5|       m         |
+             4|                 |
+             3|     x E   x     |
+             2|                 |
+             1|               R |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_apply_undo_round_trip() {
+        let positions = [
+            (initial_play_state(), 2),
+            (push_and_pull_state(), 4),
+            (trapped_pieces_state(), 4),
+            (repeated_positions_state(), 4),
+            (
+                REPEATED_POSITIONS_ACTIONS
+                    .iter()
+                    .fold(repeated_positions_state(), |game_state, action| {
+                        game_state.take_action(&action.parse().unwrap())
+                    }),
+                3,
+            ),
+            (analysis_state(), 2),
+        ];
+
+        for (game_state, depth) in positions.iter() {
+            assert_apply_undo_round_trip(game_state, *depth);
+        }
+    }
+
+    #[test]
+    fn test_apply_undo_round_trip_last_step() {
+        // The last step of a move with a passing like action back to the start of the move.
+        let game_state = take_actions!(trapped_pieces_state() => f2e, g2n, g3s);
+        assert_eq!(game_state.current_step(), 3);
+        assert!(game_state.valid_actions_no_rep().contains(&action!(g2w)));
+        assert!(!game_state.valid_actions().contains(&action!(g2w)));
+        assert_apply_undo_round_trip(&game_state, 2);
+
+        // The last step of a move after a piece was trapped.
+        let game_state = take_actions!(trapped_pieces_state() => c4n, d3w, f2n);
+        assert_eq!(game_state.current_step(), 3);
+        assert!(game_state.unwrap_play_phase().piece_trapped_this_turn());
+        assert_apply_undo_round_trip(&game_state, 2);
+    }
+
+    #[test]
+    fn test_apply_action_initial_play_state() {
+        assert_apply_action_matches_take_action(&initial_play_state(), 2);
+    }
+
+    #[test]
+    fn test_apply_action_push_and_pull() {
+        let game_state = push_and_pull_state();
+
+        assert_apply_action_matches_take_action(&game_state, 4);
+    }
+
+    #[test]
+    fn test_apply_action_trapped_pieces() {
+        let game_state = trapped_pieces_state();
+
+        assert_apply_action_matches_take_action(&game_state, 4);
+    }
+
+    #[test]
+    fn test_apply_action_repeated_positions() {
+        let game_state = repeated_positions_state();
+
+        let mut applied_game_state = game_state.clone();
+        let mut game_state = game_state;
+        for action in REPEATED_POSITIONS_ACTIONS.iter() {
+            let action: Action = action.parse().unwrap();
+            game_state = game_state.take_action(&action);
+            let _ = applied_game_state.apply_action(&action);
+            assert_states_identical(&applied_game_state, &game_state);
+        }
+
+        assert_apply_action_matches_take_action(&game_state, 3);
+    }
+
+    fn all_candidate_actions() -> Vec<Action> {
+        let mut actions = vec![Action::Pass];
+        actions.extend(Piece::ALL.iter().map(|p| Action::Place(*p)));
+        for index in 0..64 {
+            for direction in Direction::ALL.iter() {
+                actions.push(Action::Move(Square::from_index(index), *direction));
+            }
+        }
+
+        actions
+    }
+
+    fn assert_check_action_matches_valid_actions(game_state: &GameState, depth: usize) {
+        let valid_actions = game_state.valid_actions();
+        for action in all_candidate_actions() {
+            assert_eq!(
+                game_state.check_action(&action).is_ok(),
+                valid_actions.contains(&action),
+                "{:?} {:?}\n{}",
+                action,
+                game_state.check_action(&action),
+                game_state
+            );
+        }
+
+        if depth > 0 {
+            for action in valid_actions {
+                assert_check_action_matches_valid_actions(
+                    &game_state.take_action(&action),
+                    depth - 1,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_action_matches_valid_actions() {
+        let game_states = [
+            "
+             1s
+              +-----------------+
+             8| r               |
+             7|                 |
+             6|     x     x     |
+             5|       e         |
+             4|     E R         |
+             3|     x c   x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h",
+            "
+             1g
+              +-----------------+
+             8|                 |
+             7|                 |
+             6|     x     x     |
+             5|       e         |
+             4|     R M         |
+             3|     x C   x     |
+             2|     r     E     |
+             1|               R |
+              +-----------------+
+                a b c d e f g h",
+            "
+             1g
+              +-----------------+
+             8|                 |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|       E c H     |
+             3|     x     x     |
+             2|                 |
+             1|   e             |
+              +-----------------+
+                a b c d e f g h",
+        ];
+
+        for game_state in game_states.iter() {
+            let game_state: GameState = game_state.parse().unwrap();
+            assert_check_action_matches_valid_actions(&game_state, 3);
+        }
+
+        assert_check_action_matches_valid_actions(&initial_play_state(), 1);
+    }
+
+    #[test]
+    fn test_check_action_place_phase() {
+        let game_state = GameState::initial();
+        assert_eq!(game_state.check_action(&action!(e)), Ok(()));
+        assert_eq!(
+            game_state.check_action(&action!(a2n)),
+            Err(IllegalReason::WrongPhase)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(p)),
+            Err(IllegalReason::WrongPhase)
+        );
+
+        let game_state = take_actions!(game_state => e);
+        assert_eq!(
+            game_state.check_action(&action!(e)),
+            Err(IllegalReason::NoPiecesRemaining(Piece::Elephant))
+        );
+
+        assert_eq!(
+            initial_play_state().check_action(&action!(e)),
+            Err(IllegalReason::WrongPhase)
+        );
+    }
+
+    #[test]
+    fn test_check_action_reasons() {
+        let game_state: GameState = "
+             1g
+              +-----------------+
+             8|                 |
+             7|                 |
+             6|     x     x     |
+             5|       e R       |
+             4| R   r M d       |
+             3|     x C   x     |
+             2|     r     E     |
+             1|               R |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        assert_eq!(game_state.check_action(&action!(f2n)), Ok(()));
+        assert_eq!(
+            game_state.check_action(&action!(a8s)),
+            Err(IllegalReason::NoPieceAtSquare)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(h1e)),
+            Err(IllegalReason::OffBoard)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(c4e)),
+            Err(IllegalReason::SquareOccupied)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(e5n)),
+            Err(IllegalReason::PieceFrozen)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(h1s)),
+            Err(IllegalReason::OffBoard)
+        );
+        assert_eq!(game_state.check_action(&action!(c4w)), Ok(()));
+        assert_eq!(
+            game_state.check_action(&action!(c2s)),
+            Err(IllegalReason::NoStrongerPieceToPushOrPull)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(d5n)),
+            Err(IllegalReason::NoStrongerPieceToPushOrPull)
+        );
+        assert_eq!(game_state.check_action(&action!(e4s)), Ok(()));
+        assert_eq!(
+            game_state.check_action(&action!(p)),
+            Err(IllegalReason::PassOnFirstStep)
+        );
+
+        assert_eq!(
+            game_state.check_action(&action!(a4s)),
+            Err(IllegalReason::RabbitMovingBackwards)
+        );
+
+        let game_state = take_actions!(game_state => h1w);
+
+        let game_state = take_actions!(game_state => e4s);
+        assert_eq!(
+            game_state.check_action(&action!(p)),
+            Err(IllegalReason::MustCompletePush)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(f2n)),
+            Err(IllegalReason::MustCompletePush)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(e5s)),
+            Err(IllegalReason::PushRequiresStrongerPiece)
+        );
+        assert_eq!(game_state.check_action(&action!(d4e)), Ok(()));
+
+        let game_state = take_actions!(game_state => d4e);
+        assert_eq!(
+            game_state.check_action(&action!(e3n)),
+            Err(IllegalReason::SquareOccupied)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(c4e)),
+            Err(IllegalReason::PullNotAllowedAfterPush)
+        );
+        assert_eq!(
+            game_state.check_action(&action!(e3e)),
+            Err(IllegalReason::NotEnoughStepsForPush)
+        );
+    }
+
+    #[test]
+    fn test_check_action_repetition_reasons() {
+        let game_state: GameState = "
+             1g
+              +-----------------+
+             8|   r   r r   r   |
+             7|                 |
+             6|     x     x     |
+             5|     E r         |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let game_state = take_actions!(game_state => c5s, c4n);
+        assert_eq!(
+            game_state.check_action(&action!(p)),
+            Err(IllegalReason::SamePositionAsStartOfMove)
+        );
+
+        let game_state = take_actions!(game_state => c5s);
+        assert_eq!(
+            game_state.check_action(&action!(c4n)),
+            Err(IllegalReason::SamePositionAsStartOfMove)
+        );
+
+        let game_state: GameState = "
+             1g
+              +-----------------+
+             8|               r |
+             7|               d |
+             6|     x     x     |
+             5|       m         |
+             4|                 |
+             3|     x E   x     |
+             2|                 |
+             1|               R |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let game_state = take_actions!(game_state => d3n, p, h7s, p, d4s, p, h6n, p, d3n, p, h7s, p, d4s, d3s, p, h6n, p, d2n, d3n);
+        assert_eq!(
+            game_state.check_action(&action!(p)),
+            Err(IllegalReason::ThirdTimeRepetition)
+        );
+
+        let game_state = take_actions!(game_state => d4s);
+        assert_eq!(
+            game_state.check_action(&action!(d3n)),
+            Err(IllegalReason::ThirdTimeRepetition)
+        );
+    }
+
+    #[test]
+    fn test_terminal_reason_none() {
+        assert_eq!(GameState::initial().terminal_reason(), None);
+        assert_eq!(initial_play_state().terminal_reason(), None);
+
+        let game_state = take_actions!(initial_play_state() => e2n);
+        assert_eq!(game_state.terminal_reason(), None);
+    }
+
+    #[test]
+    fn test_terminal_reason_goal() {
+        let game_state: GameState = "
+             1s
+              +-----------------+
+             8| R r   r r   r   |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1|   R             |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        assert_eq!(game_state.is_terminal(), Some(Terminal::GoldWin));
+        assert_eq!(game_state.terminal_reason(), Some(TerminalReason::Goal));
+    }
+
+    #[test]
+    fn test_terminal_reason_goal_over_elimination() {
+        let game_state: GameState = "
+             1g
+              +-----------------+
+             8|                 |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1| E r             |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        assert_eq!(game_state.is_terminal(), Some(Terminal::SilverWin));
+        assert_eq!(game_state.terminal_reason(), Some(TerminalReason::Goal));
+    }
+
+    #[test]
+    fn test_terminal_reason_elimination() {
+        let game_state: GameState = "
+             1g
+              +-----------------+
+             8|   r   r r   r   |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1| E               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        assert_eq!(game_state.is_terminal(), Some(Terminal::SilverWin));
+        assert_eq!(
+            game_state.terminal_reason(),
+            Some(TerminalReason::Elimination)
+        );
+    }
+
+    #[test]
+    fn test_terminal_reason_elimination_by_capture() {
+        let game_state: GameState = "
+             1g
+              +-----------------+
+             8|                 |
+             7|   R             |
+             6|     x     x     |
+             5|                 |
+             4|     E           |
+             3|     r     x     |
+             2|                 |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let game_state = take_actions!(game_state => c4n, p);
+        assert_eq!(game_state.is_terminal(), Some(Terminal::GoldWin));
+        assert_eq!(
+            game_state.terminal_reason(),
+            Some(TerminalReason::Elimination)
+        );
+    }
+
+    #[test]
+    fn test_terminal_reason_immobilization() {
+        let game_state: GameState = "
+             1g
+              +-----------------+
+             8|               r |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2| e               |
+             1| R c             |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        assert_eq!(game_state.is_terminal(), Some(Terminal::SilverWin));
+        assert_eq!(
+            game_state.terminal_reason(),
+            Some(TerminalReason::Immobilization)
+        );
+    }
+
+    #[test]
+    fn test_terminal_reason_immobilization_mid_move() {
+        let game_state: GameState = "
+             1g
+              +-----------------+
+             8|                 |
+             7|                 |
+             6|     x     x     |
+             5|       m         |
+             4|       E         |
+             3|     x     x     |
+             2|                 |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let game_state = take_actions!(game_state => d4e, d5s, d4n);
+
+        assert_eq!(game_state.is_terminal(), Some(Terminal::SilverWin));
+        assert_eq!(
+            game_state.terminal_reason(),
+            Some(TerminalReason::Immobilization)
+        );
+    }
+
+    #[test]
+    fn test_terminal_reason_repetition() {
+        let game_state: GameState = "
+             1g
+              +-----------------+
+             8|   r   r r   r   |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|       E         |
+             3|     x     x     |
+             2|                 |
+             1| R               |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let game_state = take_actions!(game_state => d4n, d5n, d6n);
+        assert_eq!(game_state.terminal_reason(), None);
+
+        // Record every position reachable by completing the move as having already occurred twice.
+        let repeated_hashes = game_state
+            .valid_actions()
+            .iter()
+            .map(|action| {
+                let hash = game_state.take_action(action).transposition_hash();
+                format!("{:016x},{:016x}", hash, hash)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let notation = game_state.to_extended_notation();
+        let (notation, _) = notation.rsplit_once(' ').unwrap();
+        let game_state =
+            GameState::from_extended_notation(&format!("{} {}", notation, repeated_hashes))
+                .unwrap();
+
+        assert_eq!(format!("{:?}", game_state.valid_actions()), "[]");
+        assert_eq!(game_state.is_terminal(), Some(Terminal::SilverWin));
+        assert_eq!(
+            game_state.terminal_reason(),
+            Some(TerminalReason::Repetition)
+        );
+    }
+
+    fn bits(squares: &[&str]) -> BitBoard {
+        squares
+            .iter()
+            .map(|square| square.parse::<Square>().unwrap())
+            .collect()
+    }
+
+    fn analysis_state() -> GameState {
+        "
+             2g
+              +-----------------+
+             8| H M             |
+             7|               r |
+             6|     x     x     |
+             5|     d E         |
+             4|     R           |
+             3|     x     x     |
+             2|       r C       |
+             1|             c r |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_frozen_and_supported_pieces() {
+        let game_state = analysis_state();
+        let piece_board = game_state.piece_board();
+
+        assert_eq!(piece_board.frozen_pieces(true), bits(&["c4"]));
+        assert_eq!(piece_board.frozen_pieces(false), bits(&["c5", "d2"]));
+
+        assert_eq!(piece_board.supported_pieces(true), bits(&["a8", "b8"]));
+        assert_eq!(piece_board.supported_pieces(false), bits(&["g1", "h1"]));
+    }
+
+    #[test]
+    fn test_dominated_by() {
+        let game_state = analysis_state();
+        let piece_board = game_state.piece_board();
+
+        let square = |square: &str| square.parse::<Square>().unwrap();
+
+        assert_eq!(
+            piece_board.dominated_by(&square("d5")),
+            bits(&["c5", "d2", "g1", "h1", "h7"])
+        );
+        assert_eq!(piece_board.dominated_by(&square("c5")), bits(&["c4", "e2"]));
+        assert_eq!(
+            piece_board.dominated_by(&square("e2")),
+            bits(&["d2", "h1", "h7"])
+        );
+        assert_eq!(piece_board.dominated_by(&square("c4")), BitBoard::EMPTY);
+        assert_eq!(piece_board.dominated_by(&square("e4")), BitBoard::EMPTY);
+    }
+
+    #[test]
+    fn test_mobile_pieces() {
+        let game_state = analysis_state();
+        let piece_board = game_state.piece_board();
+
+        assert_eq!(
+            piece_board.mobile_pieces(true),
+            bits(&["a8", "b8", "d5", "e2"])
+        );
+
+        // The rabbit on h1 can only step backward.
+        assert_eq!(piece_board.mobile_pieces(false), bits(&["g1", "h7"]));
+    }
+
+    #[test]
+    fn test_pushable_and_pullable_targets() {
+        let game_state = analysis_state();
+        let piece_board = game_state.piece_board();
+
+        assert_eq!(piece_board.pushable_targets(true), bits(&["c5", "d2"]));
+        assert_eq!(piece_board.pullable_targets(true), bits(&["c5", "d2"]));
+        assert_eq!(piece_board.pushable_targets(false), BitBoard::EMPTY);
+        assert_eq!(piece_board.pullable_targets(false), BitBoard::EMPTY);
+
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|                 |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3| r   x     x     |
+             2| d c             |
+             1| E c             |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        let piece_board = game_state.piece_board();
+
+        // The elephant is surrounded so it can only push, and the dog is blocked in.
+        assert_eq!(piece_board.pushable_targets(true), bits(&["b1"]));
+        assert_eq!(piece_board.pullable_targets(true), BitBoard::EMPTY);
+    }
 }
@@ -0,0 +1,557 @@
+use super::bit_mask::TRAP_MASK;
+use super::{BitBoard, Evaluator, GameState, Piece, PieceBoardState, WIN_SCORE};
+
+/// The largest magnitude of a material score, which keeps the scores of lopsided boards well within `±WIN_SCORE`.
+const MAX_MATERIAL_SCORE: i32 = WIN_SCORE / 10;
+
+/// The points for each level of the officers matched by FAME, from the strongest pair to the weakest.
+const FAME_LEVELS: [f64; 8] = [256.0, 87.0, 32.0, 12.0, 4.0, 1.5, 0.5, 0.2];
+/// The points shared by the remaining rabbits of a side in FAME.
+const FAME_RABBIT_POINTS: f64 = 600.0;
+
+/// The base value of every piece in DAPE.
+const DAPE_A: f64 = 10.0;
+/// The value of a piece in DAPE which is divided among the opposing pieces stronger than it.
+const DAPE_B: f64 = 100.0;
+
+const HARLOG_Q: f64 = 1.447530126;
+const HARLOG_G: f64 = 0.6314442034;
+
+/// The number of pieces of each type for both players, indexed by `Piece::ALL`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PieceCounts {
+    gold: [u32; 6],
+    silver: [u32; 6],
+}
+
+impl PieceCounts {
+    fn from_piece_board(piece_board: &PieceBoardState) -> Self {
+        let count = |is_p1: bool| {
+            Piece::ALL.map(|piece| piece_board.bits_for_piece(piece, is_p1).count() as u32)
+        };
+
+        PieceCounts {
+            gold: count(true),
+            silver: count(false),
+        }
+    }
+
+    /// The counts at the start of the game.
+    fn initial() -> Self {
+        let counts = Piece::ALL.map(|piece| piece.initial_count() as u32);
+        PieceCounts {
+            gold: counts,
+            silver: counts,
+        }
+    }
+
+    fn swapped(&self) -> Self {
+        PieceCounts {
+            gold: self.silver,
+            silver: self.gold,
+        }
+    }
+
+    fn without_gold_rabbit(&self) -> Self {
+        let mut counts = *self;
+        counts.gold[0] -= 1;
+        counts
+    }
+}
+
+/// Returns the officers, the pieces other than rabbits, from strongest to weakest.
+fn officers(counts: &[u32; 6]) -> Vec<Piece> {
+    Piece::ALL
+        .iter()
+        .zip(counts.iter())
+        .skip(1)
+        .rev()
+        .flat_map(|(piece, count)| std::iter::repeat_n(*piece, *count as usize))
+        .collect()
+}
+
+/// Returns the number of pieces which are stronger than the piece.
+fn stronger_than(piece: Piece, counts: &[u32; 6]) -> u32 {
+    Piece::ALL
+        .iter()
+        .zip(counts.iter())
+        .filter(|(other, _)| **other > piece)
+        .map(|(_, count)| count)
+        .sum()
+}
+
+fn fame_counts(counts: &PieceCounts) -> f64 {
+    let gold_officers = officers(&counts.gold);
+    let silver_officers = officers(&counts.silver);
+    let mut gold_rabbits = counts.gold[0];
+    let mut silver_rabbits = counts.silver[0];
+    let mut score = 0.0;
+
+    for (level, points) in FAME_LEVELS.iter().enumerate() {
+        match (gold_officers.get(level), silver_officers.get(level)) {
+            (Some(gold), Some(silver)) if gold > silver => score += points,
+            (Some(gold), Some(silver)) if gold < silver => score -= points,
+            (Some(_), None) => {
+                score += points;
+                silver_rabbits = silver_rabbits.saturating_sub(1);
+            }
+            (None, Some(_)) => {
+                score -= points;
+                gold_rabbits = gold_rabbits.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+
+    let rabbit_value = |opp_officers: usize| FAME_RABBIT_POINTS / (2 * opp_officers + 1) as f64;
+
+    score + gold_rabbits as f64 * rabbit_value(silver_officers.len())
+        - silver_rabbits as f64 * rabbit_value(gold_officers.len())
+}
+
+fn dape_counts(counts: &PieceCounts) -> f64 {
+    let army_value = |own: &[u32; 6], opp: &[u32; 6]| -> f64 {
+        Piece::ALL
+            .iter()
+            .zip(own.iter())
+            .map(|(piece, count)| {
+                *count as f64 * (DAPE_A + DAPE_B / (1 + stronger_than(*piece, opp)) as f64)
+            })
+            .sum()
+    };
+
+    army_value(&counts.gold, &counts.silver) - army_value(&counts.silver, &counts.gold)
+}
+
+fn harlog_counts(counts: &PieceCounts) -> f64 {
+    let army_value = |own: &[u32; 6], opp: &[u32; 6]| -> f64 {
+        let officer_value: f64 = officers(own)
+            .iter()
+            .map(|piece| match stronger_than(*piece, opp) {
+                0 => 2.0 / HARLOG_Q,
+                stronger => 1.0 / (HARLOG_Q + stronger as f64),
+            })
+            .sum();
+        let rabbits = own[0] as f64;
+        let pieces = own.iter().sum::<u32>() as f64;
+
+        officer_value + HARLOG_G * (rabbits * pieces).ln()
+    };
+
+    army_value(&counts.gold, &counts.silver) - army_value(&counts.silver, &counts.gold)
+}
+
+/// Returns the FAME (Fritz's Arimaa Material Evaluator) score of the board from gold's perspective.
+///
+/// The officers of each side are lined up from strongest to weakest and compared pair by pair. The side with the stronger
+/// piece of each pair scores the points of that level, 256, 87, 32, 12, 4, 1.5, 0.5 and 0.2. An officer without an opposing
+/// officer at its level is matched against an opposing rabbit, which scores the level and removes the rabbit.
+/// Each remaining rabbit is then worth `600 / (2 * opposing officers + 1)`.
+pub fn fame(piece_board: &PieceBoardState) -> f64 {
+    fame_counts(&PieceCounts::from_piece_board(piece_board))
+}
+
+/// Returns the DAPE material score of the board from gold's perspective.
+///
+/// Each piece is worth `10 + 100 / (1 + s)`, where `s` is the number of opposing pieces stronger than it, so that a piece
+/// becomes more valuable as the pieces that can dominate it are lost.
+pub fn dape(piece_board: &PieceBoardState) -> f64 {
+    dape_counts(&PieceCounts::from_piece_board(piece_board))
+}
+
+/// Returns the HarLog material score of the board from gold's perspective.
+///
+/// Each officer is worth `1 / (Q + s)`, where `s` is the number of opposing pieces stronger than it, or `2 / Q` when there are none.
+/// The rabbits of each side add `G * ln(R * P)`, where `R` is the number of rabbits and `P` the number of pieces of the side,
+/// with `Q = 1.447530126` and `G = 0.6314442034`. The score is infinite when a side has no rabbits.
+pub fn harlog(piece_board: &PieceBoardState) -> f64 {
+    harlog_counts(&PieceCounts::from_piece_board(piece_board))
+}
+
+/// Scales a material score from gold's perspective so that one rabbit at the start of the game is worth 100 for the player to move.
+fn material_score(game_state: &GameState, material: fn(&PieceCounts) -> f64) -> i32 {
+    let counts = PieceCounts::from_piece_board(game_state.piece_board());
+    let counts = if game_state.is_p1_turn_to_move() {
+        counts
+    } else {
+        counts.swapped()
+    };
+
+    let initial_counts = PieceCounts::initial();
+    let rabbit_value = material(&initial_counts) - material(&initial_counts.without_gold_rabbit());
+    let score = material(&counts) / rabbit_value * 100.0;
+
+    (score.round() as i32).clamp(-MAX_MATERIAL_SCORE, MAX_MATERIAL_SCORE)
+}
+
+/// Evaluates the material of a state with `fame`, scaled so that a rabbit at the start of the game is worth 100.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FameEvaluator;
+
+impl Evaluator for FameEvaluator {
+    fn evaluate(&self, game_state: &GameState) -> i32 {
+        material_score(game_state, fame_counts)
+    }
+}
+
+/// Evaluates the material of a state with `dape`, scaled so that a rabbit at the start of the game is worth 100.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DapeEvaluator;
+
+impl Evaluator for DapeEvaluator {
+    fn evaluate(&self, game_state: &GameState) -> i32 {
+        material_score(game_state, dape_counts)
+    }
+}
+
+/// Evaluates the material of a state with `harlog`, scaled so that a rabbit at the start of the game is worth 100.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HarLogEvaluator;
+
+impl Evaluator for HarLogEvaluator {
+    fn evaluate(&self, game_state: &GameState) -> i32 {
+        material_score(game_state, harlog_counts)
+    }
+}
+
+/// Positional features of a board. Each term is the value for gold minus the value for silver.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PositionalTerms {
+    /// For each trap, the number of the player's pieces next to it, counting at most two as two pieces are enough to hold it.
+    pub trap_control: i32,
+    /// The number of rows each of the player's rabbits has advanced from the player's home row.
+    pub rabbit_advancement: i32,
+    /// The number of the player's pieces which are frozen.
+    pub frozen_pieces: i32,
+    /// The number of empty squares the player's elephant can reach in up to four steps.
+    pub elephant_mobility: i32,
+}
+
+impl PositionalTerms {
+    /// Returns the terms of the board.
+    pub fn from_piece_board(piece_board: &PieceBoardState) -> Self {
+        let term = |is_p1: bool| -> PositionalTerms {
+            let pieces = piece_board.player_piece_mask(is_p1);
+
+            let trap_control = BitBoard::new(TRAP_MASK)
+                .iter()
+                .map(|trap| (BitBoard::from(trap).neighbors() & pieces).count().min(2) as i32)
+                .sum();
+
+            let rabbit_advancement = piece_board
+                .bits_for_piece(Piece::Rabbit, is_p1)
+                .iter()
+                .map(|square| {
+                    if is_p1 {
+                        square.row() as i32 - 1
+                    } else {
+                        8 - square.row() as i32
+                    }
+                })
+                .sum();
+
+            let frozen_pieces = piece_board.frozen_pieces(is_p1).count() as i32;
+
+            let empty_squares = !piece_board.all_pieces();
+            let mut reachable = piece_board.bits_for_piece(Piece::Elephant, is_p1);
+            for _ in 0..4 {
+                reachable |= reachable.neighbors() & empty_squares;
+            }
+            let elephant_mobility = (reachable & empty_squares).count() as i32;
+
+            PositionalTerms {
+                trap_control,
+                rabbit_advancement,
+                frozen_pieces,
+                elephant_mobility,
+            }
+        };
+
+        let gold = term(true);
+        let silver = term(false);
+
+        PositionalTerms {
+            trap_control: gold.trap_control - silver.trap_control,
+            rabbit_advancement: gold.rabbit_advancement - silver.rabbit_advancement,
+            frozen_pieces: gold.frozen_pieces - silver.frozen_pieces,
+            elephant_mobility: gold.elephant_mobility - silver.elephant_mobility,
+        }
+    }
+}
+
+/// The weight of each positional term, in the same units as the material evaluators where a rabbit is worth 100.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PositionalWeights {
+    pub trap_control: i32,
+    pub rabbit_advancement: i32,
+    pub frozen_pieces: i32,
+    pub elephant_mobility: i32,
+}
+
+impl Default for PositionalWeights {
+    fn default() -> Self {
+        PositionalWeights {
+            trap_control: 15,
+            rabbit_advancement: 4,
+            frozen_pieces: -10,
+            elephant_mobility: 1,
+        }
+    }
+}
+
+impl PositionalWeights {
+    /// Returns the weighted sum of the terms.
+    pub fn score(&self, terms: &PositionalTerms) -> i32 {
+        self.trap_control * terms.trap_control
+            + self.rabbit_advancement * terms.rabbit_advancement
+            + self.frozen_pieces * terms.frozen_pieces
+            + self.elephant_mobility * terms.elephant_mobility
+    }
+}
+
+/// Adds weighted positional terms to the score of a material evaluator.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PositionalEvaluator<E = HarLogEvaluator> {
+    material: E,
+    weights: PositionalWeights,
+}
+
+impl<E: Evaluator> PositionalEvaluator<E> {
+    pub fn new(material: E, weights: PositionalWeights) -> Self {
+        PositionalEvaluator { material, weights }
+    }
+
+    pub fn weights(&self) -> &PositionalWeights {
+        &self.weights
+    }
+}
+
+impl<E: Evaluator> Evaluator for PositionalEvaluator<E> {
+    fn evaluate(&self, game_state: &GameState) -> i32 {
+        let terms = PositionalTerms::from_piece_board(game_state.piece_board());
+        let positional_score = self.weights.score(&terms);
+        let positional_score = if game_state.is_p1_turn_to_move() {
+            positional_score
+        } else {
+            -positional_score
+        };
+
+        self.material.evaluate(game_state) + positional_score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Evaluator, GameState, Piece};
+    use super::*;
+
+    fn full_material_play_state() -> GameState {
+        "
+             2g
+              +-----------------+
+             8| c d h m e h d c |
+             7| r r r r r r r r |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2| R R R R R R R R |
+             1| C D H M E H D C |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    fn without_gold_piece(piece: Piece) -> PieceCounts {
+        let mut counts = PieceCounts::initial();
+        let idx = piece.index();
+        counts.gold[idx] -= 1;
+        counts
+    }
+
+    fn assert_piece_order(material: fn(&PieceCounts) -> f64) {
+        let losses: Vec<f64> = Piece::ALL
+            .iter()
+            .map(|piece| material(&without_gold_piece(*piece)))
+            .collect();
+
+        assert_eq!(material(&PieceCounts::initial()), 0.0);
+
+        for pair in losses.windows(2) {
+            assert!(pair[0] < 0.0);
+            assert!(pair[1] < pair[0], "{:?}", losses);
+        }
+
+        let counts = without_gold_piece(Piece::Horse);
+        assert_eq!(material(&counts.swapped()), -material(&counts));
+    }
+
+    #[test]
+    fn test_fame() {
+        assert_piece_order(fame_counts);
+
+        // Losing a rabbit costs one rabbit worth 600 / 17.
+        let rabbit_loss = fame_counts(&without_gold_piece(Piece::Rabbit));
+        assert!((rabbit_loss + 600.0 / 17.0).abs() < 1e-9);
+
+        // Losing a cat loses the last level, is matched against a rabbit, and raises the value of silver's rabbits.
+        let cat_loss = fame_counts(&without_gold_piece(Piece::Cat));
+        let expected = -0.2 + 7.0 * 600.0 / 17.0 - 8.0 * 600.0 / 15.0;
+        assert!((cat_loss - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dape() {
+        assert_piece_order(dape_counts);
+
+        // Silver's pieces are no longer dominated by the lost cat: only silver's rabbits gain, 100 / 8 - 100 / 9 each.
+        let cat_loss = dape_counts(&without_gold_piece(Piece::Cat));
+        let expected = -(10.0 + 100.0 / 7.0) - 8.0 * (100.0 / 8.0 - 100.0 / 9.0);
+        assert!((cat_loss - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_harlog() {
+        assert_piece_order(harlog_counts);
+
+        let cat_loss = harlog_counts(&without_gold_piece(Piece::Cat));
+        let expected = -1.0 / (HARLOG_Q + 6.0) + HARLOG_G * (15.0_f64 / 16.0).ln();
+        assert!((cat_loss - expected).abs() < 1e-9);
+
+        let mut counts = PieceCounts::initial();
+        counts.silver[0] = 0;
+        assert_eq!(harlog_counts(&counts), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_material_from_piece_board() {
+        let game_state = full_material_play_state();
+        let piece_board = game_state.piece_board();
+
+        assert_eq!(fame(piece_board), 0.0);
+        assert_eq!(dape(piece_board), 0.0);
+        assert_eq!(harlog(piece_board), 0.0);
+
+        let game_state: GameState = game_state
+            .to_string()
+            .replacen("| R R R", "|   R R", 1)
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            fame(game_state.piece_board()),
+            fame_counts(&without_gold_piece(Piece::Rabbit))
+        );
+    }
+
+    #[test]
+    fn test_material_evaluators() {
+        let game_state: GameState = full_material_play_state()
+            .to_string()
+            .replacen("| R R R", "|   R R", 1)
+            .parse()
+            .unwrap();
+
+        assert_eq!(FameEvaluator.evaluate(&game_state), -100);
+        assert_eq!(DapeEvaluator.evaluate(&game_state), -100);
+        assert_eq!(HarLogEvaluator.evaluate(&game_state), -100);
+
+        let game_state: GameState = game_state
+            .to_string()
+            .replacen("2g", "2s", 1)
+            .parse()
+            .unwrap();
+
+        assert_eq!(FameEvaluator.evaluate(&game_state), 100);
+        assert_eq!(HarLogEvaluator.evaluate(&game_state), 100);
+    }
+
+    #[test]
+    fn test_material_evaluator_is_bounded() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|         e       |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1|       R         |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        assert_eq!(HarLogEvaluator.evaluate(&game_state), MAX_MATERIAL_SCORE);
+    }
+
+    #[test]
+    fn test_positional_terms() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|                 |
+             7|   r             |
+             6|     x     x     |
+             5|     R     R     |
+             4|       d E       |
+             3|   C x   r x     |
+             2|     H   M       |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let terms = PositionalTerms::from_piece_board(game_state.piece_board());
+
+        assert_eq!(
+            terms,
+            PositionalTerms {
+                // Gold holds c6 and f6 with a rabbit each and c3 with the cat and horse. Silver's rabbit is next to f3.
+                trap_control: (1 + 1 + 2) - 1,
+                // Gold's rabbits are on the 5th row. Silver's rabbits are on the 7th and 3rd rows.
+                rabbit_advancement: (4 + 4) - (1 + 5),
+                // Silver's dog and rabbit are frozen.
+                frozen_pieces: -2,
+                elephant_mobility: 22,
+            }
+        );
+    }
+
+    #[test]
+    fn test_positional_evaluator() {
+        let evaluator = PositionalEvaluator::<HarLogEvaluator>::default();
+
+        assert_eq!(evaluator.evaluate(&full_material_play_state()), 0);
+
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8| c d h m e h d c |
+             7| r r r r r r r r |
+             6|     x     x     |
+             5|                 |
+             4| R               |
+             3|     x     x     |
+             2|   R R R R R R R |
+             1| C D H M E H D C |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        let advanced = evaluator.evaluate(&game_state);
+
+        assert_eq!(advanced, evaluator.weights().rabbit_advancement * 2);
+
+        let game_state: GameState = game_state
+            .to_string()
+            .replacen("2g", "2s", 1)
+            .parse()
+            .unwrap();
+        assert_eq!(evaluator.evaluate(&game_state), -advanced);
+    }
+}
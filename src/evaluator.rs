@@ -0,0 +1,90 @@
+use super::{GameState, Piece};
+
+/// A static evaluation of a game state used to score the leaves of a search.
+pub trait Evaluator {
+    /// Returns the score of the state from the perspective of the player to move. Higher scores are better for that player.
+    /// The score should stay well within `±WIN_SCORE` so that it can't be confused with a terminal state.
+    fn evaluate(&self, game_state: &GameState) -> i32;
+}
+
+/// The score of a won game. Wins found in fewer steps are scored higher.
+pub const WIN_SCORE: i32 = 1_000_000;
+
+/// A basic evaluator which sums fixed values for each piece on the board.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaterialEvaluator;
+
+impl MaterialEvaluator {
+    /// Returns the value of a single piece.
+    pub fn piece_value(piece: Piece) -> i32 {
+        match piece {
+            Piece::Elephant => 800,
+            Piece::Camel => 500,
+            Piece::Horse => 300,
+            Piece::Dog => 200,
+            Piece::Cat => 150,
+            Piece::Rabbit => 100,
+        }
+    }
+}
+
+impl Evaluator for MaterialEvaluator {
+    fn evaluate(&self, game_state: &GameState) -> i32 {
+        let piece_board = game_state.piece_board();
+        let is_p1_turn_to_move = game_state.is_p1_turn_to_move();
+
+        Piece::ALL
+            .iter()
+            .map(|piece| {
+                let curr_player_count = piece_board
+                    .bits_for_piece(*piece, is_p1_turn_to_move)
+                    .count() as i32;
+                let opp_player_count = piece_board
+                    .bits_for_piece(*piece, !is_p1_turn_to_move)
+                    .count() as i32;
+
+                (curr_player_count - opp_player_count) * Self::piece_value(*piece)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::GameState;
+    use super::{Evaluator, MaterialEvaluator};
+
+    #[test]
+    fn test_material_evaluator() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|   r   r r   r   |
+             7|           e     |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|   D     M       |
+             1| R R   R         |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            MaterialEvaluator.evaluate(&game_state),
+            200 + 500 - 800 - 100
+        );
+
+        let game_state: GameState = game_state
+            .to_string()
+            .replacen("2g", "2s", 1)
+            .parse()
+            .unwrap();
+        assert_eq!(
+            MaterialEvaluator.evaluate(&game_state),
+            -(200 + 500 - 800 - 100)
+        );
+    }
+}
@@ -1,5 +1,85 @@
-use crate::{convert_piece_to_letter, Action, Direction, GameState, Piece, Square};
+use crate::{convert_char_to_piece, convert_piece_to_letter};
+use crate::{Action, BitBoard, Direction, GameState, Piece, Square};
 use itertools::Itertools;
+use std::error::Error;
+use std::fmt;
+
+/// The reason a move string could not be converted into actions.
+/// Each variant contains the index of the step, or placement, within the move that failed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MoveStringError {
+    /// The token is not a placement, step or capture.
+    BadToken { step: usize, token: String },
+    /// The token does not start with a valid piece letter.
+    UnknownPiece { step: usize, token: String },
+    /// The token contains a square which is not on the board.
+    OffBoardSquare { step: usize, token: String },
+    /// The piece in the token does not match the piece on the board.
+    PieceMismatch {
+        step: usize,
+        token: String,
+        square: Square,
+    },
+    /// The capture annotation does not match the piece trapped by the previous step.
+    CaptureMismatch { step: usize, token: String },
+    /// The action is not valid in the state reached by the preceding steps of the move.
+    IllegalStep { step: usize, action: Action },
+    /// The move ended before all pieces were placed.
+    IncompleteMove { step: usize },
+}
+
+impl MoveStringError {
+    /// Returns the index of the step within the move that failed.
+    pub fn step(&self) -> usize {
+        match self {
+            MoveStringError::BadToken { step, .. }
+            | MoveStringError::UnknownPiece { step, .. }
+            | MoveStringError::OffBoardSquare { step, .. }
+            | MoveStringError::PieceMismatch { step, .. }
+            | MoveStringError::CaptureMismatch { step, .. }
+            | MoveStringError::IllegalStep { step, .. }
+            | MoveStringError::IncompleteMove { step } => *step,
+        }
+    }
+}
+
+impl fmt::Display for MoveStringError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveStringError::BadToken { step, token } => {
+                write!(f, "Invalid token '{}' at step {}", token, step)
+            }
+            MoveStringError::UnknownPiece { step, token } => {
+                write!(f, "Unknown piece in '{}' at step {}", token, step)
+            }
+            MoveStringError::OffBoardSquare { step, token } => {
+                write!(f, "Invalid square in '{}' at step {}", token, step)
+            }
+            MoveStringError::PieceMismatch {
+                step,
+                token,
+                square,
+            } => write!(
+                f,
+                "'{}' does not match the piece on {} at step {}",
+                token, square, step
+            ),
+            MoveStringError::CaptureMismatch { step, token } => write!(
+                f,
+                "'{}' does not match a captured piece at step {}",
+                token, step
+            ),
+            MoveStringError::IllegalStep { step, action } => {
+                write!(f, "Action {} is not valid at step {}", action, step)
+            }
+            MoveStringError::IncompleteMove { step } => {
+                write!(f, "Move is incomplete after step {}", step)
+            }
+        }
+    }
+}
+
+impl Error for MoveStringError {}
 
 // TODO add tests for this
 pub fn convert_actions_to_move_string(game_state: GameState, actions: &[Action]) -> String {
@@ -8,56 +88,231 @@ pub fn convert_actions_to_move_string(game_state: GameState, actions: &[Action])
     let actions_last_idx = actions.len() - 1;
 
     for (i, action) in actions.iter().enumerate() {
-        match action {
-            Action::Move(square, direction) => {
-                let piece_board = game_state.piece_board();
-                let piece = &piece_board.piece_type_at_square(&square).unwrap();
-                let is_p1_piece =
-                    piece_board.bits_for_piece(*piece, true) & square.as_bit_board() != 0;
-
-                actions_as_string.push(format!(
-                    "{}{}{}",
-                    convert_piece_to_letter(piece, is_p1_piece),
-                    square,
-                    direction
-                ));
+        actions_as_string.extend(convert_action_to_tokens(&game_state, action));
 
-                let trapped_animal_square = &game_state.trapped_animal_for_action(&action);
-                if let Some((square, piece, is_p1_piece)) = trapped_animal_square {
-                    actions_as_string.push(format!(
-                        "{}{}x",
-                        convert_piece_to_letter(piece, *is_p1_piece),
-                        square
-                    ));
-                }
-            }
-            Action::Place(piece) => {
-                let piece_board = game_state.piece_board();
-                let square = Square::from_bit_board(piece_board.placement_bit());
+        let was_p1_move = game_state.is_p1_turn_to_move();
+        game_state = game_state.take_action(action);
+        let is_p1_move = game_state.is_p1_turn_to_move();
+
+        // Double space move strings when switching between players
+        if i != actions_last_idx && is_p1_move != was_p1_move {
+            actions_as_string.push("".to_string());
+        }
+    }
 
-                actions_as_string.push(format!(
-                    "{}{}",
-                    convert_piece_to_letter(piece, game_state.is_p1_turn_to_move()),
+    actions_as_string.iter().join(" ")
+}
+
+/// Converts a single action to its tokens in a move string.
+/// A step is followed by a capture token if it results in a piece being trapped. Passes have no tokens.
+pub fn convert_action_to_tokens(game_state: &GameState, action: &Action) -> Vec<String> {
+    let mut tokens = Vec::with_capacity(1);
+
+    match action {
+        Action::Move(square, direction) => {
+            let piece_board = game_state.piece_board();
+            let piece = &piece_board.piece_type_at_square(square).unwrap();
+            let is_p1_piece = piece_board.bits_for_piece(*piece, true).contains(*square);
+
+            tokens.push(format!(
+                "{}{}{}",
+                convert_piece_to_letter(piece, is_p1_piece),
+                square,
+                direction
+            ));
+
+            let trapped_animal_square = &game_state.trapped_animal_for_action(action);
+            if let Some((square, piece, is_p1_piece)) = trapped_animal_square {
+                tokens.push(format!(
+                    "{}{}x",
+                    convert_piece_to_letter(piece, *is_p1_piece),
                     square
                 ));
             }
-            Action::Pass => {}
+        }
+        Action::Place(piece) => {
+            let piece_board = game_state.piece_board();
+            let square = piece_board.placement_bit().first().unwrap();
+
+            tokens.push(format!(
+                "{}{}",
+                convert_piece_to_letter(piece, game_state.is_p1_turn_to_move()),
+                square
+            ));
+        }
+        Action::Pass => {}
+    }
+
+    tokens
+}
+
+/// Converts the string of a single move, such as `Ed2n Ed3n Rc3x` or a setup such as `Ra1 Rb1 ...`, into actions.
+/// Each step is validated against the actions that are valid in the state reached by the preceding steps.
+/// Moves of fewer than four steps are completed with `Action::Pass`.
+pub fn try_convert_move_string_to_actions(
+    game_state: &GameState,
+    move_string: &str,
+) -> Result<Vec<Action>, MoveStringError> {
+    let tokens = move_string.split_whitespace().collect::<Vec<_>>();
+
+    if game_state.is_play_phase() {
+        convert_step_tokens_to_actions(game_state, &tokens)
+    } else {
+        convert_placement_tokens_to_actions(game_state, &tokens)
+    }
+}
+
+fn convert_placement_tokens_to_actions(
+    game_state: &GameState,
+    tokens: &[&str],
+) -> Result<Vec<Action>, MoveStringError> {
+    let is_p1 = game_state.is_p1_turn_to_move();
+    let mut placements = Vec::with_capacity(tokens.len());
+
+    for (step, token) in tokens.iter().enumerate() {
+        if token.len() != 3 {
+            return Err(MoveStringError::BadToken {
+                step,
+                token: token.to_string(),
+            });
+        }
+
+        let (piece, is_p1_piece, square) = parse_piece_and_square(step, token)?;
+        if is_p1_piece != is_p1 {
+            return Err(MoveStringError::PieceMismatch {
+                step,
+                token: token.to_string(),
+                square,
+            });
+        }
+
+        placements.push((square, piece));
+    }
+
+    // Pieces are placed in order of the squares.
+    placements.sort_by_key(|(square, _)| *square);
+
+    let mut game_state = game_state.clone();
+    let mut actions = Vec::with_capacity(placements.len());
+    for (step, (square, piece)) in placements.into_iter().enumerate() {
+        let action = Action::Place(piece);
+        if game_state.is_p1_turn_to_move() != is_p1
+            || game_state.piece_board().placement_bit() != BitBoard::from(square)
+            || !game_state.valid_actions().contains(&action)
+        {
+            return Err(MoveStringError::IllegalStep { step, action });
         }
 
-        let was_p1_move = game_state.is_p1_turn_to_move();
         game_state = game_state.take_action(&action);
-        let is_p1_move = game_state.is_p1_turn_to_move();
+        actions.push(action);
+    }
 
-        // Double space move strings when switching between players
-        if i != actions_last_idx && is_p1_move != was_p1_move {
-            actions_as_string.push("".to_string());
+    if game_state.is_p1_turn_to_move() == is_p1 {
+        return Err(MoveStringError::IncompleteMove {
+            step: actions.len(),
+        });
+    }
+
+    Ok(actions)
+}
+
+fn convert_step_tokens_to_actions(
+    game_state: &GameState,
+    tokens: &[&str],
+) -> Result<Vec<Action>, MoveStringError> {
+    let is_p1 = game_state.is_p1_turn_to_move();
+    let mut game_state = game_state.clone();
+    let mut actions = Vec::with_capacity(4);
+    let mut expected_capture = None;
+
+    for token in tokens {
+        let step = actions.len();
+        let bad_token = || MoveStringError::BadToken {
+            step,
+            token: token.to_string(),
+        };
+
+        if token.len() != 4 || !token.is_ascii() {
+            return Err(bad_token());
+        }
+
+        let (piece, is_p1_piece, square) = parse_piece_and_square(step, &token[..3])?;
+
+        if token.ends_with('x') {
+            if expected_capture.take() != Some((square, piece, is_p1_piece)) {
+                return Err(MoveStringError::CaptureMismatch {
+                    step: step.saturating_sub(1),
+                    token: token.to_string(),
+                });
+            }
+            continue;
+        }
+
+        let direction = token[3..].parse::<Direction>().map_err(|_| bad_token())?;
+        let piece_board = game_state.piece_board();
+        let is_piece_at_square = piece_board.piece_type_at_square(&square) == Some(piece)
+            && piece_board
+                .bits_for_piece(piece, is_p1_piece)
+                .contains(square);
+        if !is_piece_at_square {
+            return Err(MoveStringError::PieceMismatch {
+                step,
+                token: token.to_string(),
+                square,
+            });
+        }
+
+        let action = Action::Move(square, direction);
+        if game_state.is_p1_turn_to_move() != is_p1 || !game_state.valid_actions().contains(&action)
+        {
+            return Err(MoveStringError::IllegalStep { step, action });
         }
+
+        expected_capture = game_state.trapped_animal_for_action(&action);
+        game_state = game_state.take_action(&action);
+        actions.push(action);
     }
 
-    actions_as_string.iter().join(" ")
+    if game_state.is_p1_turn_to_move() == is_p1 {
+        if !game_state.can_pass(true) {
+            return Err(MoveStringError::IllegalStep {
+                step: actions.len(),
+                action: Action::Pass,
+            });
+        }
+
+        actions.push(Action::Pass);
+    }
+
+    Ok(actions)
 }
 
-// TODO add tests for this
+fn parse_piece_and_square(
+    step: usize,
+    token: &str,
+) -> Result<(Piece, bool, Square), MoveStringError> {
+    let mut chars = token.chars();
+    let (piece, is_p1_piece) = chars
+        .next()
+        .and_then(convert_char_to_piece)
+        .ok_or_else(|| MoveStringError::UnknownPiece {
+            step,
+            token: token.to_string(),
+        })?;
+    let square = chars
+        .as_str()
+        .parse::<Square>()
+        .map_err(|_| MoveStringError::OffBoardSquare {
+            step,
+            token: token.to_string(),
+        })?;
+
+    Ok((piece, is_p1_piece, square))
+}
+
+/// Converts a move string into actions without validating it against a game state.
+///
+/// Panics if the move string is malformed. Prefer `try_convert_move_string_to_actions` for input which is not trusted.
 pub fn convert_move_string_to_actions(actions_as_string: &str) -> Vec<Action> {
     let mut actions = actions_as_string
         .split(' ')
@@ -91,3 +346,182 @@ pub fn convert_move_string_to_actions(actions_as_string: &str) -> Vec<Action> {
         actions
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::{action, take_actions, Action, GameState, Square};
+    use super::{try_convert_move_string_to_actions, MoveStringError};
+
+    fn officers_forward_play_state() -> GameState {
+        let game_state = GameState::initial();
+        let game_state = take_actions!(game_state => c, d, h, c, e, h, d, c);
+        let game_state = take_actions!(game_state => r, r, r, r, r, r, r, r);
+
+        let game_state = take_actions!(game_state => r, r, r, r, r, r, r, r);
+        take_actions!(game_state => c, d, h, c, e, h, d, c)
+    }
+
+    #[test]
+    fn test_try_convert_steps() {
+        let game_state = officers_forward_play_state();
+        let actions = try_convert_move_string_to_actions(&game_state, "Ee2n Ee3n Ca2n").unwrap();
+
+        assert_eq!(
+            actions,
+            vec![action!(e2n), action!(e3n), action!(a2n), action!(p)]
+        );
+    }
+
+    #[test]
+    fn test_try_convert_capture() {
+        let game_state = officers_forward_play_state();
+        let actions = try_convert_move_string_to_actions(&game_state, "Hc2n Hc3x Ee2n").unwrap();
+
+        assert_eq!(actions, vec![action!(c2n), action!(e2n), action!(p)]);
+
+        let result = try_convert_move_string_to_actions(&game_state, "Hc2n Dc3x Ee2n");
+        assert_eq!(
+            result,
+            Err(MoveStringError::CaptureMismatch {
+                step: 0,
+                token: "Dc3x".to_string()
+            })
+        );
+
+        let result = try_convert_move_string_to_actions(&game_state, "Ee2n Ee3x");
+        assert_eq!(result.map_err(|e| e.step()), Err(0));
+    }
+
+    #[test]
+    fn test_try_convert_setup() {
+        let game_state = GameState::initial();
+        let actions = try_convert_move_string_to_actions(
+            &game_state,
+            "Ra1 Rb1 Rc1 Rd1 Re1 Rf1 Rg1 Rh1 Ea2 Mb2 Hc2 Hd2 De2 Df2 Cg2 Ch2",
+        )
+        .unwrap();
+
+        assert_eq!(actions.len(), 16);
+        assert_eq!(actions[0], action!(e));
+        assert_eq!(actions[15], action!(r));
+
+        let result = try_convert_move_string_to_actions(&game_state, "Ra2 Rb2");
+        assert_eq!(result, Err(MoveStringError::IncompleteMove { step: 2 }));
+
+        let result = try_convert_move_string_to_actions(&game_state, "Ra1 rb1");
+        assert_eq!(
+            result,
+            Err(MoveStringError::PieceMismatch {
+                step: 1,
+                token: "rb1".to_string(),
+                square: "b1".parse().unwrap()
+            })
+        );
+
+        let result = try_convert_move_string_to_actions(&game_state, "Ea1 Eb1");
+        assert_eq!(
+            result,
+            Err(MoveStringError::IllegalStep {
+                step: 0,
+                action: action!(e)
+            })
+        );
+    }
+
+    #[test]
+    fn test_try_convert_errors() {
+        let game_state = officers_forward_play_state();
+        let e2: Square = "e2".parse().unwrap();
+
+        let cases = vec![
+            (
+                "Ee2",
+                MoveStringError::BadToken {
+                    step: 0,
+                    token: "Ee2".to_string(),
+                },
+            ),
+            (
+                "Ee2n Ee3q",
+                MoveStringError::BadToken {
+                    step: 1,
+                    token: "Ee3q".to_string(),
+                },
+            ),
+            (
+                "Xe2n",
+                MoveStringError::UnknownPiece {
+                    step: 0,
+                    token: "Xe2".to_string(),
+                },
+            ),
+            (
+                "Ee9n",
+                MoveStringError::OffBoardSquare {
+                    step: 0,
+                    token: "Ee9".to_string(),
+                },
+            ),
+            (
+                "Me2n",
+                MoveStringError::PieceMismatch {
+                    step: 0,
+                    token: "Me2n".to_string(),
+                    square: e2,
+                },
+            ),
+            (
+                "ee2n",
+                MoveStringError::PieceMismatch {
+                    step: 0,
+                    token: "ee2n".to_string(),
+                    square: e2,
+                },
+            ),
+            (
+                "Ee2n Ee3s Ee2s",
+                MoveStringError::IllegalStep {
+                    step: 2,
+                    action: action!(e2s),
+                },
+            ),
+            (
+                "Ee2n Ee3s",
+                MoveStringError::IllegalStep {
+                    step: 2,
+                    action: Action::Pass,
+                },
+            ),
+            (
+                "Ee2n Ee3n Ee4n Ee5n Ee6n",
+                MoveStringError::IllegalStep {
+                    step: 4,
+                    action: action!(e6n),
+                },
+            ),
+            (
+                "",
+                MoveStringError::IllegalStep {
+                    step: 0,
+                    action: Action::Pass,
+                },
+            ),
+            (
+                "Eé2n",
+                MoveStringError::BadToken {
+                    step: 0,
+                    token: "Eé2n".to_string(),
+                },
+            ),
+        ];
+
+        for (move_string, expected) in cases {
+            assert_eq!(
+                try_convert_move_string_to_actions(&game_state, move_string),
+                Err(expected),
+                "{}",
+                move_string
+            );
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use super::full_move::{convert_action_to_tokens, try_convert_move_string_to_actions};
+use super::{Action, GameState, Terminal, TerminalReason};
+use anyhow::{anyhow, Result};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A single move of a game record, made up of all of the steps or placements of a player's turn.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordMove {
+    pub move_number: usize,
+    pub is_p1: bool,
+    /// The actions of the move. Moves with fewer than four steps end with `Action::Pass`.
+    pub actions: Vec<Action>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GameResult {
+    pub winner: Terminal,
+    pub reason: TerminalReason,
+}
+
+/// A complete game parsed from a move list such as `1g Ra1 Rb1 ... 1s ra8 rb8 ... 2g Ed2n Ed3n ...`.
+///
+/// The move list supports:
+/// - Setup moves, `1g Ra1 Db1 ...`, in any square order.
+/// - Steps, `Ed2n`, with optional capture annotations, `Rc3x`.
+/// - Either `g`/`s` or `w`/`b` to denote the player of a move.
+/// - `resigns`, `timeout` or `forfeit` in place of the steps of a move.
+/// - A trailing result tag, `result <winner> <termination>`, where the termination is one of the
+///   arimaa.com codes `g`, `e`, `m`, `p`, `t`, `r`, `f` or `s`.
+/// - A trailing move number without any steps, which the archives use for the player to move at the end of the game.
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub moves: Vec<RecordMove>,
+    /// The state at the start of the game followed by the state after each move.
+    pub game_states: Vec<GameState>,
+    pub result: Option<GameResult>,
+}
+
+impl GameRecord {
+    /// Creates a record of the game played from the specified state by taking each of the actions.
+    /// Actions are grouped into moves each time the player to move changes.
+    pub fn from_actions(game_state: &GameState, actions: &[Action]) -> Self {
+        let mut game_state = game_state.clone();
+        let mut moves: Vec<RecordMove> = vec![];
+        let mut game_states = vec![game_state.clone()];
+        let mut move_is_complete = true;
+
+        for action in actions {
+            if move_is_complete {
+                moves.push(RecordMove {
+                    move_number: game_state.move_number(),
+                    is_p1: game_state.is_p1_turn_to_move(),
+                    actions: vec![],
+                });
+            }
+
+            let was_p1_turn_to_move = game_state.is_p1_turn_to_move();
+            game_state = game_state.take_action(action);
+            moves.last_mut().unwrap().actions.push(*action);
+
+            move_is_complete = was_p1_turn_to_move != game_state.is_p1_turn_to_move();
+            if move_is_complete {
+                game_states.push(game_state.clone());
+            }
+        }
+
+        // An incomplete final move is still recorded, along with the state it has reached.
+        if !move_is_complete {
+            game_states.push(game_state);
+        }
+
+        GameRecord {
+            moves,
+            game_states,
+            result: None,
+        }
+    }
+
+    /// Returns the state of the game after the last move.
+    pub fn final_state(&self) -> &GameState {
+        self.game_states
+            .last()
+            .expect("Game states should always contain the initial state")
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let move_number_regex = regex::Regex::new(r"^(\d+)([gswb])$").unwrap();
+
+        // The archives escape line breaks within the move list.
+        let s = s.replace("\\n", "\n");
+        let mut tokens = s.split_whitespace().peekable();
+
+        let mut game_state = GameState::initial();
+        let mut moves = vec![];
+        let mut game_states = vec![game_state.clone()];
+        let mut result = None;
+
+        while let Some(token) = tokens.next() {
+            if token.eq_ignore_ascii_case("result") {
+                let winner = tokens.next().ok_or_else(|| anyhow!("Missing winner"))?;
+                let termination = tokens
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing termination"))?;
+                result = Some(GameResult {
+                    winner: parse_winner(winner)?,
+                    reason: parse_termination(termination)?,
+                });
+                continue;
+            }
+
+            if result.is_some() {
+                return Err(anyhow!("Unexpected '{}' after the result", token));
+            }
+
+            let captures = move_number_regex
+                .captures(token)
+                .ok_or_else(|| anyhow!("Expected a move number but found '{}'", token))?;
+            let move_number: usize = captures.get(1).unwrap().as_str().parse()?;
+            let is_p1 = matches!(captures.get(2).unwrap().as_str(), "g" | "w");
+
+            if move_number != game_state.move_number() || is_p1 != game_state.is_p1_turn_to_move() {
+                return Err(anyhow!(
+                    "Move {} is out of order, expected {}{}",
+                    token,
+                    game_state.move_number(),
+                    if game_state.is_p1_turn_to_move() {
+                        "g"
+                    } else {
+                        "s"
+                    }
+                ));
+            }
+
+            let mut move_tokens = vec![];
+            while let Some(token) = tokens.peek() {
+                if move_number_regex.is_match(token) || token.eq_ignore_ascii_case("result") {
+                    break;
+                }
+                move_tokens.push(tokens.next().unwrap());
+            }
+
+            let ending = move_tokens.iter().find_map(|t| parse_ending(t));
+            if let Some(reason) = ending {
+                result = Some(GameResult {
+                    winner: if is_p1 {
+                        Terminal::SilverWin
+                    } else {
+                        Terminal::GoldWin
+                    },
+                    reason,
+                });
+                move_tokens.retain(|t| parse_ending(t).is_none());
+            }
+
+            if move_tokens.is_empty() {
+                let is_last_move = tokens
+                    .peek()
+                    .is_none_or(|token| token.eq_ignore_ascii_case("result"));
+                if is_last_move || ending.is_some() {
+                    continue;
+                }
+                return Err(anyhow!("Move {} has no steps", token));
+            }
+
+            let (actions, new_game_state) = take_move(&game_state, &move_tokens)
+                .map_err(|err| anyhow!("Invalid move {}: {}", token, err))?;
+
+            game_state = new_game_state;
+            moves.push(RecordMove {
+                move_number,
+                is_p1,
+                actions,
+            });
+            game_states.push(game_state.clone());
+        }
+
+        Ok(GameRecord {
+            moves,
+            game_states,
+            result,
+        })
+    }
+}
+
+/// Writes the record as a numbered move list with one move per line, followed by the result tag if there is a result.
+/// The output can be read back with `GameRecord::from_str`.
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, (record_move, game_state)) in
+            self.moves.iter().zip(self.game_states.iter()).enumerate()
+        {
+            if i != 0 {
+                writeln!(f)?;
+            }
+
+            write!(
+                f,
+                "{}{}",
+                record_move.move_number,
+                if record_move.is_p1 { "g" } else { "s" }
+            )?;
+
+            let mut game_state = game_state.clone();
+            for action in record_move.actions.iter() {
+                for token in convert_action_to_tokens(&game_state, action) {
+                    write!(f, " {}", token)?;
+                }
+                game_state = game_state.take_action(action);
+            }
+        }
+
+        if let Some(result) = &self.result {
+            if !self.moves.is_empty() {
+                writeln!(f)?;
+            }
+
+            write!(
+                f,
+                "result {} {}",
+                if result.winner == Terminal::GoldWin {
+                    "g"
+                } else {
+                    "s"
+                },
+                termination_code(result.reason)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn take_move(game_state: &GameState, tokens: &[&str]) -> Result<(Vec<Action>, GameState)> {
+    let actions = try_convert_move_string_to_actions(game_state, &tokens.join(" "))?;
+
+    let mut game_state = game_state.clone();
+    for action in actions.iter() {
+        game_state = game_state.take_action(action);
+    }
+
+    Ok((actions, game_state))
+}
+
+fn parse_ending(token: &str) -> Option<TerminalReason> {
+    match token.to_lowercase().as_str() {
+        "resign" | "resigns" => Some(TerminalReason::Resignation),
+        "timeout" => Some(TerminalReason::Timeout),
+        "forfeit" => Some(TerminalReason::Forfeit),
+        _ => None,
+    }
+}
+
+fn parse_winner(token: &str) -> Result<Terminal> {
+    match token {
+        "g" | "w" => Ok(Terminal::GoldWin),
+        "s" | "b" => Ok(Terminal::SilverWin),
+        _ => Err(anyhow!("Invalid winner '{}'", token)),
+    }
+}
+
+fn parse_termination(token: &str) -> Result<TerminalReason> {
+    match token {
+        "g" => Ok(TerminalReason::Goal),
+        "e" => Ok(TerminalReason::Elimination),
+        "m" => Ok(TerminalReason::Immobilization),
+        "p" => Ok(TerminalReason::Repetition),
+        "t" => Ok(TerminalReason::Timeout),
+        "r" => Ok(TerminalReason::Resignation),
+        "f" => Ok(TerminalReason::Forfeit),
+        "s" => Ok(TerminalReason::Score),
+        _ => Err(anyhow!("Invalid termination '{}'", token)),
+    }
+}
+
+fn termination_code(reason: TerminalReason) -> char {
+    match reason {
+        TerminalReason::Goal => 'g',
+        TerminalReason::Elimination => 'e',
+        TerminalReason::Immobilization => 'm',
+        TerminalReason::Repetition => 'p',
+        TerminalReason::Timeout => 't',
+        TerminalReason::Resignation => 'r',
+        TerminalReason::Forfeit => 'f',
+        TerminalReason::Score => 's',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{action, Action, GameState, Piece, Terminal, TerminalReason};
+    use super::{GameRecord, GameResult};
+
+    const SETUP: &str = "1g Ha2 Cb2 Dc2 Md2 Ee2 Df2 Cg2 Hh2 Ra1 Rb1 Rc1 Rd1 Re1 Rf1 Rg1 Rh1
+1s ra8 rb8 rc8 rd8 re8 rf8 rg8 rh8 ha7 cb7 dc7 md7 ee7 df7 cg7 hh7";
+
+    #[test]
+    fn test_parse_setup() {
+        let record: GameRecord = SETUP.parse().unwrap();
+
+        assert_eq!(record.moves.len(), 2);
+        assert_eq!(record.game_states.len(), 3);
+        assert_eq!(record.moves[0].actions.len(), 16);
+        assert_eq!(record.moves[0].actions[0], action!(h));
+        assert_eq!(record.moves[0].actions[8], action!(r));
+        assert!(record.moves[0].is_p1);
+        assert!(!record.moves[1].is_p1);
+
+        let final_state = record.final_state();
+        assert!(final_state.is_play_phase());
+        assert!(final_state.is_p1_turn_to_move());
+        assert_eq!(final_state.move_number(), 2);
+        assert_eq!(record.result, None);
+    }
+
+    #[test]
+    fn test_parse_setup_any_square_order() {
+        let record: GameRecord =
+            "1w Ee2 Ra1 Rb1 Rc1 Rd1 Re1 Rf1 Rg1 Rh1 Ha2 Cb2 Dc2 Md2 Df2 Cg2 Hh2"
+                .parse()
+                .unwrap();
+
+        assert_eq!(record.moves[0].actions[4], action!(e));
+    }
+
+    #[test]
+    fn test_parse_steps() {
+        let record: GameRecord = format!("{} 2g Ee2n Ee3n Ee4n 2s ee7s", SETUP)
+            .parse()
+            .unwrap();
+
+        assert_eq!(record.moves.len(), 4);
+        assert_eq!(
+            record.moves[2].actions,
+            vec![action!(e2n), action!(e3n), action!(e4n), action!(p)]
+        );
+        assert_eq!(record.moves[3].actions, vec![action!(e7s), action!(p)]);
+        assert_eq!(record.final_state().move_number(), 3);
+    }
+
+    #[test]
+    fn test_parse_single_line_with_w_b() {
+        let record: GameRecord = "1w Ra1 Rb1 Rc1 Rd1 Re1 Rf1 Rg1 Rh1 Ha2 Cb2 Dc2 Md2 Ee2 Df2 Cg2 Hh2 1b ra8 rb8 rc8 rd8 re8 rf8 rg8 rh8 ha7 cb7 dc7 md7 ee7 df7 cg7 hh7 2w Ee2n 2b"
+            .parse()
+            .unwrap();
+
+        assert_eq!(record.moves.len(), 3);
+        assert!(!record.final_state().is_p1_turn_to_move());
+    }
+
+    #[test]
+    fn test_parse_escaped_line_breaks() {
+        let record: GameRecord = SETUP.replace('\n', "\\n").parse().unwrap();
+
+        assert_eq!(record.moves.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_capture() {
+        let record: GameRecord = format!("{} 2g Dc2n Dc3x Ee2n", SETUP).parse().unwrap();
+
+        assert_eq!(record.moves.len(), 3);
+        assert_eq!(
+            record.moves[2].actions,
+            vec![action!(c2n), action!(e2n), action!(p)]
+        );
+
+        let gold_dogs = |game_state: &GameState| {
+            game_state
+                .piece_board()
+                .bits_for_piece(Piece::Dog, true)
+                .count()
+        };
+        assert_eq!(gold_dogs(&record.game_states[2]), 2);
+        assert_eq!(gold_dogs(record.final_state()), 1);
+    }
+
+    #[test]
+    fn test_parse_invalid_capture() {
+        let result = format!("{} 2g Ee2n Ee3nx", SETUP).parse::<GameRecord>();
+        assert!(result.is_err());
+
+        let result = format!("{} 2g Ee2n Ec3x", SETUP).parse::<GameRecord>();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_resignation() {
+        let record: GameRecord = format!("{} 2g Ee2n 2s resigns", SETUP).parse().unwrap();
+
+        assert_eq!(record.moves.len(), 3);
+        assert_eq!(
+            record.result,
+            Some(GameResult {
+                winner: Terminal::GoldWin,
+                reason: TerminalReason::Resignation
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_result_tag() {
+        let record: GameRecord = format!("{} 2g Ee2n 2s result g t", SETUP).parse().unwrap();
+
+        assert_eq!(
+            record.result,
+            Some(GameResult {
+                winner: Terminal::GoldWin,
+                reason: TerminalReason::Timeout
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_records() {
+        let invalid_records = [
+            "1g Ra2",
+            "2g Ee2n",
+            "1s ra7 rb7 rc7 rd7 re7 rf7 rg7 rh7 ha8 cb8 dc8 md8 ee8 df8 cg8 hh8",
+            "1g ra2 Rb2 Rc2 Rd2 Re2 Rf2 Rg2 Rh2 Ha1 Cb1 Dc1 Md1 Ee1 Df1 Cg1 Hh1",
+            "1g Ea2 Rb2 Rc2 Rd2 Re2 Rf2 Rg2 Rh2 Ha1 Cb1 Dc1 Md1 Ee1 Df1 Cg1 Hh1",
+        ];
+
+        for record in invalid_records.iter() {
+            assert!(record.parse::<GameRecord>().is_err(), "{}", record);
+        }
+
+        let invalid_moves = [
+            "2g Ee2s",
+            "2g Me2n",
+            "2g ee2n",
+            "2g Ra1s",
+            "2g Ee2n Ee3n Ee4n Ee5n Ee6n",
+            "2s ra7s",
+            "2g Ee2n 2s 3g",
+            "2g Ee2n result x g",
+        ];
+
+        for moves in invalid_moves.iter() {
+            let record = format!("{} {}", SETUP, moves);
+            assert!(record.parse::<GameRecord>().is_err(), "{}", moves);
+        }
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let record: GameRecord = format!("{} 2g Dc2n Dc3x Ee2n 2s ee7s ee6s result g r", SETUP)
+            .parse()
+            .unwrap();
+
+        let expected = format!("{}\n2g Dc2n Dc3x Ee2n\n2s ee7s ee6s\nresult g r", SETUP);
+        assert_eq!(record.to_string(), expected);
+
+        let reparsed: GameRecord = record.to_string().parse().unwrap();
+        assert_eq!(reparsed.moves, record.moves);
+        assert_eq!(reparsed.result, record.result);
+    }
+
+    #[test]
+    fn test_write_from_actions() {
+        let record: GameRecord = SETUP.parse().unwrap();
+        let game_state = record.final_state();
+
+        let actions = [
+            action!(c2n),
+            action!(e2n),
+            action!(e3n),
+            action!(p),
+            action!(e7s),
+            action!(e6s),
+            action!(d7s),
+            action!(d6s),
+            action!(a2n),
+        ];
+
+        let record = GameRecord::from_actions(game_state, &actions);
+
+        assert_eq!(record.moves.len(), 3);
+        assert_eq!(record.game_states.len(), 4);
+        assert_eq!(
+            record.to_string(),
+            "2g Dc2n Dc3x Ee2n Ee3n\n2s ee7s ee6s md7s md6s\n3g Ha2n"
+        );
+    }
+
+    #[test]
+    fn test_write_setup_from_actions() {
+        let game_state = GameState::initial();
+        let actions = "e m h h d d c c r r r r r r r r"
+            .split(' ')
+            .map(|a| a.parse().unwrap())
+            .collect::<Vec<Action>>();
+
+        let mut record = GameRecord::from_actions(&game_state, &actions);
+        record.result = Some(GameResult {
+            winner: Terminal::SilverWin,
+            reason: TerminalReason::Forfeit,
+        });
+
+        assert_eq!(
+            record.to_string(),
+            "1g Ea2 Mb2 Hc2 Hd2 De2 Df2 Cg2 Ch2 Ra1 Rb1 Rc1 Rd1 Re1 Rf1 Rg1 Rh1\nresult s f"
+        );
+    }
+}
@@ -0,0 +1,671 @@
+use super::bit_mask::{P1_OBJECTIVE_MASK, P2_OBJECTIVE_MASK, TRAP_MASK};
+use super::engine::{
+    can_move_in_direction, influenced_squares, lesser_pieces, piece_type_at_bit,
+    shift_pieces_in_direction, shift_pieces_in_opp_direction, supported_pieces, threatened_pieces,
+};
+use super::{
+    Action, BitBoard, Direction, GameState, Piece, PieceBoardState, PushPullState, Square,
+};
+use std::collections::HashSet;
+
+/// The number of steps in a full move.
+pub(crate) const STEPS_PER_MOVE: usize = 4;
+
+/// Searches for a move of at most `max_steps` steps which gets one of the player's rabbits to goal, as if the player were to move
+/// at the start of a move on the board. Useful for checking the goal threats of the opponent of the player to move.
+/// See `GameState::goal_search`.
+pub fn find_goal(
+    piece_board: &PieceBoardState,
+    p1_to_move: bool,
+    max_steps: usize,
+) -> Option<Vec<Action>> {
+    let move_state = MoveState::new(piece_board.clone(), p1_to_move, PushPullState::None);
+
+    GoalSearch::new(piece_board.clone()).find(&move_state, max_steps.min(STEPS_PER_MOVE))
+}
+
+impl GameState {
+    /// Searches for a sequence of at most `max_steps` steps which gets one of the current player's rabbits to goal by the end of the move.
+    /// Only the steps remaining in the move from `current_step()` are available, so at most `4 - current_step()` steps are used.
+    ///
+    /// Returns the shortest sequence of steps found, or None if no goal is reachable. Freezing, pushes and pulls that clear the way for the
+    /// rabbit are all considered. The returned steps do not include the pass needed to end the move when fewer than four steps were taken.
+    pub fn goal_search(&self, max_steps: usize) -> Option<Vec<Action>> {
+        let play_phase = self.as_play_phase()?;
+        if self.is_terminal().is_some() {
+            return None;
+        }
+
+        let remaining_steps = max_steps.min(STEPS_PER_MOVE - play_phase.step());
+        let move_state = MoveState::new(
+            self.piece_board().clone(),
+            self.is_p1_turn_to_move(),
+            play_phase.push_pull_state(),
+        );
+
+        GoalSearch::new(self.piece_board_for_step(0).clone()).find(&move_state, remaining_steps)
+    }
+}
+
+/// The board and push/pull state part way through a move, which are all that the legality of the remaining steps depends on.
+/// Repetitions are not tracked, so a step which would repeat a position for the third time is not excluded.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct MoveState {
+    pub(crate) piece_board: PieceBoardState,
+    pub(crate) p1_to_move: bool,
+    pub(crate) push_pull_state: PushPullState,
+}
+
+impl MoveState {
+    pub(crate) fn new(
+        piece_board: PieceBoardState,
+        p1_to_move: bool,
+        push_pull_state: PushPullState,
+    ) -> Self {
+        MoveState {
+            piece_board,
+            p1_to_move,
+            push_pull_state,
+        }
+    }
+
+    /// Returns the steps which can be taken with the number of steps remaining in the move.
+    /// Pushes are only started when a step remains to complete them.
+    pub(crate) fn steps(&self, remaining_steps: usize) -> Vec<Action> {
+        let piece_board = &self.piece_board;
+        let opp_pieces = piece_board.player_pieces(!self.p1_to_move);
+        let non_frozen_pieces = piece_board.non_frozen_piece_bits(self.p1_to_move);
+        let mut steps = Vec::new();
+
+        if let PushPullState::MustCompletePush(square, pushed_piece) = self.push_pull_state {
+            for direction in Direction::ALL {
+                let pushing_piece_bit =
+                    shift_pieces_in_opp_direction(square.as_bit_board(), &direction)
+                        & non_frozen_pieces;
+
+                if pushing_piece_bit != 0
+                    && piece_type_at_bit(pushing_piece_bit, piece_board) > pushed_piece
+                {
+                    steps.push(Action::Move(
+                        Square::from_bit_board(pushing_piece_bit),
+                        direction,
+                    ));
+                }
+            }
+
+            return steps;
+        }
+
+        let backward_direction = if self.p1_to_move {
+            Direction::Down
+        } else {
+            Direction::Up
+        };
+        let pushable_pieces = if remaining_steps >= 2 {
+            threatened_pieces(non_frozen_pieces, opp_pieces, piece_board)
+        } else {
+            0
+        };
+
+        for direction in Direction::ALL {
+            let backward_rabbits = if direction == backward_direction {
+                piece_board.rabbits().bits()
+            } else {
+                0
+            };
+            let pulled_pieces = match self.push_pull_state {
+                PushPullState::PossiblePull(square, piece) => {
+                    shift_pieces_in_opp_direction(square.as_bit_board(), &direction)
+                        & lesser_pieces(piece, piece_board)
+                        & opp_pieces
+                }
+                _ => 0,
+            };
+            let moving_pieces =
+                (non_frozen_pieces & !backward_rabbits) | pushable_pieces | pulled_pieces;
+
+            for square in
+                BitBoard::new(moving_pieces & can_move_in_direction(&direction, piece_board))
+            {
+                steps.push(Action::Move(square, direction));
+            }
+        }
+
+        steps
+    }
+
+    /// Takes a step returned by `steps`, removing any captured pieces. A step by an opponent piece which can't be counted as
+    /// a pull is a push, which must be completed by the next step.
+    pub(crate) fn take_step(&self, square: Square, direction: Direction) -> MoveState {
+        let piece_board = &self.piece_board;
+        let square_bit = square.as_bit_board();
+        let piece = piece_type_at_bit(square_bit, piece_board);
+        let is_opp_piece = piece_board.player_pieces(!self.p1_to_move) & square_bit != 0;
+
+        let push_pull_state = match self.push_pull_state {
+            PushPullState::MustCompletePush(_, _) => PushPullState::None,
+            PushPullState::PossiblePull(pull_square, pulling_piece)
+                if is_opp_piece
+                    && pulling_piece > piece
+                    && shift_pieces_in_direction(square_bit, &direction)
+                        == pull_square.as_bit_board() =>
+            {
+                PushPullState::None
+            }
+            _ if is_opp_piece => PushPullState::MustCompletePush(square, piece),
+            _ if piece != Piece::Rabbit => PushPullState::PossiblePull(square, piece),
+            _ => PushPullState::None,
+        };
+
+        MoveState {
+            piece_board: piece_board.take_step(&square, &direction),
+            p1_to_move: self.p1_to_move,
+            push_pull_state,
+        }
+    }
+
+    /// True unless a push is waiting to be completed.
+    pub(crate) fn can_end_move(&self) -> bool {
+        !matches!(self.push_pull_state, PushPullState::MustCompletePush(_, _))
+    }
+}
+
+struct GoalSearch {
+    initial_piece_board: PieceBoardState,
+    failed_states: HashSet<(MoveState, usize)>,
+    actions: Vec<Action>,
+}
+
+impl GoalSearch {
+    fn new(initial_piece_board: PieceBoardState) -> Self {
+        GoalSearch {
+            initial_piece_board,
+            failed_states: HashSet::new(),
+            actions: Vec::with_capacity(STEPS_PER_MOVE),
+        }
+    }
+
+    fn find(&mut self, move_state: &MoveState, max_steps: usize) -> Option<Vec<Action>> {
+        let rabbit_run = rabbit_run(move_state, max_steps);
+
+        // Iterative deepening returns the shortest goal. A run of a rabbit on its own is the shortest once no goal with fewer steps was found.
+        // Failed states are kept between depths since they record the steps that were remaining.
+        (0..=max_steps).find_map(|max_depth| match &rabbit_run {
+            Some(rabbit_run) if rabbit_run.len() == max_depth => Some(rabbit_run.clone()),
+            _ if self.search(move_state, max_depth) => Some(self.actions.clone()),
+            _ => None,
+        })
+    }
+
+    fn search(&mut self, move_state: &MoveState, remaining_steps: usize) -> bool {
+        if self.is_goal(move_state) {
+            return true;
+        }
+
+        let p1_to_move = move_state.p1_to_move;
+        let reachable_rabbits =
+            rabbits_in_reach(&move_state.piece_board, p1_to_move, remaining_steps);
+        if remaining_steps == 0
+            || reachable_rabbits == 0
+            || self
+                .failed_states
+                .contains(&(move_state.clone(), remaining_steps))
+        {
+            return false;
+        }
+
+        // Without a step to spare, every remaining step must advance one of the rabbits furthest from goal.
+        let must_advance_rabbit =
+            rabbits_in_reach(&move_state.piece_board, p1_to_move, remaining_steps - 1) == 0;
+        let forward_direction = if p1_to_move {
+            Direction::Up
+        } else {
+            Direction::Down
+        };
+
+        for action in move_state.steps(remaining_steps) {
+            let (square, direction) = match action {
+                Action::Move(square, direction) => (square, direction),
+                _ => continue,
+            };
+
+            if must_advance_rabbit
+                && (direction != forward_direction
+                    || square.as_bit_board() & reachable_rabbits == 0)
+            {
+                continue;
+            }
+
+            self.actions.push(action);
+            if self.search(
+                &move_state.take_step(square, direction),
+                remaining_steps - 1,
+            ) {
+                return true;
+            }
+            self.actions.pop();
+        }
+
+        self.failed_states
+            .insert((move_state.clone(), remaining_steps));
+        false
+    }
+
+    /// True if the move can end with one of the player's rabbits at goal. A move must change the board, so ending on the board the move started
+    /// from is not a goal even when a rabbit was already on the goal row.
+    fn is_goal(&self, move_state: &MoveState) -> bool {
+        move_state.can_end_move()
+            && rabbits_in_reach(&move_state.piece_board, move_state.p1_to_move, 0) != 0
+            && move_state.piece_board != self.initial_piece_board
+    }
+}
+
+/// Finds the shortest walk of one of the player's rabbits to goal with no other piece moving. The walk only crosses empty squares,
+/// never steps onto a trap without a friendly piece next to it, and can't continue from a square where the rabbit is frozen.
+fn rabbit_run(move_state: &MoveState, max_steps: usize) -> Option<Vec<Action>> {
+    let piece_board = &move_state.piece_board;
+    let p1_to_move = move_state.p1_to_move;
+    if !move_state.can_end_move() {
+        return None;
+    }
+
+    let runners = rabbits_in_reach(piece_board, p1_to_move, max_steps)
+        & piece_board.non_frozen_piece_bits(p1_to_move);
+
+    BitBoard::new(runners)
+        .iter()
+        .filter_map(|square| rabbit_run_from(piece_board, p1_to_move, square, max_steps))
+        .min_by_key(|actions| actions.len())
+}
+
+fn rabbit_run_from(
+    piece_board: &PieceBoardState,
+    p1_to_move: bool,
+    square: Square,
+    max_steps: usize,
+) -> Option<Vec<Action>> {
+    let square_bit = square.as_bit_board();
+    let friendly_pieces = piece_board.player_pieces(p1_to_move) & !square_bit;
+    // A friendly piece on a trap which only the rabbit kept alive is captured by its first step.
+    let friendly_pieces = friendly_pieces & !(TRAP_MASK & !supported_pieces(friendly_pieces));
+    let opp_pieces = piece_board.player_pieces(!p1_to_move);
+
+    let supported_squares = influenced_squares(friendly_pieces);
+    let empty_squares = !(friendly_pieces | opp_pieces);
+    let safe_squares = empty_squares & !(TRAP_MASK & !supported_squares);
+    let frozen_squares =
+        influenced_squares(opp_pieces & !piece_board.rabbits().bits()) & !supported_squares;
+
+    let (directions, goal_mask) = if p1_to_move {
+        (
+            [Direction::Up, Direction::Right, Direction::Left],
+            P1_OBJECTIVE_MASK,
+        )
+    } else {
+        (
+            [Direction::Down, Direction::Right, Direction::Left],
+            P2_OBJECTIVE_MASK,
+        )
+    };
+    // The rabbit can step from its starting square, which the caller checked it isn't frozen on.
+    let movable = |squares: u64, steps: usize| {
+        if steps == 0 {
+            squares
+        } else {
+            squares & !frozen_squares
+        }
+    };
+
+    // Each entry holds the squares the rabbit can be on after that many steps.
+    let mut reached = vec![square_bit];
+    while reached.len() <= max_steps {
+        let steps = reached.len() - 1;
+        let from_squares = movable(reached[steps], steps);
+        let next_squares = directions.iter().fold(0, |next_squares, direction| {
+            next_squares | shift_pieces_in_direction(from_squares, direction)
+        }) & safe_squares;
+
+        if next_squares == 0 {
+            return None;
+        }
+
+        if next_squares & goal_mask == 0 {
+            reached.push(next_squares);
+            continue;
+        }
+
+        // Walk back from the goal, finding a square the rabbit could have stepped from at each step.
+        let mut target = BitBoard::new(next_squares & goal_mask)
+            .first()
+            .unwrap()
+            .as_bit_board();
+        let mut actions = Vec::with_capacity(reached.len());
+        for (steps, squares) in reached.iter().enumerate().rev() {
+            let (from, direction) = directions
+                .iter()
+                .map(|direction| {
+                    let from = shift_pieces_in_opp_direction(target, direction);
+                    (from & movable(*squares, steps), *direction)
+                })
+                .find(|(from, _)| *from != 0)
+                .unwrap();
+
+            actions.push(Action::Move(Square::from_bit_board(from), direction));
+            target = from;
+        }
+
+        actions.reverse();
+        return Some(actions);
+    }
+
+    None
+}
+
+/// Returns the player's rabbits which are close enough to their goal row to reach it in the number of steps.
+fn rabbits_in_reach(piece_board: &PieceBoardState, is_p1: bool, steps: usize) -> u64 {
+    piece_board.bits_for_piece(Piece::Rabbit, is_p1).bits() & goal_rows_mask(is_p1, steps + 1)
+}
+
+/// Returns the squares of the rows closest to the player's goal, including the goal row itself.
+fn goal_rows_mask(is_p1: bool, rows: usize) -> u64 {
+    if rows >= 8 {
+        u64::MAX
+    } else if is_p1 {
+        // Gold's goal is the top row, which holds the lowest bits.
+        (1 << (8 * rows)) - 1
+    } else {
+        u64::MAX << (8 * (8 - rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::{random_positions, start_of_move};
+    use super::super::{action, take_actions, GameState, Terminal};
+    use super::*;
+
+    fn assert_goal(game_state: &GameState, actions: &[Action]) {
+        let mut game_state = game_state.clone();
+        let winner = if game_state.is_p1_turn_to_move() {
+            Terminal::GoldWin
+        } else {
+            Terminal::SilverWin
+        };
+
+        for action in actions {
+            game_state = game_state.take_action(action);
+        }
+
+        if game_state.current_step() != 0 {
+            game_state = game_state.take_action(&Action::Pass);
+        }
+
+        assert_eq!(game_state.is_terminal(), Some(winner));
+    }
+
+    fn rabbit_near_goal() -> GameState {
+        "
+             2g
+              +-----------------+
+             8|           r     |
+             7|                 |
+             6|   R x   e x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    fn blocked_rabbit() -> GameState {
+        "
+             2g
+              +-----------------+
+             8| c E             |
+             7|                 |
+             6| R   x     x   r |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_goal_search() {
+        let game_state = rabbit_near_goal();
+        let actions = game_state.goal_search(4).unwrap();
+
+        assert_eq!(actions, vec![action!(b6n), action!(b7n)]);
+        assert_goal(&game_state, &actions);
+
+        assert_eq!(game_state.goal_search(1), None);
+    }
+
+    #[test]
+    fn test_goal_search_uses_remaining_steps() {
+        let game_state = take_actions!(rabbit_near_goal() => b6w);
+
+        assert_eq!(
+            game_state.goal_search(4),
+            Some(vec![action!(a6n), action!(a7n)])
+        );
+
+        let game_state = take_actions!(game_state => a6e, b6w);
+        assert_eq!(game_state.goal_search(4), None);
+    }
+
+    #[test]
+    fn test_goal_search_pulls_blocker() {
+        let game_state = blocked_rabbit();
+
+        // The cat must be pulled off the goal square, after which the elephant keeps the rabbit from being frozen.
+        assert_eq!(game_state.goal_search(3), None);
+
+        let actions = game_state.goal_search(4).unwrap();
+        assert_eq!(actions.len(), 4);
+        assert_goal(&game_state, &actions);
+    }
+
+    #[test]
+    fn test_goal_search_unfreezes_rabbit() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|                 |
+             7|   R d           |
+             6|     x     x   r |
+             5|   E             |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        assert_eq!(game_state.goal_search(1), None);
+        assert_eq!(
+            game_state.goal_search(4),
+            Some(vec![action!(b5n), action!(b7n)])
+        );
+    }
+
+    #[test]
+    fn test_goal_search_at_goal_mid_move() {
+        let game_state = take_actions!(rabbit_near_goal() => b6n, b7n);
+
+        assert_eq!(game_state.goal_search(4), Some(vec![]));
+    }
+
+    #[test]
+    fn test_find_goal_for_opponent() {
+        let game_state = blocked_rabbit();
+
+        // Silver's rabbit is five rows from goal.
+        assert_eq!(find_goal(game_state.piece_board(), false, 4), None);
+
+        let game_state: GameState = game_state
+            .to_string()
+            .replacen("6| R   x     x   r |", "6| R   x     x     |", 1)
+            .replacen("3|     x     x     |", "3|     x     x   r |", 1)
+            .parse()
+            .unwrap();
+
+        let actions = find_goal(game_state.piece_board(), false, 4).unwrap();
+        assert_eq!(actions, vec![action!(h3s), action!(h2s)]);
+    }
+
+    #[test]
+    fn test_rabbit_run_avoids_unsupported_trap() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|               r |
+             7|                 |
+             6|     x     x     |
+             5|     R           |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        let move_state =
+            MoveState::new(game_state.piece_board().clone(), true, PushPullState::None);
+
+        // The rabbit would be captured on c6, so it goes around the trap.
+        let actions = rabbit_run(&move_state, 4).unwrap();
+        assert_eq!(
+            actions,
+            vec![action!(c5w), action!(b5n), action!(b6n), action!(b7n)]
+        );
+        assert_eq!(rabbit_run(&move_state, 3), None);
+        assert_goal(&game_state, &actions);
+
+        // A friendly piece next to the trap keeps the rabbit alive on it.
+        let game_state: GameState = game_state
+            .to_string()
+            .replacen("6|     x     x     |", "6|     x D   x     |", 1)
+            .parse()
+            .unwrap();
+        let move_state =
+            MoveState::new(game_state.piece_board().clone(), true, PushPullState::None);
+
+        assert_eq!(
+            rabbit_run(&move_state, 4),
+            Some(vec![action!(c5n), action!(c6n), action!(c7n)])
+        );
+    }
+
+    /// Finds the length of the shortest goal by trying every valid action of the state, which the search on the masks is checked against.
+    fn shortest_goal_by_valid_actions(game_state: &GameState, max_steps: usize) -> Option<usize> {
+        let remaining_steps = max_steps.min(STEPS_PER_MOVE - game_state.current_step());
+        let p1_to_move = game_state.is_p1_turn_to_move();
+
+        if game_state.is_terminal().is_some() {
+            return None;
+        }
+
+        (0..=remaining_steps)
+            .find(|steps| has_goal_by_valid_actions(game_state, p1_to_move, *steps))
+    }
+
+    fn has_goal_by_valid_actions(
+        game_state: &GameState,
+        p1_to_move: bool,
+        remaining_steps: usize,
+    ) -> bool {
+        let is_goal = |game_state: &GameState| {
+            let winner = if p1_to_move {
+                Terminal::GoldWin
+            } else {
+                Terminal::SilverWin
+            };
+
+            rabbits_in_reach(game_state.piece_board(), p1_to_move, 0) != 0
+                && game_state.is_terminal() == Some(winner)
+        };
+
+        if game_state.is_p1_turn_to_move() != p1_to_move {
+            return is_goal(game_state);
+        }
+
+        if game_state.can_pass(true) && is_goal(&game_state.take_action(&Action::Pass)) {
+            return true;
+        }
+
+        remaining_steps > 0
+            && game_state
+                .valid_actions()
+                .iter()
+                .filter(|action| **action != Action::Pass)
+                .any(|action| {
+                    has_goal_by_valid_actions(
+                        &game_state.take_action(action),
+                        p1_to_move,
+                        remaining_steps - 1,
+                    )
+                })
+    }
+
+    #[test]
+    fn test_goal_search_matches_valid_actions() {
+        let mut goals = 0;
+
+        for game_state in random_positions(40) {
+            let mid_move_state = game_state
+                .valid_actions()
+                .first()
+                .map(|action| game_state.take_action(action));
+
+            for game_state in std::iter::once(game_state).chain(mid_move_state) {
+                let actions = game_state.goal_search(4);
+                assert_eq!(
+                    actions.as_ref().map(|actions| actions.len()),
+                    shortest_goal_by_valid_actions(&game_state, 4),
+                    "{}",
+                    game_state
+                );
+
+                if let Some(actions) = actions {
+                    assert_goal(&game_state, &actions);
+                    goals += 1;
+                }
+            }
+        }
+
+        assert!(goals > 10, "Only {} of the positions had a goal", goals);
+    }
+
+    #[test]
+    fn test_find_goal_matches_valid_actions() {
+        for game_state in random_positions(20) {
+            let p1_to_move = !game_state.is_p1_turn_to_move();
+            let opponent_state = start_of_move(game_state.piece_board(), p1_to_move);
+
+            assert_eq!(
+                find_goal(game_state.piece_board(), p1_to_move, 4).map(|actions| actions.len()),
+                shortest_goal_by_valid_actions(&opponent_state, 4),
+                "{}",
+                opponent_state
+            );
+        }
+    }
+
+    #[test]
+    fn test_goal_search_setup_phase() {
+        assert_eq!(GameState::initial().goal_search(4), None);
+    }
+}
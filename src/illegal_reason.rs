@@ -0,0 +1,83 @@
+use super::Piece;
+use std::fmt;
+
+/// The reason an action is not valid, as returned by `GameState::check_action`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IllegalReason {
+    /// Pieces can only be placed during the setup phase and steps or passes can only be taken during the play phase.
+    WrongPhase,
+    /// All pieces of this type have already been placed by the player.
+    NoPiecesRemaining(Piece),
+    /// There is no piece on the square to move.
+    NoPieceAtSquare,
+    /// The step would move the piece off of the board.
+    OffBoard,
+    /// The square being moved to is already occupied.
+    SquareOccupied,
+    /// The piece is next to a stronger opponent piece and has no adjacent friendly piece.
+    PieceFrozen,
+    /// Rabbits cannot move backwards.
+    RabbitMovingBackwards,
+    /// An opponent piece was pushed in the previous step. The next step must move a piece into the vacated square.
+    MustCompletePush,
+    /// The piece completing a push must be stronger than the pushed piece.
+    PushRequiresStrongerPiece,
+    /// An opponent piece can only be moved by pushing or pulling it with an adjacent stronger piece.
+    NoStrongerPieceToPushOrPull,
+    /// A push takes two steps and this is the last step of the move.
+    NotEnoughStepsForPush,
+    /// The previous step completed a push so it cannot also be used to pull.
+    PullNotAllowedAfterPush,
+    /// A move must contain at least one step before passing.
+    PassOnFirstStep,
+    /// The move would result in a position occurring for the third time.
+    ThirdTimeRepetition,
+    /// The move would result in the same position as the start of the move.
+    SamePositionAsStartOfMove,
+}
+
+impl fmt::Display for IllegalReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IllegalReason::WrongPhase => {
+                write!(f, "The action is not valid in this phase of the game")
+            }
+            IllegalReason::NoPiecesRemaining(piece) => {
+                write!(f, "All pieces of type {} have already been placed", piece)
+            }
+            IllegalReason::NoPieceAtSquare => write!(f, "There is no piece on the square"),
+            IllegalReason::OffBoard => write!(f, "The piece cannot move off of the board"),
+            IllegalReason::SquareOccupied => write!(f, "The square is already occupied"),
+            IllegalReason::PieceFrozen => write!(f, "The piece is frozen"),
+            IllegalReason::RabbitMovingBackwards => write!(f, "Rabbits cannot move backwards"),
+            IllegalReason::MustCompletePush => write!(f, "The push must be completed"),
+            IllegalReason::PushRequiresStrongerPiece => {
+                write!(f, "The push must be completed by a stronger piece")
+            }
+            IllegalReason::NoStrongerPieceToPushOrPull => {
+                write!(f, "There is no stronger piece to push or pull the piece")
+            }
+            IllegalReason::NotEnoughStepsForPush => {
+                write!(f, "There are not enough steps remaining to complete a push")
+            }
+            IllegalReason::PullNotAllowedAfterPush => {
+                write!(
+                    f,
+                    "A pull cannot be made with the piece that completed a push"
+                )
+            }
+            IllegalReason::PassOnFirstStep => write!(f, "A pass cannot be made on the first step"),
+            IllegalReason::ThirdTimeRepetition => {
+                write!(f, "The move would repeat a position for the third time")
+            }
+            IllegalReason::SamePositionAsStartOfMove => {
+                write!(
+                    f,
+                    "The move would return to the position at the start of the move"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for IllegalReason {}
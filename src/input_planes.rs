@@ -0,0 +1,361 @@
+use super::bit_mask::TRAP_MASK;
+use super::{BitBoard, GameState, Piece, PieceBoardState, PushPullState};
+
+/// The number of squares in each plane.
+pub const PLANE_SIZE: usize = 64;
+
+/// The number of planes for the pieces of a single board, six for the current player followed by six for the opponent.
+pub const PIECE_PLANES: usize = 12;
+
+/// The number of planes encoded without the previous piece boards of the move.
+pub const BASE_INPUT_PLANES: usize = PIECE_PLANES + 16;
+
+/// The number of planes used to encode the previous piece boards of the move, one set of piece planes for each of the previous three steps.
+pub const PREVIOUS_BOARD_PLANES: usize = PIECE_PLANES * 3;
+
+/// The index of the side to move plane. Every square is set when gold is to move.
+pub const SIDE_TO_MOVE_PLANE: usize = PIECE_PLANES;
+/// The index of the first of four step planes. Every square of the plane for the current step is set.
+pub const STEP_PLANE: usize = SIDE_TO_MOVE_PLANE + 1;
+/// The index of the plane marking the square of a piece which may be pulled by the next step.
+pub const POSSIBLE_PULL_SQUARE_PLANE: usize = STEP_PLANE + 4;
+/// The index of the plane marking the square which must be occupied to complete a push.
+pub const MUST_COMPLETE_PUSH_SQUARE_PLANE: usize = POSSIBLE_PULL_SQUARE_PLANE + 1;
+/// The index of the first of six planes for the piece type of a pending push or pull. Every square of the plane for the piece is set.
+pub const PUSH_PULL_PIECE_PLANE: usize = MUST_COMPLETE_PUSH_SQUARE_PLANE + 1;
+/// The index of the plane marking the trap squares.
+pub const TRAP_PLANE: usize = PUSH_PULL_PIECE_PLANE + 6;
+/// The index of the plane marking the current player's frozen pieces, followed by the plane for the opponent's frozen pieces.
+pub const FROZEN_PLANE: usize = TRAP_PLANE + 1;
+/// The index of the first plane of the previous piece boards, when they are included.
+pub const PREVIOUS_BOARD_PLANE: usize = BASE_INPUT_PLANES;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct InputPlaneOptions {
+    /// When true, the piece boards of the previous steps of the move are encoded after the base planes, most recent first.
+    /// Steps that have not been taken are left empty.
+    pub include_previous_piece_boards: bool,
+}
+
+impl InputPlaneOptions {
+    /// The number of planes encoded with these options.
+    pub fn num_planes(&self) -> usize {
+        if self.include_previous_piece_boards {
+            BASE_INPUT_PLANES + PREVIOUS_BOARD_PLANES
+        } else {
+            BASE_INPUT_PLANES
+        }
+    }
+
+    /// The number of values encoded with these options.
+    pub fn input_len(&self) -> usize {
+        self.num_planes() * PLANE_SIZE
+    }
+}
+
+impl GameState {
+    /// Encodes the state as a set of 8x8 input planes for a neural network, laid out plane by plane.
+    ///
+    /// The state is encoded from the perspective of the player to move. Piece planes list the current player's pieces first,
+    /// and when silver is to move the board is flipped vertically so that silver's pieces start at the bottom of each plane.
+    /// See the `*_PLANE` constants for the layout.
+    pub fn input_planes(&self, options: &InputPlaneOptions) -> Vec<f32> {
+        let mut planes = vec![0.0; options.input_len()];
+        self.encode_input_planes(options, &mut planes);
+        planes
+    }
+
+    /// Encodes the state the same as `input_planes` into an existing slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the slice is not `options.input_len()`.
+    pub fn encode_input_planes(&self, options: &InputPlaneOptions, planes: &mut [f32]) {
+        assert_eq!(
+            planes.len(),
+            options.input_len(),
+            "Expected a slice of length {}",
+            options.input_len()
+        );
+
+        planes.fill(0.0);
+
+        let is_p1 = self.is_p1_turn_to_move();
+        let piece_board = self.piece_board();
+
+        encode_piece_planes(piece_board, is_p1, &mut planes[..PIECE_PLANES * PLANE_SIZE]);
+
+        if is_p1 {
+            plane_mut(planes, SIDE_TO_MOVE_PLANE).fill(1.0);
+        }
+
+        set_bits(
+            plane_mut(planes, TRAP_PLANE),
+            BitBoard::new(TRAP_MASK),
+            is_p1,
+        );
+        set_bits(
+            plane_mut(planes, FROZEN_PLANE),
+            piece_board.frozen_pieces(is_p1),
+            is_p1,
+        );
+        set_bits(
+            plane_mut(planes, FROZEN_PLANE + 1),
+            piece_board.frozen_pieces(!is_p1),
+            is_p1,
+        );
+
+        let play_phase = match self.as_play_phase() {
+            Some(play_phase) => play_phase,
+            None => return,
+        };
+
+        let step = play_phase.step();
+        plane_mut(planes, STEP_PLANE + step).fill(1.0);
+
+        let push_pull = match play_phase.push_pull_state() {
+            PushPullState::PossiblePull(square, piece) => {
+                Some((POSSIBLE_PULL_SQUARE_PLANE, square, piece))
+            }
+            PushPullState::MustCompletePush(square, piece) => {
+                Some((MUST_COMPLETE_PUSH_SQUARE_PLANE, square, piece))
+            }
+            PushPullState::None => None,
+        };
+
+        if let Some((square_plane, square, piece)) = push_pull {
+            set_bits(
+                plane_mut(planes, square_plane),
+                BitBoard::from(square),
+                is_p1,
+            );
+            plane_mut(planes, PUSH_PULL_PIECE_PLANE + piece.index()).fill(1.0);
+        }
+
+        if options.include_previous_piece_boards {
+            let previous_piece_boards = play_phase.previous_piece_boards();
+
+            for (i, previous_piece_board) in previous_piece_boards.iter().rev().enumerate() {
+                let start = (PREVIOUS_BOARD_PLANE + i * PIECE_PLANES) * PLANE_SIZE;
+                let end = start + PIECE_PLANES * PLANE_SIZE;
+                encode_piece_planes(
+                    previous_piece_board.piece_board(),
+                    is_p1,
+                    &mut planes[start..end],
+                );
+            }
+        }
+    }
+}
+
+fn encode_piece_planes(piece_board: &PieceBoardState, is_p1: bool, planes: &mut [f32]) {
+    for piece in Piece::ALL.iter() {
+        let idx = piece.index();
+        set_bits(
+            plane_mut(planes, idx),
+            piece_board.bits_for_piece(*piece, is_p1),
+            is_p1,
+        );
+        set_bits(
+            plane_mut(planes, idx + 6),
+            piece_board.bits_for_piece(*piece, !is_p1),
+            is_p1,
+        );
+    }
+}
+
+fn plane_mut(planes: &mut [f32], plane: usize) -> &mut [f32] {
+    &mut planes[plane * PLANE_SIZE..(plane + 1) * PLANE_SIZE]
+}
+
+/// Sets a square of the plane for each bit. When encoding for silver the rows are flipped.
+fn set_bits(plane: &mut [f32], bits: BitBoard, is_p1: bool) {
+    for square in bits {
+        let square_idx = if is_p1 {
+            square.index()
+        } else {
+            square.index() ^ 56
+        };
+
+        plane[square_idx] = 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::initial_play_state;
+    use super::super::{take_actions, GameState};
+    use super::*;
+
+    fn plane(planes: &[f32], plane: usize) -> Vec<usize> {
+        planes[plane * PLANE_SIZE..(plane + 1) * PLANE_SIZE]
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0.0)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn is_filled(planes: &[f32], plane: usize) -> bool {
+        planes[plane * PLANE_SIZE..(plane + 1) * PLANE_SIZE]
+            .iter()
+            .all(|value| *value == 1.0)
+    }
+
+    #[test]
+    fn test_input_planes_len() {
+        let game_state = initial_play_state();
+
+        assert_eq!(
+            game_state.input_planes(&InputPlaneOptions::default()).len(),
+            28 * 64
+        );
+        assert_eq!(
+            game_state
+                .input_planes(&InputPlaneOptions {
+                    include_previous_piece_boards: true
+                })
+                .len(),
+            64 * 64
+        );
+    }
+
+    #[test]
+    fn test_input_planes_gold_perspective() {
+        let planes = initial_play_state().input_planes(&InputPlaneOptions::default());
+
+        assert_eq!(plane(&planes, 0), (48..56).collect::<Vec<_>>());
+        assert_eq!(plane(&planes, 5), vec![60]);
+        assert_eq!(plane(&planes, 6), (8..16).collect::<Vec<_>>());
+        assert_eq!(plane(&planes, 11), vec![4]);
+
+        assert!(is_filled(&planes, SIDE_TO_MOVE_PLANE));
+        assert!(is_filled(&planes, STEP_PLANE));
+        assert!(plane(&planes, STEP_PLANE + 1).is_empty());
+        assert_eq!(plane(&planes, TRAP_PLANE), vec![18, 21, 42, 45]);
+        assert!(plane(&planes, FROZEN_PLANE).is_empty());
+        assert!(plane(&planes, FROZEN_PLANE + 1).is_empty());
+    }
+
+    #[test]
+    fn test_input_planes_silver_perspective() {
+        let game_state = take_actions!(initial_play_state() => d2n, d3n, d4n, d5n);
+        let planes = game_state.input_planes(&InputPlaneOptions::default());
+
+        // Silver's rabbits are on the 7th row, which is flipped to the bottom.
+        assert_eq!(plane(&planes, 0), (48..56).collect::<Vec<_>>());
+        assert_eq!(plane(&planes, 5), vec![60]);
+        assert_eq!(plane(&planes, 11), vec![4]);
+        assert_eq!(plane(&planes, 6), vec![8, 9, 10, 12, 13, 14, 15, 43]);
+
+        assert!(plane(&planes, SIDE_TO_MOVE_PLANE).is_empty());
+        assert!(is_filled(&planes, STEP_PLANE));
+    }
+
+    #[test]
+    fn test_input_planes_push_pull() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|                 |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|       d         |
+             3|     x E   x     |
+             2|                 |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let planes =
+            take_actions!(game_state.clone() => d3s).input_planes(&InputPlaneOptions::default());
+
+        assert!(is_filled(&planes, STEP_PLANE + 1));
+        assert_eq!(plane(&planes, POSSIBLE_PULL_SQUARE_PLANE), vec![43]);
+        assert!(plane(&planes, MUST_COMPLETE_PUSH_SQUARE_PLANE).is_empty());
+        assert!(is_filled(&planes, PUSH_PULL_PIECE_PLANE + 5));
+
+        let planes = take_actions!(game_state => d4n).input_planes(&InputPlaneOptions::default());
+
+        assert!(plane(&planes, POSSIBLE_PULL_SQUARE_PLANE).is_empty());
+        assert_eq!(plane(&planes, MUST_COMPLETE_PUSH_SQUARE_PLANE), vec![35]);
+        assert!(is_filled(&planes, PUSH_PULL_PIECE_PLANE + 2));
+        assert!(plane(&planes, PUSH_PULL_PIECE_PLANE + 5).is_empty());
+    }
+
+    #[test]
+    fn test_input_planes_frozen() {
+        let game_state: GameState = "
+             2s
+              +-----------------+
+             8|                 |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|       d E       |
+             3|     x   r x     |
+             2|         M       |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        let planes = game_state.input_planes(&InputPlaneOptions::default());
+
+        // Silver's dog and rabbit are next to stronger gold pieces without a friendly piece next to them.
+        assert_eq!(plane(&planes, FROZEN_PLANE), vec![20, 27]);
+        assert!(plane(&planes, FROZEN_PLANE + 1).is_empty());
+    }
+
+    #[test]
+    fn test_input_planes_previous_piece_boards() {
+        let options = InputPlaneOptions {
+            include_previous_piece_boards: true,
+        };
+        let game_state = initial_play_state();
+        let after_one_step = take_actions!(game_state.clone() => a2n);
+        let after_two_steps = take_actions!(after_one_step.clone() => a3n);
+
+        let planes = after_two_steps.input_planes(&options);
+        let previous_planes = &planes
+            [PREVIOUS_BOARD_PLANE * PLANE_SIZE..(PREVIOUS_BOARD_PLANE + PIECE_PLANES) * PLANE_SIZE];
+        let older_planes = &planes[(PREVIOUS_BOARD_PLANE + PIECE_PLANES) * PLANE_SIZE
+            ..(PREVIOUS_BOARD_PLANE + PIECE_PLANES * 2) * PLANE_SIZE];
+        let unused_planes = &planes[(PREVIOUS_BOARD_PLANE + PIECE_PLANES * 2) * PLANE_SIZE..];
+
+        assert_eq!(
+            previous_planes,
+            &after_one_step.input_planes(&options)[..PIECE_PLANES * PLANE_SIZE]
+        );
+        assert_eq!(
+            older_planes,
+            &game_state.input_planes(&options)[..PIECE_PLANES * PLANE_SIZE]
+        );
+        assert!(unused_planes.iter().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    fn test_encode_input_planes_overwrites_slice() {
+        let game_state = initial_play_state();
+        let options = InputPlaneOptions::default();
+        let mut planes = vec![1.0; options.input_len()];
+
+        game_state.encode_input_planes(&options, &mut planes);
+
+        assert_eq!(planes, game_state.input_planes(&options));
+    }
+
+    #[test]
+    fn test_input_planes_setup_phase() {
+        let game_state = take_actions!(GameState::initial() => e, m);
+        let planes = game_state.input_planes(&InputPlaneOptions::default());
+
+        assert_eq!(plane(&planes, 5), vec![48]);
+        assert_eq!(plane(&planes, 4), vec![49]);
+        assert!(is_filled(&planes, SIDE_TO_MOVE_PLANE));
+        assert!(plane(&planes, STEP_PLANE).is_empty());
+    }
+}
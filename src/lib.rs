@@ -98,8 +98,7 @@ assert_eq!(game_state.is_terminal(), Some(Terminal::GoldWin));
 Generate a full set of unique moves.
 
 ```
-use arimaa_engine_step::{take_actions, Action, GameState};
-use std::collections::HashSet;
+use arimaa_engine_step::{take_actions, GameState};
 
 // Start a new game with a basic setup.
 let game_state = GameState::initial();
@@ -109,44 +108,9 @@ let game_state = take_actions!(game_state => c, d, h, c, e, h, d, c);
 let game_state = take_actions!(game_state => c, d, h, c, e, h, d, c);
 let game_state = take_actions!(game_state => r, r, r, r, r, r, r, r);
 
-fn recurse_actions_for_state(
-    game_state: &GameState,
-    actions: &[Action],
-    moves: &mut Vec<(Vec<Action>, GameState)>,
-    hashes: &mut HashSet<u64>,
-) {
-    let curr_player = game_state.is_p1_turn_to_move();
-
-    // Go through each valid action for the current step in the move.
-    for action in game_state.valid_actions() {
-        let new_game_state = game_state.take_action(&action);
-
-        // If the state was already explored in another transposition, then skip over that state.
-        let game_state_hash = new_game_state.transposition_hash();
-        if hashes.contains(&game_state_hash) {
-            continue;
-        } else {
-            hashes.insert(game_state_hash);
-        }
-
-        let mut actions = actions.to_vec();
-        actions.push(action);
-
-        // If the state is terminal or it is the end of the turn for the player, then it is a full move.
-        if matches!(new_game_state.is_terminal(), Some(_)) || curr_player != new_game_state.is_p1_turn_to_move() {
-            moves.push((actions, new_game_state));
-            continue;
-        }
-
-        // A full move has not been reached, it is not terminal and it is still the same players turn.
-        // Recurse to check the next set of valid steps in the move.
-        recurse_actions_for_state(&new_game_state, &actions, moves, hashes);
-    }
-}
-
-let mut moves = vec![];
-let mut hashes = HashSet::new();
-recurse_actions_for_state(&game_state, &[], &mut moves, &mut hashes);
+// Each move is the list of actions taken along with the resulting state.
+// Transpositions are collapsed so that each resulting state is only returned once.
+let moves = game_state.valid_moves();
 
 println!("Found {} unique moves", moves.len());
 assert_eq!(moves.len(), 2467, "The setup should have 2467 unique moves.");
@@ -156,6 +120,28 @@ for (i, (actions, _game_state)) in moves.into_iter().enumerate() {
 }
 ```
 
+Moves can also be generated lazily, optionally keeping transpositions or limiting the number of steps.
+
+```
+use arimaa_engine_step::{take_actions, GameState, MoveGenOptions};
+
+let game_state = GameState::initial();
+let game_state = take_actions!(game_state => r, r, r, r, r, r, r, r);
+let game_state = take_actions!(game_state => c, d, h, c, e, h, d, c);
+
+let game_state = take_actions!(game_state => c, d, h, c, e, h, d, c);
+let game_state = take_actions!(game_state => r, r, r, r, r, r, r, r);
+
+let options = MoveGenOptions {
+    max_steps: 2,
+    ..MoveGenOptions::default()
+};
+
+for (actions, _game_state) in game_state.valid_moves_iter(options).take(10) {
+    println!("{:?}", actions);
+}
+```
+
 # Example: Create a plane out of piece board bits
 
 Creates a slice for a 8x8 board that would be used as an input plane for a neural network by taking
@@ -178,16 +164,11 @@ let game_state = board!("
      +-----------------+
        a b c d e f g h");
 
-let mut gold_rabbit_bits = game_state.piece_board().bits_for_piece(Piece::Rabbit, true);
+let gold_rabbit_bits = game_state.piece_board().bits_for_piece(Piece::Rabbit, true);
 let mut plane = [0; 64];
 
-while gold_rabbit_bits != 0 {
-    let bit_idx = gold_rabbit_bits.trailing_zeros() as usize;
-    let removed_bit_idx = bit_idx;
-
-    plane[removed_bit_idx] = 1;
-
-    gold_rabbit_bits ^= 1 << bit_idx;
+for square in gold_rabbit_bits {
+    plane[square.index()] = 1;
 }
 
 assert_eq!(
@@ -204,6 +185,30 @@ assert_eq!(
     ]
 );
 ```
+
+Full sets of input planes can be encoded with `GameState::input_planes`. The state is encoded from the perspective of
+the player to move, with the current player's pieces first, followed by the side to move, step, push/pull, trap and frozen piece planes.
+
+```
+use arimaa_engine_step::{take_actions, GameState, InputPlaneOptions, PLANE_SIZE};
+
+let game_state = GameState::initial();
+let game_state = take_actions!(game_state => r, r, r, r, r, r, r, r);
+let game_state = take_actions!(game_state => c, d, h, c, e, h, d, c);
+
+let game_state = take_actions!(game_state => c, d, h, c, e, h, d, c);
+let game_state = take_actions!(game_state => r, r, r, r, r, r, r, r);
+
+let options = InputPlaneOptions {
+    include_previous_piece_boards: true,
+};
+let planes: Vec<f32> = game_state.input_planes(&options);
+
+assert_eq!(planes.len(), options.num_planes() * PLANE_SIZE);
+
+// The first plane holds the rabbits of the player to move.
+assert_eq!(planes[..PLANE_SIZE].iter().sum::<f32>(), 8.0);
+```
 */
 
 #![allow(clippy::inconsistent_digit_grouping)]
@@ -213,29 +218,71 @@ assert_eq!(
 mod bit_manip;
 mod bit_mask;
 mod engine_tests;
+#[cfg(test)]
+mod test_util;
 mod zobrist_values;
 
 pub mod action;
+pub mod action_list;
+pub mod aei;
+pub mod binary_encoding;
+pub mod bit_board;
+pub mod capture_threats;
 pub mod constants;
 pub mod direction;
 pub mod display;
+// The engine works on the raw bits of `PieceBoardState` rather than its `BitBoard` accessors.
+#[allow(deprecated)]
 pub mod engine;
+pub mod eval;
+pub mod evaluator;
 pub mod full_move;
+pub mod game_record;
+pub mod goal_search;
+pub mod illegal_reason;
+pub mod input_planes;
 pub mod linked_list;
 pub mod macros;
+#[cfg(feature = "mcts")]
+pub mod mcts;
+pub mod move_gen;
+pub mod perft;
 pub mod piece;
+pub mod policy;
+#[cfg(feature = "search")]
+pub mod search;
+#[cfg(feature = "serde")]
+mod serialization;
 pub mod square;
+pub mod symmetry;
 pub mod terminal;
 pub mod zobrist;
 
 pub use action::*;
+pub use action_list::*;
+pub use binary_encoding::*;
+pub use bit_board::*;
+pub use capture_threats::*;
 pub use constants::*;
 pub use direction::*;
 pub use display::*;
 pub use engine::*;
+pub use eval::*;
+pub use evaluator::*;
+pub use game_record::*;
+pub use goal_search::*;
+pub use illegal_reason::*;
+pub use input_planes::*;
 pub use linked_list::*;
-pub use macros::*;
+#[cfg(feature = "mcts")]
+pub use mcts::*;
+pub use move_gen::*;
+pub use perft::*;
 pub use piece::*;
+pub use policy::*;
+#[cfg(feature = "search")]
+pub use search::*;
 pub use square::*;
+pub use symmetry::*;
 pub use terminal::*;
 pub use zobrist::Zobrist;
@@ -0,0 +1,621 @@
+use super::{Action, GameState, Terminal};
+use std::collections::HashMap;
+
+/// The policy and value of a state as predicted by an `Oracle`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evaluation {
+    /// The prior probability of each action, in the same order as the actions given to the oracle.
+    /// The values are normalized, so they do not need to sum to 1.
+    pub policy: Vec<f32>,
+    /// The expected outcome between -1 (loss) and 1 (win) from the perspective of the player to move.
+    pub value: f32,
+}
+
+/// Provides the policy and value of states reached by the search, such as from a neural network.
+pub trait Oracle {
+    /// Evaluates a state which is not terminal. `actions` are the valid actions of the state.
+    fn evaluate(&mut self, game_state: &GameState, actions: &[Action]) -> Evaluation;
+}
+
+/// An oracle with a uniform policy and a value of 0 for every state.
+/// Useful for testing, as the search is then only guided by the terminal states it finds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UniformOracle;
+
+impl Oracle for UniformOracle {
+    fn evaluate(&mut self, _game_state: &GameState, actions: &[Action]) -> Evaluation {
+        Evaluation {
+            policy: vec![1.0; actions.len()],
+            value: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MctsOptions {
+    /// The exploration constant of the PUCT formula.
+    pub cpuct: f32,
+    /// The value assumed for actions which have not been visited, from the perspective of the player taking the action.
+    pub fpu_value: f32,
+    /// The number of losses added to each action along a path while its leaf is waiting to be evaluated.
+    /// This spreads the leaves selected for a batch across the tree.
+    pub virtual_loss: f32,
+    /// When true, states reached by different orders of steps share a single node in the tree.
+    pub merge_transpositions: bool,
+}
+
+impl Default for MctsOptions {
+    fn default() -> Self {
+        MctsOptions {
+            cpuct: 1.25,
+            fpu_value: 0.0,
+            virtual_loss: 1.0,
+            merge_transpositions: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Edge {
+    action: Action,
+    prior: f32,
+    child: Option<usize>,
+    visits: u32,
+    /// The total value from the perspective of the player taking the action.
+    total_value: f32,
+    virtual_losses: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    game_state: GameState,
+    hash: u64,
+    edges: Vec<Edge>,
+    visits: u32,
+    /// The value from the perspective of the player to move when the state is terminal.
+    terminal_value: Option<f32>,
+}
+
+/// A leaf selected by `Mcts::select_leaf` which needs to be evaluated by an oracle before it can be added to the tree.
+#[derive(Debug)]
+pub struct Leaf {
+    path: Vec<(usize, usize)>,
+    game_state: GameState,
+    actions: Vec<Action>,
+}
+
+impl Leaf {
+    /// The state to evaluate.
+    pub fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+
+    /// The valid actions of the state, which are the actions that the policy of the evaluation must be given for.
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+}
+
+/// A Monte Carlo tree search over steps using PUCT selection.
+///
+/// Each edge of the tree is a single step, so the player to move only switches after a pass or the fourth step of a move.
+/// Values are stored from the perspective of the player taking each action.
+///
+/// Leaves can be evaluated one at a time with `search`, or in batches by calling `select_leaf` several times before
+/// evaluating the leaves together and passing each result to `expand`. Virtual losses steer the selections of a batch apart.
+///
+/// When transpositions are merged, nodes are shared based on `GameState::transposition_hash`. The hash does not include the
+/// history of the game, so a shared node keeps the history of the first path that reached it.
+pub struct Mcts {
+    options: MctsOptions,
+    nodes: Vec<Node>,
+    transpositions: HashMap<u64, usize>,
+    root: usize,
+}
+
+impl Mcts {
+    pub fn new(game_state: GameState, options: MctsOptions) -> Self {
+        let mut mcts = Mcts {
+            options,
+            nodes: vec![],
+            transpositions: HashMap::new(),
+            root: 0,
+        };
+
+        mcts.root = mcts.add_node(game_state);
+        mcts
+    }
+
+    /// The state at the root of the tree.
+    pub fn root_state(&self) -> &GameState {
+        &self.nodes[self.root].game_state
+    }
+
+    /// The number of nodes in the tree.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The number of times the root has been visited.
+    pub fn root_visits(&self) -> u32 {
+        self.nodes[self.root].visits
+    }
+
+    /// Returns each valid action of the root along with the number of visits and the average value from the perspective of the player to move.
+    /// Before the root is expanded there are no actions.
+    pub fn root_actions(&self) -> Vec<(Action, u32, f32)> {
+        self.nodes[self.root]
+            .edges
+            .iter()
+            .map(|edge| (edge.action, edge.visits, average_value(edge)))
+            .collect()
+    }
+
+    /// Returns the most visited action of the root, or None if the root has not been expanded.
+    pub fn best_action(&self) -> Option<Action> {
+        self.nodes[self.root]
+            .edges
+            .iter()
+            .max_by_key(|edge| edge.visits)
+            .map(|edge| edge.action)
+    }
+
+    /// Runs the specified number of iterations, evaluating each leaf with the oracle.
+    pub fn search<O: Oracle>(&mut self, oracle: &mut O, iterations: usize) {
+        for _ in 0..iterations {
+            if let Some(leaf) = self.select_leaf() {
+                let evaluation = oracle.evaluate(&leaf.game_state, &leaf.actions);
+                self.expand(leaf, evaluation);
+            }
+        }
+    }
+
+    /// Selects the next leaf to evaluate, adding a virtual loss to each action along its path.
+    ///
+    /// Returns None when the selection ended at a terminal state or a repeated state, in which case the result has
+    /// already been backed up and no evaluation is needed.
+    pub fn select_leaf(&mut self) -> Option<Leaf> {
+        let mut path: Vec<(usize, usize)> = vec![];
+        let mut node_id = self.root;
+
+        loop {
+            let node = &self.nodes[node_id];
+
+            if let Some(value) = node.terminal_value {
+                self.backup(&path, node_id, value);
+                return None;
+            }
+
+            if node.edges.is_empty() {
+                // Only the root is added to the tree before it has been evaluated.
+                let game_state = node.game_state.clone();
+                let actions = game_state.valid_actions();
+                return Some(Leaf {
+                    path,
+                    game_state,
+                    actions,
+                });
+            }
+
+            let edge_idx = self.select_edge(node);
+            path.push((node_id, edge_idx));
+            self.nodes[node_id].edges[edge_idx].virtual_losses += 1;
+
+            let edge = &self.nodes[node_id].edges[edge_idx];
+            let child_id = match edge.child {
+                Some(child_id) => child_id,
+                None => {
+                    let game_state = self.nodes[node_id].game_state.take_action(&edge.action);
+
+                    match self.find_transposition(&game_state) {
+                        Some(child_id) => {
+                            self.nodes[node_id].edges[edge_idx].child = Some(child_id);
+                            child_id
+                        }
+                        None if game_state.is_terminal().is_some() => {
+                            let child_id = self.add_node(game_state);
+                            self.nodes[node_id].edges[edge_idx].child = Some(child_id);
+                            child_id
+                        }
+                        None => {
+                            let actions = game_state.valid_actions();
+                            return Some(Leaf {
+                                path,
+                                game_state,
+                                actions,
+                            });
+                        }
+                    }
+                }
+            };
+
+            // Merged transpositions can lead back to a state on the current path. Treat the repetition as even.
+            if path.iter().any(|(id, _)| *id == child_id) {
+                path.pop();
+                self.nodes[node_id].edges[edge_idx].virtual_losses -= 1;
+                self.nodes[node_id].edges[edge_idx].visits += 1;
+                self.backup(&path, node_id, 0.0);
+                return None;
+            }
+
+            node_id = child_id;
+        }
+    }
+
+    /// Adds an evaluated leaf to the tree and backs up its value along the path it was selected from.
+    pub fn expand(&mut self, leaf: Leaf, evaluation: Evaluation) {
+        let Leaf {
+            path,
+            game_state,
+            actions,
+        } = leaf;
+
+        let node_id = match path.last() {
+            None => self.root,
+            Some((parent_id, edge_idx)) => {
+                let existing_child = self.nodes[*parent_id].edges[*edge_idx]
+                    .child
+                    .or_else(|| self.find_transposition(&game_state));
+
+                // Another leaf of the same batch may have already added the state.
+                let child_id = existing_child.unwrap_or_else(|| self.add_node(game_state));
+                self.nodes[*parent_id].edges[*edge_idx].child = Some(child_id);
+                child_id
+            }
+        };
+
+        if self.nodes[node_id].edges.is_empty() {
+            let total_prior: f32 = evaluation.policy.iter().filter(|p| **p > 0.0).sum();
+            let uniform_prior = 1.0 / actions.len().max(1) as f32;
+
+            self.nodes[node_id].edges = actions
+                .iter()
+                .enumerate()
+                .map(|(i, action)| Edge {
+                    action: *action,
+                    prior: match evaluation.policy.get(i) {
+                        Some(prior) if total_prior > 0.0 => prior.max(0.0) / total_prior,
+                        _ => uniform_prior,
+                    },
+                    child: None,
+                    visits: 0,
+                    total_value: 0.0,
+                    virtual_losses: 0,
+                })
+                .collect();
+        }
+
+        self.backup(&path, node_id, evaluation.value);
+    }
+
+    /// Moves the root of the tree to the state after taking the action, keeping the statistics of the subtree below it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any leaves selected by `select_leaf` have not been expanded.
+    pub fn advance(&mut self, action: &Action) {
+        let root = &self.nodes[self.root];
+        assert!(
+            root.edges.iter().all(|edge| edge.virtual_losses == 0),
+            "All selected leaves must be expanded before advancing"
+        );
+
+        let game_state = root.game_state.take_action(action);
+        let child = root
+            .edges
+            .iter()
+            .find(|edge| edge.action == *action)
+            .and_then(|edge| edge.child);
+
+        match child {
+            Some(child_id) => {
+                // The new root keeps the exact history of the game rather than that of a merged transposition.
+                self.nodes[child_id].game_state = game_state;
+                self.root = child_id;
+                self.retain_reachable();
+            }
+            None => *self = Mcts::new(game_state, self.options),
+        }
+    }
+
+    fn select_edge(&self, node: &Node) -> usize {
+        let virtual_loss = self.options.virtual_loss;
+        let parent_visits: f32 = node
+            .edges
+            .iter()
+            .map(|edge| edge.visits as f32 + edge.virtual_losses as f32 * virtual_loss)
+            .sum();
+        let exploration = self.options.cpuct * parent_visits.max(1.0).sqrt();
+
+        let mut best_idx = 0;
+        let mut best_score = f32::NEG_INFINITY;
+
+        for (i, edge) in node.edges.iter().enumerate() {
+            let virtual_visits = edge.virtual_losses as f32 * virtual_loss;
+            let visits = edge.visits as f32 + virtual_visits;
+            let q = if visits > 0.0 {
+                (edge.total_value - virtual_visits) / visits
+            } else {
+                self.options.fpu_value
+            };
+            let u = exploration * edge.prior / (1.0 + visits);
+
+            if q + u > best_score {
+                best_score = q + u;
+                best_idx = i;
+            }
+        }
+
+        best_idx
+    }
+
+    /// Backs up a value from the perspective of the player to move at the leaf node, removing the virtual losses of the path.
+    fn backup(&mut self, path: &[(usize, usize)], leaf_id: usize, value: f32) {
+        let leaf_is_p1 = self.nodes[leaf_id].game_state.is_p1_turn_to_move();
+        self.nodes[leaf_id].visits += 1;
+
+        for (node_id, edge_idx) in path.iter() {
+            let node = &mut self.nodes[*node_id];
+            let is_same_player = node.game_state.is_p1_turn_to_move() == leaf_is_p1;
+            let edge = &mut node.edges[*edge_idx];
+
+            edge.virtual_losses -= 1;
+            edge.visits += 1;
+            edge.total_value += if is_same_player { value } else { -value };
+
+            if *node_id != leaf_id {
+                node.visits += 1;
+            }
+        }
+    }
+
+    fn find_transposition(&self, game_state: &GameState) -> Option<usize> {
+        if self.options.merge_transpositions {
+            self.transpositions
+                .get(&game_state.transposition_hash())
+                .copied()
+        } else {
+            None
+        }
+    }
+
+    fn add_node(&mut self, game_state: GameState) -> usize {
+        let id = self.nodes.len();
+        let hash = game_state.transposition_hash();
+        let terminal_value = game_state.is_terminal().map(|terminal| {
+            if (terminal == Terminal::GoldWin) == game_state.is_p1_turn_to_move() {
+                1.0
+            } else {
+                -1.0
+            }
+        });
+
+        if self.options.merge_transpositions {
+            self.transpositions.insert(hash, id);
+        }
+
+        self.nodes.push(Node {
+            game_state,
+            hash,
+            edges: vec![],
+            visits: 0,
+            terminal_value,
+        });
+
+        id
+    }
+
+    /// Removes the nodes which are no longer reachable from the root.
+    fn retain_reachable(&mut self) {
+        let mut new_ids: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut order = vec![self.root];
+        new_ids[self.root] = Some(0);
+
+        let mut i = 0;
+        while i < order.len() {
+            let node_id = order[i];
+            for child_id in self.nodes[node_id]
+                .edges
+                .iter()
+                .filter_map(|edge| edge.child)
+            {
+                if new_ids[child_id].is_none() {
+                    new_ids[child_id] = Some(order.len());
+                    order.push(child_id);
+                }
+            }
+            i += 1;
+        }
+
+        let mut old_nodes: Vec<Option<Node>> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .map(Some)
+            .collect();
+
+        self.nodes = order
+            .iter()
+            .map(|old_id| {
+                let mut node = old_nodes[*old_id].take().unwrap();
+                for edge in node.edges.iter_mut() {
+                    edge.child = edge.child.and_then(|child_id| new_ids[child_id]);
+                }
+                node
+            })
+            .collect();
+
+        self.transpositions = if self.options.merge_transpositions {
+            self.nodes
+                .iter()
+                .enumerate()
+                .map(|(id, node)| (node.hash, id))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        self.root = 0;
+    }
+}
+
+fn average_value(edge: &Edge) -> f32 {
+    if edge.visits > 0 {
+        edge.total_value / edge.visits as f32
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::initial_play_state;
+    use super::super::{action, take_actions, Action, GameState};
+    use super::{Evaluation, Mcts, MctsOptions, Oracle, UniformOracle};
+
+    fn goal_state() -> GameState {
+        "
+             2g
+              +-----------------+
+             8|           r     |
+             7|                 |
+             6|   R x   e x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap()
+    }
+
+    /// An oracle which strongly prefers a single action whenever it is available.
+    struct PreferredActionOracle(Action);
+
+    impl Oracle for PreferredActionOracle {
+        fn evaluate(&mut self, _game_state: &GameState, actions: &[Action]) -> Evaluation {
+            Evaluation {
+                policy: actions
+                    .iter()
+                    .map(|action| if *action == self.0 { 100.0 } else { 1.0 })
+                    .collect(),
+                value: 0.0,
+            }
+        }
+    }
+
+    #[test]
+    fn test_mcts_finds_goal() {
+        let mut mcts = Mcts::new(goal_state(), MctsOptions::default());
+        mcts.search(&mut UniformOracle, 3000);
+
+        assert_eq!(mcts.best_action(), Some(action!(b6n)));
+        assert_eq!(mcts.root_visits(), 3000);
+
+        let (_, _, value) = mcts
+            .root_actions()
+            .into_iter()
+            .find(|(action, _, _)| *action == action!(b6n))
+            .unwrap();
+        assert!(value > 0.5, "{}", value);
+    }
+
+    #[test]
+    fn test_mcts_follows_policy() {
+        let game_state = initial_play_state();
+        let mut mcts = Mcts::new(game_state, MctsOptions::default());
+        mcts.search(&mut PreferredActionOracle(action!(e2n)), 200);
+
+        assert_eq!(mcts.best_action(), Some(action!(e2n)));
+    }
+
+    #[test]
+    fn test_mcts_merges_transpositions() {
+        let mut mcts = Mcts::new(goal_state(), MctsOptions::default());
+        let mut mcts_unmerged = Mcts::new(
+            goal_state(),
+            MctsOptions {
+                merge_transpositions: false,
+                ..MctsOptions::default()
+            },
+        );
+
+        mcts.search(&mut UniformOracle, 2000);
+        mcts_unmerged.search(&mut UniformOracle, 2000);
+
+        assert!(mcts.node_count() < mcts_unmerged.node_count());
+    }
+
+    #[test]
+    fn test_mcts_virtual_loss_spreads_batch() {
+        let mut mcts = Mcts::new(initial_play_state(), MctsOptions::default());
+        mcts.search(&mut UniformOracle, 1);
+
+        let leaves: Vec<_> = (0..8).filter_map(|_| mcts.select_leaf()).collect();
+        assert_eq!(leaves.len(), 8);
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            for other in leaves[i + 1..].iter() {
+                assert_ne!(
+                    leaf.game_state().transposition_hash(),
+                    other.game_state().transposition_hash()
+                );
+            }
+        }
+
+        for leaf in leaves {
+            let actions = leaf.actions().len();
+            mcts.expand(
+                leaf,
+                Evaluation {
+                    policy: vec![1.0; actions],
+                    value: 0.0,
+                },
+            );
+        }
+
+        assert_eq!(mcts.root_visits(), 9);
+        assert!(mcts
+            .root_actions()
+            .iter()
+            .all(|(_, visits, _)| *visits <= 1));
+    }
+
+    #[test]
+    fn test_mcts_advance_reuses_tree() {
+        let mut mcts = Mcts::new(initial_play_state(), MctsOptions::default());
+        mcts.search(&mut PreferredActionOracle(action!(e2n)), 500);
+
+        let (_, visits, _) = mcts
+            .root_actions()
+            .into_iter()
+            .find(|(action, _, _)| *action == action!(e2n))
+            .unwrap();
+        let node_count = mcts.node_count();
+
+        mcts.advance(&action!(e2n));
+
+        assert_eq!(mcts.root_visits(), visits);
+        assert!(mcts.node_count() < node_count);
+        assert_eq!(
+            format!("{:?}", mcts.root_state()),
+            format!("{:?}", take_actions!(initial_play_state() => e2n))
+        );
+
+        mcts.search(&mut UniformOracle, 100);
+        assert_eq!(mcts.root_visits(), visits + 100);
+    }
+
+    #[test]
+    fn test_mcts_advance_unexplored_action() {
+        let mut mcts = Mcts::new(initial_play_state(), MctsOptions::default());
+        mcts.search(&mut PreferredActionOracle(action!(e2n)), 10);
+        mcts.advance(&action!(a2n));
+
+        assert_eq!(mcts.root_visits(), 0);
+        assert_eq!(mcts.node_count(), 1);
+        assert_eq!(
+            format!("{:?}", mcts.root_state()),
+            format!("{:?}", take_actions!(initial_play_state() => a2n))
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use super::{Action, GameState};
+use std::collections::HashSet;
+
+/// Options controlling how full moves are generated by `GameState::valid_moves_with_options`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MoveGenOptions {
+    /// When true, steps resulting in a third time repetition or in a position equal to the start of the move are excluded.
+    pub check_repetitions: bool,
+    /// When true, moves which arrive at an already generated state through a different order of steps are skipped.
+    pub collapse_transpositions: bool,
+    /// The maximum number of steps, excluding a final pass, that a single move may contain. Values above 4 have no effect.
+    pub max_steps: usize,
+}
+
+impl Default for MoveGenOptions {
+    fn default() -> Self {
+        MoveGenOptions {
+            check_repetitions: true,
+            collapse_transpositions: true,
+            max_steps: 4,
+        }
+    }
+}
+
+struct Frame {
+    game_state: GameState,
+    actions: std::vec::IntoIter<Action>,
+}
+
+/// A lazy iterator over the full moves available from a game state.
+/// Created by `GameState::valid_moves_iter`.
+pub struct ValidMoves {
+    options: MoveGenOptions,
+    is_p1_turn_to_move: bool,
+    stack: Vec<Frame>,
+    actions: Vec<Action>,
+    hashes: HashSet<u64>,
+    steps_taken: usize,
+}
+
+impl ValidMoves {
+    fn new(game_state: &GameState, options: MoveGenOptions) -> Self {
+        let mut valid_moves = ValidMoves {
+            options,
+            is_p1_turn_to_move: game_state.is_p1_turn_to_move(),
+            stack: Vec::with_capacity(4),
+            actions: Vec::with_capacity(4),
+            hashes: HashSet::new(),
+            steps_taken: 0,
+        };
+
+        valid_moves.push_frame(game_state.clone());
+        valid_moves
+    }
+
+    /// Returns the number of actions, including passes and placements, taken so far to generate the moves.
+    /// This includes actions leading to transpositions that were skipped.
+    pub fn steps_taken(&self) -> usize {
+        self.steps_taken
+    }
+
+    fn push_frame(&mut self, game_state: GameState) {
+        let mut actions = if self.options.check_repetitions {
+            game_state.valid_actions()
+        } else {
+            game_state.valid_actions_no_rep()
+        };
+
+        // Once the cap on steps has been reached, the only way to complete the move is to pass.
+        if game_state.is_play_phase() && self.stack.len() >= self.options.max_steps {
+            actions.retain(|action| *action == Action::Pass);
+        }
+
+        self.stack.push(Frame {
+            game_state,
+            actions: actions.into_iter(),
+        });
+    }
+}
+
+impl Iterator for ValidMoves {
+    type Item = (Vec<Action>, GameState);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last_mut()?;
+
+            let action = match frame.actions.next() {
+                Some(action) => action,
+                None => {
+                    // All steps from this state have been explored, backtrack to the previous step.
+                    self.stack.pop();
+                    self.actions.pop();
+                    continue;
+                }
+            };
+
+            let new_game_state = frame.game_state.take_action(&action);
+            self.steps_taken += 1;
+
+            // If the state was already explored in another transposition, then skip over that state.
+            if self.options.collapse_transpositions
+                && !self.hashes.insert(new_game_state.transposition_hash())
+            {
+                continue;
+            }
+
+            // If the state is terminal or it is the end of the turn for the player, then it is a full move.
+            if new_game_state.is_terminal().is_some()
+                || self.is_p1_turn_to_move != new_game_state.is_p1_turn_to_move()
+            {
+                let mut actions = self.actions.clone();
+                actions.push(action);
+                return Some((actions, new_game_state));
+            }
+
+            // A full move has not been reached, continue with the next set of valid steps in the move.
+            self.actions.push(action);
+            self.push_frame(new_game_state);
+        }
+    }
+}
+
+impl GameState {
+    /// Returns the set of unique full moves available to the current player.
+    /// Each move is the list of actions taken along with the resulting state.
+    ///
+    /// Steps resulting in repetitions are excluded and transpositions are collapsed so that each resulting state is only returned once.
+    /// <p style="background:rgba(255,181,77,0.16);padding:0.75em;">
+    /// <strong>Warning:</strong> During the setup phase a full move consists of 16 placements, which results in a very large number of moves.
+    /// </p>
+    pub fn valid_moves(&self) -> Vec<(Vec<Action>, GameState)> {
+        self.valid_moves_iter(MoveGenOptions::default()).collect()
+    }
+
+    /// Returns the set of full moves available to the current player, generated with the specified options.
+    pub fn valid_moves_with_options(
+        &self,
+        options: MoveGenOptions,
+    ) -> Vec<(Vec<Action>, GameState)> {
+        self.valid_moves_iter(options).collect()
+    }
+
+    /// Returns an iterator which lazily generates the full moves available to the current player.
+    /// Moves are generated depth first in the order of `valid_actions`.
+    pub fn valid_moves_iter(&self, options: MoveGenOptions) -> ValidMoves {
+        ValidMoves::new(self, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::initial_play_state;
+    use super::super::{action, board, Action, GameState, Terminal};
+    use super::MoveGenOptions;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_valid_moves_initial_play_state() {
+        let game_state = initial_play_state();
+        let moves = game_state.valid_moves();
+
+        assert_eq!(moves.len(), 2467);
+
+        let hashes: HashSet<_> = moves.iter().map(|(_, g)| g.transposition_hash()).collect();
+        assert_eq!(hashes.len(), moves.len());
+
+        for (_, new_game_state) in moves.iter() {
+            assert!(!new_game_state.is_p1_turn_to_move());
+        }
+    }
+
+    #[test]
+    fn test_valid_moves_iter_matches_valid_moves() {
+        let game_state = initial_play_state();
+        let moves = game_state.valid_moves();
+        let moves_iter = game_state
+            .valid_moves_iter(MoveGenOptions::default())
+            .collect::<Vec<_>>();
+
+        assert_eq!(moves.len(), moves_iter.len());
+        for ((actions, _), (actions_iter, _)) in moves.iter().zip(moves_iter.iter()) {
+            assert_eq!(actions, actions_iter);
+        }
+    }
+
+    #[test]
+    fn test_valid_moves_iter_is_lazy() {
+        let game_state = initial_play_state();
+        let first_move = game_state
+            .valid_moves_iter(MoveGenOptions::default())
+            .next();
+
+        let (actions, new_game_state) = first_move.unwrap();
+        assert_eq!(actions.len(), 4);
+        assert!(!new_game_state.is_p1_turn_to_move());
+    }
+
+    #[test]
+    fn test_valid_moves_without_collapsing_transpositions() {
+        let game_state = initial_play_state();
+        let moves = game_state.valid_moves_with_options(MoveGenOptions {
+            collapse_transpositions: false,
+            ..MoveGenOptions::default()
+        });
+
+        let hashes: HashSet<_> = moves.iter().map(|(_, g)| g.transposition_hash()).collect();
+        assert_eq!(hashes.len(), 2467);
+        assert!(moves.len() > hashes.len());
+    }
+
+    #[test]
+    fn test_valid_moves_max_steps() {
+        let game_state = initial_play_state();
+        let moves = game_state.valid_moves_with_options(MoveGenOptions {
+            max_steps: 1,
+            ..MoveGenOptions::default()
+        });
+
+        let single_steps = game_state.valid_actions().len();
+        assert_eq!(moves.len(), single_steps);
+
+        for (actions, _) in moves.iter() {
+            assert_eq!(actions.len(), 2);
+            assert_eq!(actions[1], Action::Pass);
+        }
+    }
+
+    fn goal_state() -> GameState {
+        board!(
+            "2g
+             +-----------------+
+            8|           r     |
+            7|                 |
+            6|   R x   e x     |
+            5|                 |
+            4|                 |
+            3|     x     x     |
+            2|                 |
+            1|                 |
+             +-----------------+
+               a b c d e f g h"
+        )
+    }
+
+    #[test]
+    fn test_valid_moves_terminal_ends_move() {
+        let game_state = goal_state();
+        let moves = game_state.valid_moves();
+
+        assert!(moves
+            .iter()
+            .any(|(_, new_game_state)| new_game_state.is_terminal() == Some(Terminal::GoldWin)));
+
+        for (actions, new_game_state) in moves.iter() {
+            if new_game_state.is_terminal() == Some(Terminal::GoldWin) {
+                assert_eq!(&actions[..2], &[action!(b6n), action!(b7n)]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_valid_moves_repetitions() {
+        let game_state = goal_state();
+
+        let moves = game_state.valid_moves();
+        let moves_no_rep = game_state.valid_moves_with_options(MoveGenOptions {
+            check_repetitions: false,
+            ..MoveGenOptions::default()
+        });
+
+        // Without checking for repetitions, moves such as b6w a6e returning to the initial position are included.
+        assert_eq!(moves_no_rep.len(), moves.len() + 1);
+        assert!(moves_no_rep.iter().any(|(_, new_game_state)| new_game_state
+            .piece_board()
+            .p1_pieces()
+            == game_state.piece_board().p1_pieces()));
+    }
+}
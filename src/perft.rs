@@ -0,0 +1,208 @@
+use super::full_move::convert_actions_to_move_string;
+use super::{GameState, MoveGenOptions};
+use std::ops::{Add, AddAssign};
+
+/// The node counts of a `GameState::perft` search.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PerftResult {
+    /// The number of unique full moves at the final depth, which is the number of positions reached.
+    pub moves: u64,
+    /// The number of actions, including passes, taken to generate the moves at the final depth.
+    pub steps: u64,
+}
+
+impl Add for PerftResult {
+    type Output = PerftResult;
+
+    fn add(self, other: PerftResult) -> PerftResult {
+        PerftResult {
+            moves: self.moves + other.moves,
+            steps: self.steps + other.steps,
+        }
+    }
+}
+
+impl AddAssign for PerftResult {
+    fn add_assign(&mut self, other: PerftResult) {
+        *self = *self + other;
+    }
+}
+
+impl GameState {
+    /// Counts the nodes of the full move tree to the specified depth. Each level of depth is a full move by one player.
+    ///
+    /// Moves are generated the same as `valid_moves`, so transpositions within a move are collapsed and repetitions are excluded.
+    /// Terminal positions are not expanded any further. A depth of 0 counts the state itself as a single move.
+    /// <p style="background:rgba(255,181,77,0.16);padding:0.75em;">
+    /// <strong>Warning:</strong> The number of nodes grows very quickly. During the setup phase even a depth of 1 is not feasible.
+    /// </p>
+    pub fn perft(&self, depth: usize) -> PerftResult {
+        if depth == 0 {
+            return PerftResult { moves: 1, steps: 0 };
+        }
+
+        if self.is_terminal().is_some() {
+            return PerftResult::default();
+        }
+
+        let mut valid_moves = self.valid_moves_iter(MoveGenOptions::default());
+
+        if depth == 1 {
+            let moves = valid_moves.by_ref().count() as u64;
+            return PerftResult {
+                moves,
+                steps: valid_moves.steps_taken() as u64,
+            };
+        }
+
+        valid_moves
+            .map(|(_, game_state)| game_state.perft(depth - 1))
+            .fold(PerftResult::default(), |total, result| total + result)
+    }
+
+    /// Counts the nodes of the full move tree to the specified depth for each of the moves available to the current player.
+    /// For a depth greater than 1 the counts sum to the result of `perft`. This is useful for finding the move at which the counts of two move generators diverge.
+    pub fn perft_divide(&self, depth: usize) -> Vec<(String, PerftResult)> {
+        self.valid_moves()
+            .into_iter()
+            .map(|(actions, game_state)| {
+                let move_string = convert_actions_to_move_string(self.clone(), &actions);
+                (move_string, game_state.perft(depth.saturating_sub(1)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::initial_play_state;
+    use super::super::GameState;
+    use super::PerftResult;
+
+    /// Reference positions with the expected counts for each depth starting at 1.
+    const REFERENCE_POSITIONS: &[(&str, &[PerftResult])] = &[
+        // A rabbit one step from goal.
+        (
+            "2g 0 [     r           R  e                                           ] - 0 - c5597b93d31991aa",
+            &[
+                PerftResult {
+                    moves: 11,
+                    steps: 40,
+                },
+                PerftResult {
+                    moves: 1232,
+                    steps: 7424,
+                },
+                PerftResult {
+                    moves: 9253,
+                    steps: 34804,
+                },
+            ],
+        ),
+        // Pieces able to push and pull in each direction.
+        (
+            "12g 0 [  r r  r                   eR   R rMd      C      r  E         R] - 0 - 342ad054d0ff427c",
+            &[
+                PerftResult {
+                    moves: 1379,
+                    steps: 8012,
+                },
+            ],
+        ),
+        // A push that must be completed on the third step.
+        (
+            "12g 2 [  r r  r                   eR   R rM       Cd     r  E        R ] push:de4 0 [  r r  r                   eR   R rMd      C      r  E         R][  r r  r                   eR   R rMd      C      r  E        R ] 342ad054d0ff427c",
+            &[
+                PerftResult {
+                    moves: 14,
+                    steps: 15,
+                },
+            ],
+        ),
+        // Pieces moving back and forth with positions that have already occurred.
+        (
+            "23s 1 [       r       d           m       E                           R] pull:dh6 0 [       r               d   m       E                           R] 035bd05c63622d8c,e8a96062b08a7225,215f5a64887ada59,caadea5a5b9285f0,035bd05c63622d8c,e8a96062b08a7225,215f5a64887ada59",
+            &[
+                PerftResult {
+                    moves: 35,
+                    steps: 109,
+                },
+                PerftResult {
+                    moves: 9228,
+                    steps: 41334,
+                },
+            ],
+        ),
+    ];
+
+    fn assert_perft(game_state: &GameState, expected: &[PerftResult]) {
+        for (i, expected) in expected.iter().enumerate() {
+            let depth = i + 1;
+            assert_eq!(
+                game_state.perft(depth),
+                *expected,
+                "depth {}\n{}",
+                depth,
+                game_state.to_extended_notation()
+            );
+        }
+    }
+
+    #[test]
+    fn test_perft_depth_0() {
+        assert_eq!(
+            initial_play_state().perft(0),
+            PerftResult { moves: 1, steps: 0 }
+        );
+    }
+
+    #[test]
+    fn test_perft_initial_play_state() {
+        let game_state = initial_play_state();
+        let result = game_state.perft(1);
+
+        assert_eq!(
+            result,
+            PerftResult {
+                moves: 2467,
+                steps: 8380
+            }
+        );
+        assert_eq!(result.moves as usize, game_state.valid_moves().len());
+    }
+
+    #[test]
+    #[ignore = "takes several minutes without optimizations"]
+    fn test_perft_initial_play_state_depth_2() {
+        assert_eq!(
+            initial_play_state().perft(2),
+            PerftResult {
+                moves: 6065465,
+                steps: 20619408
+            }
+        );
+    }
+
+    #[test]
+    fn test_perft_reference_positions() {
+        for (notation, expected) in REFERENCE_POSITIONS.iter() {
+            let game_state = GameState::from_extended_notation(notation).unwrap();
+            assert_perft(&game_state, expected);
+        }
+    }
+
+    #[test]
+    fn test_perft_divide_sums_to_perft() {
+        let (notation, expected) = REFERENCE_POSITIONS[0];
+        let game_state = GameState::from_extended_notation(notation).unwrap();
+        let divide = game_state.perft_divide(2);
+
+        assert_eq!(divide.len() as u64, expected[0].moves);
+        assert_eq!(
+            divide
+                .into_iter()
+                .fold(PerftResult::default(), |total, (_, result)| total + result),
+            expected[1]
+        );
+    }
+}
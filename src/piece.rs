@@ -2,6 +2,7 @@ use anyhow::anyhow;
 use std::fmt;
 use std::str::FromStr;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
 pub enum Piece {
     Rabbit,
@@ -21,6 +22,27 @@ impl Piece {
         Piece::Camel,
         Piece::Elephant,
     ];
+
+    /// Returns the position of the piece in `Piece::ALL`, from 0 for a rabbit to 5 for an elephant.
+    pub const fn index(self) -> usize {
+        match self {
+            Piece::Rabbit => 0,
+            Piece::Cat => 1,
+            Piece::Dog => 2,
+            Piece::Horse => 3,
+            Piece::Camel => 4,
+            Piece::Elephant => 5,
+        }
+    }
+
+    /// Returns the number of pieces of the type that each player starts the game with.
+    pub const fn initial_count(self) -> usize {
+        match self {
+            Piece::Rabbit => 8,
+            Piece::Cat | Piece::Dog | Piece::Horse => 2,
+            Piece::Camel | Piece::Elephant => 1,
+        }
+    }
 }
 
 impl fmt::Display for Piece {
@@ -65,3 +87,15 @@ impl FromStr for Piece {
         Err(anyhow!("Invalid value for piece"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piece_index_matches_all() {
+        for (index, piece) in Piece::ALL.iter().enumerate() {
+            assert_eq!(piece.index(), index);
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use super::{Action, Direction, GameState, Piece, Square};
+
+/// The index of the first move. Moves are indexed by square and then by direction, `square * 4 + direction`.
+pub const MOVE_POLICY_INDEX: usize = 0;
+/// The index of the pass action.
+pub const PASS_POLICY_INDEX: usize = MOVE_POLICY_INDEX + 64 * 4;
+/// The index of the first placement. Placements are indexed by piece, from rabbit to elephant.
+pub const PLACE_POLICY_INDEX: usize = PASS_POLICY_INDEX + 1;
+/// The number of indices in a policy vector.
+pub const POLICY_SIZE: usize = PLACE_POLICY_INDEX + 6;
+
+impl Action {
+    /// Returns the index of the action in a policy vector from gold's perspective. The mapping is a bijection over `0..POLICY_SIZE`.
+    pub fn policy_index(&self) -> usize {
+        match self {
+            Action::Move(square, direction) => {
+                MOVE_POLICY_INDEX + square.index() * 4 + direction.index()
+            }
+            Action::Pass => PASS_POLICY_INDEX,
+            Action::Place(piece) => PLACE_POLICY_INDEX + piece.index(),
+        }
+    }
+
+    /// Returns the action at an index of a policy vector from gold's perspective, or None if the index is out of range.
+    pub fn from_policy_index(index: usize) -> Option<Action> {
+        if index < PASS_POLICY_INDEX {
+            let square = Square::from_index(((index - MOVE_POLICY_INDEX) / 4) as u8);
+            let direction = Direction::ALL[(index - MOVE_POLICY_INDEX) % 4];
+            Some(Action::Move(square, direction))
+        } else if index == PASS_POLICY_INDEX {
+            Some(Action::Pass)
+        } else if index < POLICY_SIZE {
+            Some(Action::Place(Piece::ALL[index - PLACE_POLICY_INDEX]))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the index of the action in a policy vector from the perspective of the specified player.
+    /// For silver, the board is flipped vertically the same as `GameState::input_planes`, so moving south is indexed as moving north.
+    pub fn policy_index_for_player(&self, p1_perspective: bool) -> usize {
+        self.flip_for_player(p1_perspective).policy_index()
+    }
+
+    /// Returns the action at an index of a policy vector from the perspective of the specified player.
+    pub fn from_policy_index_for_player(index: usize, p1_perspective: bool) -> Option<Action> {
+        Action::from_policy_index(index).map(|action| action.flip_for_player(p1_perspective))
+    }
+
+    fn flip_for_player(&self, p1_perspective: bool) -> Action {
+        if p1_perspective {
+            *self
+        } else {
+            self.flip_colors()
+        }
+    }
+}
+
+impl GameState {
+    /// Returns the index of the action in a policy vector from the perspective of the player to move.
+    pub fn policy_index(&self, action: &Action) -> usize {
+        action.policy_index_for_player(self.is_p1_turn_to_move())
+    }
+
+    /// Returns the action at an index of a policy vector from the perspective of the player to move.
+    pub fn action_for_policy_index(&self, index: usize) -> Option<Action> {
+        Action::from_policy_index_for_player(index, self.is_p1_turn_to_move())
+    }
+
+    /// Returns a mask of length `POLICY_SIZE` which is true at the index of each valid action, from the perspective of the player to move.
+    pub fn policy_mask(&self) -> Vec<bool> {
+        let mut mask = vec![false; POLICY_SIZE];
+
+        for action in self.valid_actions() {
+            mask[self.policy_index(&action)] = true;
+        }
+
+        mask
+    }
+
+    /// Maps a policy vector of non-negative weights, indexed from the perspective of the player to move, to a probability for each valid action.
+    /// Weights of invalid actions are ignored. If the valid actions have no weight, each valid action is given the same probability.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the policy is not `POLICY_SIZE`.
+    pub fn policy_distribution(&self, policy: &[f32]) -> Vec<(Action, f32)> {
+        assert_eq!(
+            policy.len(),
+            POLICY_SIZE,
+            "Expected a policy of length {}",
+            POLICY_SIZE
+        );
+
+        let weights: Vec<(Action, f32)> = self
+            .valid_actions()
+            .into_iter()
+            .map(|action| {
+                let weight = policy[self.policy_index(&action)].max(0.0);
+                (action, weight)
+            })
+            .collect();
+
+        normalize(weights)
+    }
+
+    /// Maps a policy vector of logits, indexed from the perspective of the player to move, to a probability for each valid action
+    /// by taking the softmax over the valid actions only.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the policy is not `POLICY_SIZE`.
+    pub fn policy_distribution_from_logits(&self, logits: &[f32]) -> Vec<(Action, f32)> {
+        assert_eq!(
+            logits.len(),
+            POLICY_SIZE,
+            "Expected a policy of length {}",
+            POLICY_SIZE
+        );
+
+        let logits: Vec<(Action, f32)> = self
+            .valid_actions()
+            .into_iter()
+            .map(|action| (action, logits[self.policy_index(&action)]))
+            .collect();
+
+        let max_logit = logits
+            .iter()
+            .map(|(_, logit)| *logit)
+            .fold(f32::NEG_INFINITY, f32::max);
+
+        normalize(
+            logits
+                .into_iter()
+                .map(|(action, logit)| (action, (logit - max_logit).exp()))
+                .collect(),
+        )
+    }
+}
+
+fn normalize(mut weights: Vec<(Action, f32)>) -> Vec<(Action, f32)> {
+    let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+
+    if total > 0.0 && total.is_finite() {
+        for (_, weight) in weights.iter_mut() {
+            *weight /= total;
+        }
+    } else {
+        let uniform = 1.0 / weights.len() as f32;
+        for (_, weight) in weights.iter_mut() {
+            *weight = uniform;
+        }
+    }
+
+    weights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::initial_play_state;
+    use super::super::{action, take_actions, Action, GameState};
+    use super::*;
+
+    #[test]
+    fn test_policy_index_bijection() {
+        for p1_perspective in [true, false] {
+            for index in 0..POLICY_SIZE {
+                let action = Action::from_policy_index_for_player(index, p1_perspective).unwrap();
+                assert_eq!(action.policy_index_for_player(p1_perspective), index);
+            }
+
+            assert_eq!(
+                Action::from_policy_index_for_player(POLICY_SIZE, p1_perspective),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_policy_index_layout() {
+        assert_eq!(POLICY_SIZE, 263);
+        assert_eq!(action!(a8n).policy_index(), 0);
+        assert_eq!(action!(a8w).policy_index(), 3);
+        assert_eq!(action!(b8n).policy_index(), 4);
+        assert_eq!(action!(h1w).policy_index(), 255);
+        assert_eq!(action!(p).policy_index(), 256);
+        assert_eq!(action!(r).policy_index(), 257);
+        assert_eq!(action!(e).policy_index(), 262);
+    }
+
+    #[test]
+    fn test_policy_index_silver_perspective() {
+        assert_eq!(
+            action!(d7s).policy_index_for_player(false),
+            action!(d2n).policy_index()
+        );
+        assert_eq!(
+            action!(a8e).policy_index_for_player(false),
+            action!(a1e).policy_index()
+        );
+        assert_eq!(action!(p).policy_index_for_player(false), PASS_POLICY_INDEX);
+        assert_eq!(
+            action!(m).policy_index_for_player(false),
+            action!(m).policy_index()
+        );
+    }
+
+    #[test]
+    fn test_policy_mask() {
+        let game_state = initial_play_state();
+        let mask = game_state.policy_mask();
+        let valid_actions = game_state.valid_actions();
+
+        assert_eq!(mask.len(), POLICY_SIZE);
+        assert_eq!(mask.iter().filter(|m| **m).count(), valid_actions.len());
+        assert!(mask[action!(d2n).policy_index()]);
+        assert!(!mask[action!(d1n).policy_index()]);
+
+        let game_state = take_actions!(game_state => a2n, a3n, a4n, b2n);
+        let mask = game_state.policy_mask();
+
+        // Silver's rabbit moving south from d7 is indexed the same as gold's rabbit moving north from d2.
+        assert!(mask[action!(d2n).policy_index()]);
+        assert_eq!(
+            game_state.action_for_policy_index(action!(d2n).policy_index()),
+            Some(action!(d7s))
+        );
+    }
+
+    #[test]
+    fn test_policy_mask_place_phase() {
+        let game_state = GameState::initial();
+        let mask = game_state.policy_mask();
+
+        assert_eq!(mask.iter().filter(|m| **m).count(), 6);
+        assert!(mask[PLACE_POLICY_INDEX..].iter().all(|m| *m));
+    }
+
+    #[test]
+    fn test_policy_distribution() {
+        let game_state = initial_play_state();
+        let mut policy = vec![1.0; POLICY_SIZE];
+        policy[action!(d2n).policy_index()] = 11.0;
+        policy[action!(d1n).policy_index()] = 1000.0;
+
+        let distribution = game_state.policy_distribution(&policy);
+        let total: f32 = distribution.iter().map(|(_, p)| p).sum();
+
+        assert_eq!(distribution.len(), game_state.valid_actions().len());
+        assert!((total - 1.0).abs() < 1e-5);
+
+        let (_, d2n_probability) = distribution
+            .iter()
+            .find(|(action, _)| *action == action!(d2n))
+            .unwrap();
+        let (_, a2n_probability) = distribution
+            .iter()
+            .find(|(action, _)| *action == action!(a2n))
+            .unwrap();
+
+        assert!((d2n_probability / a2n_probability - 11.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_policy_distribution_without_weight_is_uniform() {
+        let game_state = initial_play_state();
+        let distribution = game_state.policy_distribution(&[0.0; POLICY_SIZE]);
+        let uniform = 1.0 / distribution.len() as f32;
+
+        assert!(distribution.iter().all(|(_, p)| *p == uniform));
+    }
+
+    #[test]
+    fn test_policy_distribution_from_logits() {
+        let game_state = initial_play_state();
+        let mut logits = vec![0.0; POLICY_SIZE];
+        logits[action!(d2n).policy_index()] = 2.0_f32.ln();
+        logits[action!(d1n).policy_index()] = 1000.0;
+
+        let distribution = game_state.policy_distribution_from_logits(&logits);
+        let total: f32 = distribution.iter().map(|(_, p)| p).sum();
+
+        assert!((total - 1.0).abs() < 1e-5);
+
+        let (_, d2n_probability) = distribution
+            .iter()
+            .find(|(action, _)| *action == action!(d2n))
+            .unwrap();
+        let (_, a2n_probability) = distribution
+            .iter()
+            .find(|(action, _)| *action == action!(a2n))
+            .unwrap();
+
+        assert!((d2n_probability / a2n_probability - 2.0).abs() < 1e-4);
+    }
+}
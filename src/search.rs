@@ -0,0 +1,666 @@
+use super::{Action, Evaluator, GameState, Terminal, WIN_SCORE};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// The number of nodes searched between checks of the time limit and stop flag.
+const CHECK_LIMITS_INTERVAL: u64 = 1024;
+
+/// Scores beyond this value are wins or losses, which are adjusted by their distance from the root when stored in the transposition table.
+const WIN_THRESHOLD: i32 = WIN_SCORE - 1000;
+
+/// The limits of a search. The search stops once any of the limits has been reached.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SearchLimits {
+    /// The maximum depth in steps, including passes, to search.
+    pub max_depth: Option<usize>,
+    /// The maximum number of nodes to search.
+    pub max_nodes: Option<u64>,
+    /// The maximum amount of time to search.
+    pub max_time: Option<Duration>,
+}
+
+/// The result of the last completed iteration of a search.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchResult {
+    /// The score from the perspective of the player to move at the root.
+    pub score: i32,
+    /// The principal variation, which is the sequence of best actions for both players starting with the action to take.
+    pub pv: Vec<Action>,
+    /// The depth in steps of the last completed iteration.
+    pub depth: usize,
+    /// The total number of nodes searched across all iterations.
+    pub nodes: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TableEntry {
+    hash: u64,
+    depth: usize,
+    score: i32,
+    bound: Bound,
+    best_action: Option<Action>,
+}
+
+/// An iterative deepening alpha-beta search over steps.
+///
+/// Each ply of the search is a single step, so the player to move only switches after a pass or the fourth step of a move.
+/// Scores are negated at those plies, which is a negamax search where consecutive steps of a move are searched by the same player.
+///
+/// Positions are stored in a transposition table keyed by `GameState::transposition_hash`. Once a step of the move has been taken,
+/// the hash at the start of the move is mixed into the key, since whether the move can end depends on the board it started from.
+/// The key does not include the history of previous moves, so in rare cases a stored score may not account for a repetition.
+pub struct Search<E: Evaluator> {
+    evaluator: E,
+    table: Vec<Option<TableEntry>>,
+    nodes: u64,
+    max_nodes: u64,
+    deadline: Option<Instant>,
+    limits_active: bool,
+    aborted: bool,
+}
+
+impl<E: Evaluator> Search<E> {
+    /// The default number of entries in the transposition table.
+    pub const DEFAULT_TABLE_SIZE: usize = 1 << 18;
+
+    pub fn new(evaluator: E) -> Self {
+        Self::with_table_size(evaluator, Self::DEFAULT_TABLE_SIZE)
+    }
+
+    /// Creates a search with a transposition table of the specified number of entries. A size of 0 disables the table.
+    pub fn with_table_size(evaluator: E, table_size: usize) -> Self {
+        Search {
+            evaluator,
+            table: vec![None; table_size],
+            nodes: 0,
+            max_nodes: u64::MAX,
+            deadline: None,
+            limits_active: false,
+            aborted: false,
+        }
+    }
+
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// Removes all of the entries from the transposition table, such as when starting a new game.
+    pub fn clear(&mut self) {
+        self.table.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    /// Searches the state until one of the limits has been reached.
+    pub fn search(&mut self, game_state: &GameState, limits: &SearchLimits) -> SearchResult {
+        self.search_with_stop(game_state, limits, &AtomicBool::new(false))
+    }
+
+    /// Searches the state until one of the limits has been reached or `stop` has been set.
+    /// At least a depth of one step is always completed so that there is an action to take.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the state is in the setup phase.
+    pub fn search_with_stop(
+        &mut self,
+        game_state: &GameState,
+        limits: &SearchLimits,
+        stop: &AtomicBool,
+    ) -> SearchResult {
+        assert!(
+            game_state.is_play_phase(),
+            "Search is only supported in the play phase"
+        );
+
+        let mut game_state = game_state.clone();
+        let max_depth = limits.max_depth.unwrap_or(usize::MAX);
+
+        self.nodes = 0;
+        self.max_nodes = limits.max_nodes.unwrap_or(u64::MAX);
+        self.deadline = limits.max_time.map(|max_time| Instant::now() + max_time);
+        self.limits_active = false;
+        self.aborted = false;
+
+        let mut result = SearchResult {
+            score: 0,
+            pv: vec![],
+            depth: 0,
+            nodes: 0,
+        };
+
+        for depth in 1..=max_depth {
+            let mut pv = vec![];
+            let score = self.alpha_beta(
+                &mut game_state,
+                depth,
+                0,
+                -WIN_SCORE,
+                WIN_SCORE,
+                &mut pv,
+                stop,
+            );
+
+            if self.aborted {
+                break;
+            }
+
+            self.extend_pv_from_table(&mut game_state, &mut pv, depth);
+            result = SearchResult {
+                score,
+                pv,
+                depth,
+                nodes: self.nodes,
+            };
+
+            // A forced result has been found or there is nothing left to search.
+            if score.abs() > WIN_THRESHOLD || game_state.is_terminal().is_some() {
+                break;
+            }
+
+            // The limits are only applied after the first iteration so that there is always an action to take.
+            self.limits_active = true;
+
+            if self.limit_reached(stop) {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn alpha_beta(
+        &mut self,
+        game_state: &mut GameState,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Action>,
+        stop: &AtomicBool,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.nodes % CHECK_LIMITS_INTERVAL == 0 && self.limit_reached(stop) {
+            self.aborted = true;
+        }
+
+        if self.aborted {
+            return 0;
+        }
+
+        if let Some(terminal) = game_state.is_terminal() {
+            let is_win = (terminal == Terminal::GoldWin) == game_state.is_p1_turn_to_move();
+            let score = WIN_SCORE - ply as i32;
+            return if is_win { score } else { -score };
+        }
+
+        if depth == 0 {
+            return self.evaluator.evaluate(game_state);
+        }
+
+        let hash = table_key(game_state);
+        let entry = self.probe(hash);
+        if let Some(entry) = entry.filter(|entry| entry.depth >= depth && ply > 0) {
+            let score = score_from_table(entry.score, ply);
+            let is_cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+
+            if is_cutoff {
+                pv.clear();
+                pv.extend(entry.best_action);
+                return score;
+            }
+        }
+
+        let mut actions = game_state.valid_actions();
+        if let Some(best_action) = entry.and_then(|entry| entry.best_action) {
+            if let Some(index) = actions.iter().position(|action| *action == best_action) {
+                actions[..=index].rotate_right(1);
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best_score = -WIN_SCORE;
+        let mut best_action = None;
+        let mut child_pv = Vec::with_capacity(depth);
+
+        for action in actions {
+            let is_p1_turn_to_move = game_state.is_p1_turn_to_move();
+            let undo_info = game_state.apply_action(&action);
+            let is_same_player = game_state.is_p1_turn_to_move() == is_p1_turn_to_move;
+
+            child_pv.clear();
+            let score = if is_same_player {
+                self.alpha_beta(
+                    game_state,
+                    depth - 1,
+                    ply + 1,
+                    alpha,
+                    beta,
+                    &mut child_pv,
+                    stop,
+                )
+            } else {
+                -self.alpha_beta(
+                    game_state,
+                    depth - 1,
+                    ply + 1,
+                    -beta,
+                    -alpha,
+                    &mut child_pv,
+                    stop,
+                )
+            };
+
+            game_state.undo_action(undo_info);
+
+            if self.aborted {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_action = Some(action);
+
+                if score > alpha {
+                    alpha = score;
+                    pv.clear();
+                    pv.push(action);
+                    pv.extend_from_slice(&child_pv);
+                }
+            }
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+
+        self.store(TableEntry {
+            hash,
+            depth,
+            score: score_to_table(best_score, ply),
+            bound,
+            best_action,
+        });
+
+        best_score
+    }
+
+    /// Follows the best actions stored in the table from the end of the principal variation, up to the depth of the search.
+    /// A cutoff on a stored exact score returns only the best action of that position, which would otherwise end the variation early.
+    fn extend_pv_from_table(&self, game_state: &mut GameState, pv: &mut Vec<Action>, depth: usize) {
+        let mut undo_infos = Vec::with_capacity(depth);
+        for action in pv.iter() {
+            undo_infos.push(game_state.apply_action(action));
+        }
+
+        while pv.len() < depth && game_state.is_terminal().is_none() {
+            let best_action = self
+                .probe(table_key(game_state))
+                .and_then(|entry| entry.best_action)
+                .filter(|action| game_state.valid_actions().contains(action));
+
+            match best_action {
+                Some(action) => {
+                    undo_infos.push(game_state.apply_action(&action));
+                    pv.push(action);
+                }
+                None => break,
+            }
+        }
+
+        for undo_info in undo_infos.into_iter().rev() {
+            game_state.undo_action(undo_info);
+        }
+    }
+
+    fn limit_reached(&self, stop: &AtomicBool) -> bool {
+        if !self.limits_active {
+            return false;
+        }
+
+        self.nodes >= self.max_nodes
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            || stop.load(Ordering::Relaxed)
+    }
+
+    fn probe(&self, hash: u64) -> Option<TableEntry> {
+        if self.table.is_empty() {
+            return None;
+        }
+
+        let index = (hash % self.table.len() as u64) as usize;
+        self.table[index].filter(|entry| entry.hash == hash)
+    }
+
+    fn store(&mut self, entry: TableEntry) {
+        if self.table.is_empty() {
+            return;
+        }
+
+        let index = (entry.hash % self.table.len() as u64) as usize;
+        let slot = &mut self.table[index];
+
+        // Prefer keeping deeper results of the same position, otherwise always replace.
+        if slot.is_none_or(|existing| existing.hash != entry.hash || existing.depth <= entry.depth)
+        {
+            *slot = Some(entry);
+        }
+    }
+}
+
+/// The key of the state in the transposition table. After the first step of a move, the hash at the start of the move is mixed in
+/// because the valid actions depend on it. A pass is only valid if the board has changed, and the last step can't recreate the initial board.
+fn table_key(game_state: &GameState) -> u64 {
+    let hash = game_state.transposition_hash();
+    match game_state.as_play_phase() {
+        Some(play_phase) if play_phase.step() > 0 => {
+            hash ^ play_phase
+                .initial_hash_of_move()
+                .board_state_hash()
+                .rotate_left(32)
+        }
+        _ => hash,
+    }
+}
+
+/// Win and loss scores are relative to the root. Stored scores are made relative to the position instead.
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score > WIN_THRESHOLD {
+        score + ply as i32
+    } else if score < -WIN_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score > WIN_THRESHOLD {
+        score - ply as i32
+    } else if score < -WIN_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{action, take_actions, Evaluator, GameState, MaterialEvaluator, WIN_SCORE};
+    use super::{table_key, Search, SearchLimits};
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    fn assert_pv_is_valid(game_state: &GameState, pv: &[super::Action]) {
+        let mut game_state = game_state.clone();
+        for action in pv {
+            assert!(
+                game_state.valid_actions().contains(action),
+                "{:?} is not valid in\n{}",
+                action,
+                game_state
+            );
+            game_state = game_state.take_action(action);
+        }
+    }
+
+    fn depth_limit(max_depth: usize) -> SearchLimits {
+        SearchLimits {
+            max_depth: Some(max_depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    #[test]
+    fn test_search_finds_goal() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|           r     |
+             7|                 |
+             6|   R x   e x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let mut search = Search::new(MaterialEvaluator);
+        let result = search.search(&game_state, &depth_limit(6));
+
+        assert_eq!(&result.pv[..2], &[action!(b6n), action!(b7n)]);
+        assert_eq!(result.score, WIN_SCORE - 3);
+        assert!(result.depth <= 6);
+        assert_pv_is_valid(&game_state, &result.pv);
+    }
+
+    #[test]
+    fn test_search_captures_piece() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|   r   r r   r   |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|     h           |
+             3|     x E         |
+             2|                 |
+             1| R R R R         |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let mut search = Search::new(MaterialEvaluator);
+        let result = search.search(&game_state, &depth_limit(4));
+
+        assert_eq!(result.depth, 4);
+        assert!(result.score >= MaterialEvaluator.evaluate(&game_state) + 300);
+        assert_pv_is_valid(&game_state, &result.pv);
+    }
+
+    #[test]
+    fn test_search_avoids_losing_goal() {
+        let game_state: GameState = "
+             2s
+              +-----------------+
+             8|       r r     r |
+             7|   R e           |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1|               E |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        // Only the last step of the move remains. Moving the elephant unfreezes the rabbit, which can then reach goal.
+        let game_state = take_actions!(game_state => h8s, h7s, h6s);
+        let mut search = Search::new(MaterialEvaluator);
+        let result = search.search(&game_state, &depth_limit(4));
+
+        assert!(result.score > -WIN_SCORE / 2, "{:?}", result);
+        assert_ne!(result.pv[0], action!(c7e));
+        assert_pv_is_valid(&game_state, &result.pv);
+    }
+
+    #[test]
+    fn test_search_transposition_table_matches_without_table() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8|   r   r r   r   |
+             7|                 |
+             6|     x     x     |
+             5|     d m         |
+             4|     E           |
+             3|     x     x     |
+             2|       C         |
+             1| R R R R         |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let mut search = Search::new(MaterialEvaluator);
+        let mut search_without_table = Search::with_table_size(MaterialEvaluator, 0);
+
+        let result = search.search(&game_state, &depth_limit(4));
+        let result_without_table = search_without_table.search(&game_state, &depth_limit(4));
+
+        assert_eq!(result.score, result_without_table.score);
+        assert_eq!(result.pv.len(), result_without_table.pv.len());
+        assert!(result.nodes < result_without_table.nodes);
+        assert_pv_is_valid(&game_state, &result.pv);
+        assert_pv_is_valid(&game_state, &result_without_table.pv);
+
+        // A variation cut short by a cutoff on an exact score is completed from the table.
+        let mut pv = result.pv[..1].to_vec();
+        search.extend_pv_from_table(&mut game_state.clone(), &mut pv, result.depth);
+        assert_eq!(pv, result.pv);
+    }
+
+    #[test]
+    fn test_table_key_includes_board_at_start_of_move() {
+        let elephant_on_d4: GameState = "
+             2g
+              +-----------------+
+             8|   r   r r   r   |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|       E         |
+             3|     x     x     |
+             2|                 |
+             1| R R R R         |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        let elephant_on_d6: GameState = "
+             2g
+              +-----------------+
+             8|   r   r r   r   |
+             7|                 |
+             6|     x E   x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2|                 |
+             1| R R R R         |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        // Both reach the same board on the same step, but the first has returned to the board it started from.
+        let returned = take_actions!(elephant_on_d4.clone() => d4n, d5s);
+        let advanced = take_actions!(elephant_on_d6 => d6s, d5s);
+
+        assert_eq!(returned.transposition_hash(), advanced.transposition_hash());
+        assert!(!returned.valid_actions().contains(&action!(p)));
+        assert!(advanced.valid_actions().contains(&action!(p)));
+        assert_ne!(table_key(&returned), table_key(&advanced));
+
+        assert_eq!(
+            table_key(&elephant_on_d4),
+            elephant_on_d4.transposition_hash()
+        );
+    }
+
+    #[test]
+    fn test_search_node_limit() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8| h c d m e d c h |
+             7| r r r r r r r r |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2| R R R R R R R R |
+             1| H C D M E D C H |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let mut search = Search::new(MaterialEvaluator);
+        let result = search.search(
+            &game_state,
+            &SearchLimits {
+                max_nodes: Some(5000),
+                ..SearchLimits::default()
+            },
+        );
+
+        assert!(result.depth >= 1);
+        assert!(!result.pv.is_empty());
+        assert!(result.nodes <= 5000 + super::CHECK_LIMITS_INTERVAL);
+        assert_pv_is_valid(&game_state, &result.pv);
+    }
+
+    #[test]
+    fn test_search_time_limit_and_stop() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8| h c d m e d c h |
+             7| r r r r r r r r |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2| R R R R R R R R |
+             1| H C D M E D C H |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        let mut search = Search::new(MaterialEvaluator);
+        let result = search.search(
+            &game_state,
+            &SearchLimits {
+                max_time: Some(Duration::from_millis(50)),
+                ..SearchLimits::default()
+            },
+        );
+        assert!(!result.pv.is_empty());
+
+        let stop = AtomicBool::new(true);
+        let result = search.search_with_stop(&game_state, &SearchLimits::default(), &stop);
+        assert_eq!(result.depth, 1);
+        assert_pv_is_valid(&game_state, &result.pv);
+    }
+}
@@ -0,0 +1,200 @@
+use super::{Action, GameState, List, Phase, PieceBoard, Square, Zobrist};
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+/// Serializes a type using its compact string notation, such as `d2n` for an action or `d2` for a square.
+macro_rules! serialize_as_str {
+    ($type:ty) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+serialize_as_str!(Action);
+serialize_as_str!(Square);
+
+/// Lists are serialized as a sequence from the first appended element to the last.
+impl<T: Serialize> Serialize for List<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let elems: Vec<&T> = self.iter().collect();
+        let mut seq = serializer.serialize_seq(Some(elems.len()))?;
+        for elem in elems.into_iter().rev() {
+            seq.serialize_element(elem)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for List<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let elems = Vec::<T>::deserialize(deserializer)?;
+        Ok(elems
+            .into_iter()
+            .fold(List::new(), |list, elem| list.append(elem)))
+    }
+}
+
+/// The serialized fields of a `GameState`. States are deserialized through `GameState::new`, which recomputes the mirrored hash from the piece board.
+#[derive(serde::Deserialize)]
+pub(crate) struct GameStateFields {
+    p1_turn_to_move: bool,
+    move_number: usize,
+    phase: Phase,
+    piece_board: PieceBoard,
+    hash: Zobrist,
+}
+
+impl From<GameStateFields> for GameState {
+    fn from(fields: GameStateFields) -> Self {
+        GameState::new(
+            fields.p1_turn_to_move,
+            fields.move_number,
+            fields.phase,
+            fields.piece_board,
+            fields.hash,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::initial_play_state;
+    use super::super::{action, take_actions, Piece, PieceBoardState, PushPullState, Terminal};
+    use super::*;
+
+    fn mid_turn_state() -> GameState {
+        let game_state = take_actions!(initial_play_state() => e2n, e3n, p);
+        take_actions!(game_state => d7s, d8s)
+    }
+
+    fn assert_same_state(actual: &GameState, expected: &GameState) {
+        assert_eq!(actual.to_string(), expected.to_string());
+        assert_eq!(actual.move_number(), expected.move_number());
+        assert_eq!(actual.current_step(), expected.current_step());
+        assert_eq!(actual.transposition_hash(), expected.transposition_hash());
+        assert_eq!(actual.canonical_hash(), expected.canonical_hash());
+        assert_eq!(actual.valid_actions(), expected.valid_actions());
+
+        let actual_play_phase = actual.unwrap_play_phase();
+        let expected_play_phase = expected.unwrap_play_phase();
+        assert_eq!(
+            actual_play_phase.push_pull_state(),
+            expected_play_phase.push_pull_state()
+        );
+        assert_eq!(
+            actual_play_phase.hash_history().iter().collect::<Vec<_>>(),
+            expected_play_phase
+                .hash_history()
+                .iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            actual_play_phase.previous_piece_boards().len(),
+            expected_play_phase.previous_piece_boards().len()
+        );
+    }
+
+    #[test]
+    fn test_serialize_compact_notation() {
+        assert_eq!(serde_json::to_string(&action!(d2n)).unwrap(), r#""d2n""#);
+        assert_eq!(serde_json::to_string(&action!(p)).unwrap(), r#""p""#);
+        assert_eq!(
+            serde_json::to_string(&Square::new('d', 2)).unwrap(),
+            r#""d2""#
+        );
+
+        let action: Action = serde_json::from_str(r#""a8e""#).unwrap();
+        assert_eq!(action, action!(a8e));
+        assert!(serde_json::from_str::<Action>(r#""a9e""#).is_err());
+    }
+
+    #[test]
+    fn test_serialize_types_round_trip() {
+        let push_pull_state = PushPullState::PossiblePull(Square::new('d', 8), Piece::Dog);
+        let json = serde_json::to_string(&push_pull_state).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PushPullState>(&json).unwrap(),
+            push_pull_state
+        );
+
+        let terminal = Terminal::SilverWin;
+        let json = serde_json::to_string(&terminal).unwrap();
+        assert_eq!(serde_json::from_str::<Terminal>(&json).unwrap(), terminal);
+
+        let zobrist = Zobrist::from_piece_board(initial_play_state().piece_board(), true, 2);
+        let bytes = bincode::serialize(&zobrist).unwrap();
+        let deserialized: Zobrist = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(deserialized, zobrist);
+        assert_eq!(deserialized.canonical_hash(), zobrist.canonical_hash());
+
+        let list = List::new().append(1).append(2).append(3);
+        assert_eq!(serde_json::to_string(&list).unwrap(), "[1,2,3]");
+        let list: List<i32> = serde_json::from_str("[1,2,3]").unwrap();
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&3, &2, &1]);
+    }
+
+    #[test]
+    fn test_serialize_game_state_json_round_trip() {
+        let game_state = mid_turn_state();
+        assert_eq!(game_state.current_step(), 2);
+        assert_eq!(
+            game_state.unwrap_play_phase().push_pull_state(),
+            PushPullState::PossiblePull(Square::new('d', 8), Piece::Camel)
+        );
+        assert_eq!(game_state.unwrap_play_phase().hash_history().len(), 2);
+
+        let json = serde_json::to_string(&game_state).unwrap();
+        let deserialized: GameState = serde_json::from_str(&json).unwrap();
+
+        assert_same_state(&deserialized, &game_state);
+
+        let game_state = take_actions!(game_state => d7n, p);
+        let deserialized = take_actions!(deserialized => d7n, p);
+        assert_same_state(&deserialized, &game_state);
+    }
+
+    #[test]
+    fn test_deserialize_game_state_without_mirrored_hash() {
+        let game_state = mid_turn_state();
+        let mut json = serde_json::to_value(&game_state).unwrap();
+        json["hash"]["mirrored_hash"] = serde_json::Value::Null;
+
+        let deserialized: GameState = serde_json::from_value(json).unwrap();
+        assert_same_state(&deserialized, &game_state);
+    }
+
+    #[test]
+    fn test_serialize_game_state_bincode_round_trip() {
+        let game_state = mid_turn_state();
+        let bytes = bincode::serialize(&game_state).unwrap();
+        let deserialized: GameState = bincode::deserialize(&bytes).unwrap();
+
+        assert_same_state(&deserialized, &game_state);
+
+        let piece_board = game_state.piece_board();
+        let bytes = bincode::serialize(piece_board).unwrap();
+        let deserialized: PieceBoardState = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(deserialized.all_pieces(), piece_board.all_pieces());
+        assert_eq!(deserialized.p1_pieces(), piece_board.p1_pieces());
+    }
+
+    #[test]
+    fn test_serialize_place_phase_round_trip() {
+        let game_state = take_actions!(GameState::initial() => e, m);
+        let json = serde_json::to_string(&game_state).unwrap();
+        let deserialized: GameState = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.to_string(), game_state.to_string());
+        assert_eq!(deserialized.valid_actions(), game_state.valid_actions());
+    }
+}
@@ -40,7 +40,7 @@ impl Square {
 
     pub fn row(&self) -> u8 {
         let index = self.0 as usize;
-        let row = BOARD_HEIGHT - (index / BOARD_WIDTH) as usize;
+        let row = BOARD_HEIGHT - index / BOARD_WIDTH;
         row as u8
     }
 }
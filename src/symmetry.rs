@@ -0,0 +1,586 @@
+use super::{Action, Direction, GameState, List, Phase, PieceBoard, PieceBoardState, PlayPhase};
+use super::{PushPullState, Square, Zobrist};
+use std::collections::HashSet;
+
+/// Mirrors the bits of a board left to right, swapping the a and h columns.
+pub fn mirror_bits_horizontal(bits: u64) -> u64 {
+    bits.reverse_bits().swap_bytes()
+}
+
+/// Flips the bits of a board top to bottom, swapping the 1st and 8th rows.
+pub fn flip_bits_vertical(bits: u64) -> u64 {
+    bits.swap_bytes()
+}
+
+impl Square {
+    /// Returns the square mirrored left to right, such as a1 to h1.
+    pub fn mirror_horizontal(&self) -> Square {
+        Square::from_index(self.index() as u8 ^ 7)
+    }
+
+    /// Returns the square flipped top to bottom, such as a1 to a8.
+    pub fn flip_vertical(&self) -> Square {
+        Square::from_index(self.index() as u8 ^ 56)
+    }
+}
+
+impl Direction {
+    /// Returns the direction mirrored left to right, swapping east and west.
+    pub fn mirror_horizontal(&self) -> Direction {
+        match self {
+            Direction::Right => Direction::Left,
+            Direction::Left => Direction::Right,
+            _ => *self,
+        }
+    }
+
+    /// Returns the direction flipped top to bottom, swapping north and south.
+    pub fn flip_vertical(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            _ => *self,
+        }
+    }
+}
+
+impl Action {
+    /// Returns the equivalent action on a board mirrored left to right.
+    pub fn mirror_horizontal(&self) -> Action {
+        match self {
+            Action::Move(square, direction) => {
+                Action::Move(square.mirror_horizontal(), direction.mirror_horizontal())
+            }
+            _ => *self,
+        }
+    }
+
+    /// Returns the equivalent action on a board with the colors flipped, which is flipped top to bottom.
+    pub fn flip_colors(&self) -> Action {
+        match self {
+            Action::Move(square, direction) => {
+                Action::Move(square.flip_vertical(), direction.flip_vertical())
+            }
+            _ => *self,
+        }
+    }
+}
+
+impl PushPullState {
+    fn map_square(&self, map: impl Fn(&Square) -> Square) -> PushPullState {
+        match self {
+            PushPullState::PossiblePull(square, piece) => {
+                PushPullState::PossiblePull(map(square), *piece)
+            }
+            PushPullState::MustCompletePush(square, piece) => {
+                PushPullState::MustCompletePush(map(square), *piece)
+            }
+            PushPullState::None => PushPullState::None,
+        }
+    }
+}
+
+impl PieceBoardState {
+    /// Returns the board mirrored left to right.
+    pub fn mirror_horizontal(&self) -> PieceBoardState {
+        self.map_bits(mirror_bits_horizontal, self.p1_pieces().bits())
+    }
+
+    /// Returns the board flipped top to bottom with the gold and silver pieces swapped.
+    pub fn flip_colors(&self) -> PieceBoardState {
+        let p2_pieces = self.all_pieces().bits() & !self.p1_pieces().bits();
+        self.map_bits(flip_bits_vertical, p2_pieces)
+    }
+
+    fn map_bits(&self, map: fn(u64) -> u64, p1_pieces: u64) -> PieceBoardState {
+        PieceBoard::new(
+            map(p1_pieces),
+            map(self.elephants().bits()),
+            map(self.camels().bits()),
+            map(self.horses().bits()),
+            map(self.dogs().bits()),
+            map(self.cats().bits()),
+            map(self.rabbits().bits()),
+        )
+        .piece_board()
+        .clone()
+    }
+}
+
+impl GameState {
+    /// Returns the state mirrored left to right. Every valid action of the state maps to a valid action of the mirrored state through `Action::mirror_horizontal`.
+    ///
+    /// The hash history is mirrored along with the board, so repetitions are detected as in the original state. Hashes in the
+    /// history whose mirrored hash is unknown, such as those parsed with `Zobrist::from_hash`, can't be mirrored and are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics during the setup phase, where pieces are always placed from left to right.
+    pub fn mirror_horizontal(&self) -> GameState {
+        self.transform(
+            self.is_p1_turn_to_move(),
+            PieceBoardState::mirror_horizontal,
+            Square::mirror_horizontal,
+            Zobrist::mirror_horizontal,
+        )
+    }
+
+    /// Returns the state with the colors of the pieces and the player to move swapped, and the board flipped top to bottom so that
+    /// each player's rabbits still move towards their goal. The move number is kept the same. Every valid action of the state maps
+    /// to a valid action of the flipped state through `Action::flip_colors`, and no others are valid.
+    ///
+    /// The hashes of positions from previous moves can't be flipped, so the hash history only holds the hash of the position at
+    /// the start of the current move. Returns None if a position in the history has already occurred twice, as repeating it again
+    /// could be forbidden in this state but not in the flipped state. Repetitions of positions from before the flip are not detected
+    /// in states reached from the flipped state.
+    ///
+    /// # Panics
+    ///
+    /// Panics during the setup phase, where gold always places first.
+    pub fn flip_colors(&self) -> Option<GameState> {
+        let has_repeated_position = self.as_play_phase().is_some_and(|play_phase| {
+            let mut hashes = HashSet::new();
+            !play_phase
+                .hash_history()
+                .iter()
+                .all(|hash| hashes.insert(hash))
+        });
+
+        if has_repeated_position {
+            return None;
+        }
+
+        Some(self.transform(
+            !self.is_p1_turn_to_move(),
+            PieceBoardState::flip_colors,
+            Square::flip_vertical,
+            |_| None,
+        ))
+    }
+
+    /// The same as `canonical_hash`, which is shared by positions that are mirror images of each other.
+    pub fn symmetric_transposition_hash(&self) -> u64 {
+        self.canonical_hash()
+    }
+
+    fn transform(
+        &self,
+        p1_turn_to_move: bool,
+        map_piece_board: fn(&PieceBoardState) -> PieceBoardState,
+        map_square: fn(&Square) -> Square,
+        map_hash: fn(&Zobrist) -> Option<Zobrist>,
+    ) -> GameState {
+        let play_phase = self
+            .as_play_phase()
+            .expect("Symmetry transforms are only supported during the play phase");
+
+        let to_piece_board = |piece_board: &PieceBoardState| {
+            let piece_board = map_piece_board(piece_board);
+            PieceBoard::new(
+                piece_board.p1_pieces().bits(),
+                piece_board.elephants().bits(),
+                piece_board.camels().bits(),
+                piece_board.horses().bits(),
+                piece_board.dogs().bits(),
+                piece_board.cats().bits(),
+                piece_board.rabbits().bits(),
+            )
+        };
+
+        let piece_board = to_piece_board(self.piece_board());
+        let previous_piece_boards: Vec<PieceBoard> = play_phase
+            .previous_piece_boards()
+            .iter()
+            .map(|previous_piece_board| to_piece_board(previous_piece_board.piece_board()))
+            .collect();
+
+        let initial_piece_board = previous_piece_boards.first().unwrap_or(&piece_board);
+        let initial_hash_of_move =
+            Zobrist::from_piece_board(initial_piece_board.piece_board(), p1_turn_to_move, 0);
+        let hash = Zobrist::from_piece_board(
+            piece_board.piece_board(),
+            p1_turn_to_move,
+            play_phase.step(),
+        );
+
+        // The history ends with the hash at the start of the current move, which is replaced by the transformed one.
+        let hash_history = play_phase.hash_history();
+        let previous_hashes: Vec<Zobrist> = hash_history
+            .iter()
+            .skip(usize::from(
+                hash_history.head() == Some(&play_phase.initial_hash_of_move()),
+            ))
+            .filter_map(map_hash)
+            .collect();
+        let hash_history = previous_hashes
+            .into_iter()
+            .rev()
+            .fold(List::new(), |list, hash| list.append(hash))
+            .append(initial_hash_of_move);
+
+        GameState::new(
+            p1_turn_to_move,
+            self.move_number(),
+            Phase::PlayPhase(PlayPhase::new(
+                initial_hash_of_move,
+                hash_history,
+                previous_piece_boards,
+                play_phase.push_pull_state().map_square(map_square),
+                play_phase.piece_trapped_this_turn(),
+            )),
+            piece_board,
+            hash,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::{initial_play_state, to_piece_board};
+    use super::super::{action, take_actions, Action, BitBoard, GameState, Piece};
+    use super::*;
+
+    fn push_pull_state() -> GameState {
+        let game_state: GameState = "
+             12g
+              +-----------------+
+             8|   r   r r   r   |
+             7| m   h e   c   r |
+             6|   H x     x d   |
+             5|     r     C     |
+             4|       R   D     |
+             3|     x E   x     |
+             2| R     M   R     |
+             1|       R   R   R |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+
+        take_actions!(game_state => d3s)
+    }
+
+    fn assert_actions_map(
+        game_state: &GameState,
+        transformed: &GameState,
+        map: fn(&Action) -> Action,
+    ) {
+        let mut expected: Vec<Action> = game_state.valid_actions().iter().map(map).collect();
+        let mut actual = transformed.valid_actions();
+        expected.sort();
+        actual.sort();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_mirror_bits_horizontal() {
+        assert_eq!(mirror_bits_horizontal(1), 1 << 7);
+        assert_eq!(
+            mirror_bits_horizontal(Square::new('c', 3).as_bit_board()),
+            Square::new('f', 3).as_bit_board()
+        );
+        assert_eq!(
+            flip_bits_vertical(Square::new('c', 3).as_bit_board()),
+            Square::new('c', 6).as_bit_board()
+        );
+    }
+
+    #[test]
+    fn test_mirror_horizontal_square_and_action() {
+        assert_eq!(Square::new('a', 2).mirror_horizontal(), Square::new('h', 2));
+        assert_eq!(action!(b3e).mirror_horizontal(), action!(g3w));
+        assert_eq!(action!(d5n).mirror_horizontal(), action!(e5n));
+        assert_eq!(action!(p).mirror_horizontal(), action!(p));
+        assert_eq!(action!(b3e).flip_colors(), action!(b6e));
+        assert_eq!(action!(d2n).flip_colors(), action!(d7s));
+    }
+
+    #[test]
+    fn test_mirror_horizontal() {
+        let game_state = push_pull_state();
+        let mirrored = game_state.mirror_horizontal();
+
+        assert_eq!(
+            mirrored.unwrap_play_phase().push_pull_state(),
+            PushPullState::PossiblePull(Square::new('e', 3), Piece::Elephant)
+        );
+        assert_actions_map(&game_state, &mirrored, Action::mirror_horizontal);
+        assert_eq!(
+            format!("{:?}", mirrored.mirror_horizontal()),
+            format!("{:?}", game_state)
+        );
+        assert_eq!(
+            mirrored.mirror_horizontal().transposition_hash(),
+            game_state.transposition_hash()
+        );
+    }
+
+    #[test]
+    fn test_mirror_horizontal_takes_mirrored_actions() {
+        let game_state = initial_play_state();
+        let actions = [action!(e2n), action!(e3n), action!(e1n), action!(d2e)];
+
+        let mut mirrored = game_state.mirror_horizontal();
+        let mut original = game_state;
+
+        for action in actions.iter() {
+            original = original.take_action(action);
+            mirrored = mirrored.take_action(&action.mirror_horizontal());
+
+            assert_eq!(
+                original.mirror_horizontal().to_extended_notation(),
+                mirrored.to_extended_notation()
+            );
+            assert_eq!(
+                original.mirror_horizontal().transposition_hash(),
+                mirrored.transposition_hash()
+            );
+        }
+    }
+
+    /// Returns each state while the elephant and dog shuffle back and forth, ending where passing would repeat a position a third time.
+    fn repeated_position_states() -> Vec<GameState> {
+        let mut game_state: GameState = "
+             1g
+              +-----------------+
+             8|               r |
+             7|               d |
+             6|     x     x     |
+             5|       m         |
+             4|                 |
+             3|     x E   x     |
+             2|                 |
+             1|               R |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        let actions = [
+            "d3n", "p", "h7s", "p", "d4s", "p", "h6n", "p", "d3n", "p", "h7s", "p", "d4s", "d3s",
+            "p", "h6n", "p", "d2n", "d3n",
+        ];
+
+        let mut game_states = vec![game_state.clone()];
+        for action in actions.iter() {
+            game_state = game_state.take_action(&action.parse().unwrap());
+            game_states.push(game_state.clone());
+        }
+
+        game_states
+    }
+
+    #[test]
+    fn test_mirror_horizontal_detects_repetitions() {
+        let game_state = repeated_position_states().pop().unwrap();
+
+        // Passing would repeat the position a third time.
+        let mirrored = game_state.mirror_horizontal();
+        assert!(!game_state.valid_actions().contains(&Action::Pass));
+        assert!(!mirrored.valid_actions().contains(&Action::Pass));
+        assert!(mirrored.valid_actions_no_rep().contains(&Action::Pass));
+        assert_actions_map(&game_state, &mirrored, Action::mirror_horizontal);
+        assert_eq!(
+            mirrored.unwrap_play_phase().hash_history().len(),
+            game_state.unwrap_play_phase().hash_history().len()
+        );
+    }
+
+    #[test]
+    fn test_flip_colors_with_repeated_positions() {
+        let game_states = repeated_position_states();
+
+        for game_state in &game_states {
+            if let Some(flipped) = game_state.flip_colors() {
+                assert_actions_map(game_state, &flipped, Action::flip_colors);
+            }
+        }
+
+        let game_state = game_states.last().unwrap();
+        assert!(!game_state.valid_actions().contains(&Action::Pass));
+        assert!(game_state.flip_colors().is_none());
+        assert!(game_states[0].flip_colors().is_some());
+    }
+
+    #[test]
+    fn test_flip_colors_seeds_hash_history() {
+        let game_state = take_actions!(initial_play_state() => e2n, e3n);
+        let flipped = game_state.flip_colors().unwrap();
+        let hash_history = flipped.unwrap_play_phase().hash_history();
+
+        assert_eq!(hash_history.len(), 1);
+        assert_eq!(
+            hash_history.head().map(|hash| hash.board_state_hash()),
+            Some(
+                initial_play_state()
+                    .flip_colors()
+                    .unwrap()
+                    .transposition_hash()
+            )
+        );
+    }
+
+    #[test]
+    fn test_flip_colors() {
+        let game_state = push_pull_state();
+        let flipped = game_state.flip_colors().unwrap();
+
+        assert!(!flipped.is_p1_turn_to_move());
+        assert_eq!(flipped.current_step(), 1);
+        assert_eq!(
+            flipped.unwrap_play_phase().push_pull_state(),
+            PushPullState::PossiblePull(Square::new('d', 6), Piece::Elephant)
+        );
+        assert_eq!(
+            flipped.piece_board().bits_for_piece(Piece::Elephant, false),
+            BitBoard::from(Square::new('d', 7))
+        );
+        assert_actions_map(&game_state, &flipped, Action::flip_colors);
+
+        let flipped_back = flipped.flip_colors().unwrap();
+        assert_eq!(
+            flipped_back.transposition_hash(),
+            game_state.transposition_hash()
+        );
+        assert_eq!(
+            flipped_back.piece_board().p1_pieces(),
+            game_state.piece_board().p1_pieces()
+        );
+    }
+
+    #[test]
+    fn test_flip_colors_takes_flipped_actions() {
+        let game_state = initial_play_state();
+        let actions = [action!(e2n), action!(e3n), action!(p), action!(a7s)];
+
+        let mut flipped = game_state.flip_colors().unwrap();
+        let mut original = game_state;
+
+        for action in actions.iter() {
+            original = original.take_action(action);
+            flipped = flipped.take_action(&action.flip_colors());
+
+            assert_eq!(
+                original.flip_colors().unwrap().transposition_hash(),
+                flipped.transposition_hash()
+            );
+        }
+    }
+
+    #[test]
+    fn test_canonical_hash_of_state_created_from_hash() {
+        let created_from_hash = |game_state: &GameState| {
+            let hash = Zobrist::from_hash(game_state.transposition_hash());
+            GameState::new(
+                game_state.is_p1_turn_to_move(),
+                game_state.move_number(),
+                Phase::PlayPhase(PlayPhase::initial(hash, List::new())),
+                to_piece_board(game_state.piece_board()),
+                hash,
+            )
+        };
+
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8| r r r r r r r r |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2| R R R C C R R R |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        let mirrored = take_actions!(game_state.clone() => g2n, p);
+        let game_state = take_actions!(game_state => b2n, p);
+        let game_state_from_hash = created_from_hash(&game_state);
+
+        assert_eq!(
+            game_state_from_hash.canonical_hash(),
+            game_state.canonical_hash()
+        );
+        assert_eq!(
+            game_state_from_hash.canonical_hash(),
+            created_from_hash(&mirrored).canonical_hash()
+        );
+    }
+
+    #[test]
+    fn test_canonical_hash() {
+        let game_state: GameState = "
+             2g
+              +-----------------+
+             8| r r r r r r r r |
+             7|                 |
+             6|     x     x     |
+             5|                 |
+             4|                 |
+             3|     x     x     |
+             2| R R R C C R R R |
+             1|                 |
+              +-----------------+
+                a b c d e f g h"
+            .parse()
+            .unwrap();
+        let mirrored = take_actions!(game_state.clone() => g2n);
+        let game_state = take_actions!(game_state => b2n);
+
+        assert_ne!(
+            game_state.transposition_hash(),
+            mirrored.transposition_hash()
+        );
+        assert_eq!(game_state.canonical_hash(), mirrored.canonical_hash());
+        assert_eq!(
+            game_state.symmetric_transposition_hash(),
+            mirrored.symmetric_transposition_hash()
+        );
+        assert_eq!(
+            game_state.symmetric_transposition_hash(),
+            game_state.canonical_hash()
+        );
+        assert_eq!(
+            GameState::initial().canonical_hash(),
+            GameState::initial().transposition_hash()
+        );
+
+        let setup_state = take_actions!(GameState::initial() => e);
+        assert_eq!(
+            setup_state.symmetric_transposition_hash(),
+            setup_state.canonical_hash()
+        );
+    }
+
+    #[test]
+    fn test_canonical_hash_is_maintained_incrementally() {
+        let mut game_state = initial_play_state();
+
+        for i in 0..200 {
+            let valid_actions = game_state.valid_actions();
+            if valid_actions.is_empty() || game_state.is_terminal().is_some() {
+                break;
+            }
+
+            let action = valid_actions[(i * 7) % valid_actions.len()];
+            game_state = game_state.take_action(&action);
+            let mirrored = game_state.mirror_horizontal();
+
+            assert_eq!(game_state.canonical_hash(), mirrored.canonical_hash());
+            assert_eq!(
+                game_state.canonical_hash(),
+                game_state
+                    .transposition_hash()
+                    .min(mirrored.transposition_hash())
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mirror_horizontal_setup_phase_panics() {
+        GameState::initial().mirror_horizontal();
+    }
+}
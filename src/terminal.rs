@@ -1,5 +1,31 @@
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Terminal {
     GoldWin,
     SilverWin,
 }
+
+/// The reason a game ended.
+///
+/// `GameState::terminal_reason` reports the reasons that can be determined from the board: goal, elimination,
+/// immobilization and repetition. The remaining reasons are decided outside of the board, such as by a game server.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TerminalReason {
+    /// A rabbit reached the opposite side of the board.
+    Goal,
+    /// A player lost all of their rabbits.
+    Elimination,
+    /// The player to move has no valid move, as all of their pieces are frozen or blocked.
+    Immobilization,
+    /// The only moves available to the player to move would result in a third time repetition.
+    Repetition,
+    /// A player ran out of time.
+    Timeout,
+    /// A player resigned.
+    Resignation,
+    /// A player forfeited, such as by making an illegal move or disconnecting.
+    Forfeit,
+    /// The game was stopped and decided by score.
+    Score,
+}
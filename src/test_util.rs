@@ -0,0 +1,96 @@
+use super::bit_mask::TRAP_MASK;
+use super::{
+    take_actions, GameState, List, Phase, PieceBoard, PieceBoardState, PlayPhase, Zobrist,
+};
+
+/// Creates the play phase state after both players set up with their rabbits on the home row.
+pub(crate) fn initial_play_state() -> GameState {
+    let game_state = GameState::initial();
+    let game_state = take_actions!(game_state => r, r, r, r, r, r, r, r);
+    let game_state = take_actions!(game_state => h, c, d, m, e, d, c, h);
+
+    let game_state = take_actions!(game_state => h, c, d, m, e, d, c, h);
+    take_actions!(game_state => r, r, r, r, r, r, r, r)
+}
+
+/// Creates a piece board with the same pieces, such as to pass to `GameState::new`.
+pub(crate) fn to_piece_board(piece_board: &PieceBoardState) -> PieceBoard {
+    PieceBoard::new(
+        piece_board.p1_pieces().bits(),
+        piece_board.elephants().bits(),
+        piece_board.camels().bits(),
+        piece_board.horses().bits(),
+        piece_board.dogs().bits(),
+        piece_board.cats().bits(),
+        piece_board.rabbits().bits(),
+    )
+}
+
+/// Creates a play phase state at the first step of a move for the player on the board, without any history.
+pub(crate) fn start_of_move(piece_board: &PieceBoardState, p1_to_move: bool) -> GameState {
+    let piece_board = to_piece_board(piece_board);
+    let hash = Zobrist::from_piece_board(piece_board.piece_board(), p1_to_move, 0);
+
+    GameState::new(
+        p1_to_move,
+        2,
+        Phase::PlayPhase(PlayPhase::initial(hash, List::new())),
+        piece_board,
+        hash,
+    )
+}
+
+/// Scatters a few pieces of each player over the board away from the traps, with rabbits close enough to goal for some to reach it.
+pub(crate) fn random_positions(count: usize) -> Vec<GameState> {
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut random = move |bound: u64| {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) % bound
+    };
+
+    (0..count)
+        .map(|i| {
+            // The player's pieces followed by the elephants, camels, horses, dogs, cats and rabbits.
+            let mut bits = [0u64; 7];
+            let mut occupied = TRAP_MASK;
+
+            for is_p1 in [true, false] {
+                for piece_index in 1..7 {
+                    let count = match piece_index {
+                        1 => 1,
+                        6 => 1 + random(3),
+                        _ => random(2),
+                    };
+
+                    for _ in 0..count {
+                        // Rabbits are kept off of both goal rows, between the 7th and 3rd rows from their goal.
+                        let (min_index, max_index) = match (piece_index, is_p1) {
+                            (6, true) => (8, 48),
+                            (6, false) => (16, 56),
+                            _ => (0, 64),
+                        };
+                        let square_bit = loop {
+                            let square_bit = 1 << (min_index + random(max_index - min_index));
+                            if occupied & square_bit == 0 {
+                                break square_bit;
+                            }
+                        };
+
+                        occupied |= square_bit;
+                        bits[piece_index] |= square_bit;
+                        if is_p1 {
+                            bits[0] |= square_bit;
+                        }
+                    }
+                }
+            }
+
+            let piece_board = PieceBoard::new(
+                bits[0], bits[1], bits[2], bits[3], bits[4], bits[5], bits[6],
+            );
+            start_of_move(piece_board.piece_board(), i % 2 == 0)
+        })
+        .collect()
+}
@@ -1,15 +1,56 @@
 use super::zobrist_values::*;
-use super::{map_bit_board_to_squares, Piece, Square};
-use super::{GameState, PieceBoardState, PushPullState};
+use super::{GameState, PieceBoard, PieceBoardState, PushPullState};
+use super::{Piece, Square};
+use std::hash::{Hash, Hasher};
 
-#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+/// A Zobrist hash of a state along with the hash of the same state mirrored left to right.
+/// Both hashes are updated incrementally. The mirrored hash is unknown for hashes created with `from_hash`.
+/// Equality and hashing only use the hash of the state, as the mirrored hash is derived from the same position.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug)]
 pub struct Zobrist {
     hash: u64,
+    mirrored_hash: Option<u64>,
+}
+
+impl PartialEq for Zobrist {
+    fn eq(&self, other: &Zobrist) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl Eq for Zobrist {}
+
+impl Hash for Zobrist {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
+    }
 }
 
 impl Zobrist {
     pub fn initial() -> Self {
-        Zobrist { hash: INITIAL }
+        Zobrist {
+            hash: INITIAL,
+            mirrored_hash: Some(INITIAL),
+        }
+    }
+
+    /// Creates a hash from a value previously returned by `board_state_hash`.
+    /// The mirrored position is not known, so `canonical_hash` returns None for the created hash and any hash derived from it.
+    /// `GameState::new` recomputes the mirrored hash from the piece board, so states created with the hash have a canonical hash.
+    pub fn from_hash(hash: u64) -> Self {
+        Zobrist {
+            hash,
+            mirrored_hash: None,
+        }
+    }
+
+    /// Creates a hash from values previously returned by `board_state_hash` and `mirrored_hash`.
+    pub fn from_hashes(hash: u64, mirrored_hash: u64) -> Self {
+        Zobrist {
+            hash,
+            mirrored_hash: Some(mirrored_hash),
+        }
     }
 
     pub fn from_piece_board(
@@ -24,16 +65,25 @@ impl Zobrist {
 
         hash ^= STEP_VALUES[step_num];
 
-        for is_p1 in [true, false].iter() {
-            for piece in Piece::ALL.iter() {
-                let piece_bits = piece_board.bits_for_piece(*piece, *is_p1);
-                for square in map_bit_board_to_squares(piece_bits) {
-                    hash ^= piece_value(square, *piece, *is_p1);
-                }
-            }
+        let (piece_board_value, mirrored_piece_board_value) =
+            piece_board_value(PieceBoard::initial().piece_board(), piece_board);
+
+        Zobrist {
+            hash: hash ^ piece_board_value,
+            mirrored_hash: Some(hash ^ mirrored_piece_board_value),
         }
+    }
+
+    /// Recomputes the mirrored hash from the piece board of the state that the hash was created from.
+    /// States created with `GameState::new` use this so that their canonical hash is known even for a hash created with `from_hash`.
+    pub(crate) fn with_mirrored_hash_from(&self, piece_board: &PieceBoardState) -> Self {
+        let (piece_board_value, mirrored_piece_board_value) =
+            piece_board_value(PieceBoard::initial().piece_board(), piece_board);
 
-        Zobrist { hash }
+        Zobrist {
+            hash: self.hash,
+            mirrored_hash: Some(self.hash ^ piece_board_value ^ mirrored_piece_board_value),
+        }
     }
 
     pub fn move_piece(
@@ -48,12 +98,19 @@ impl Zobrist {
         } else {
             0
         };
-        let piece_board_value = piece_board_value(prev_game_state.piece_board(), new_piece_board);
+        let (piece_board_value, mirrored_piece_board_value) =
+            piece_board_value(prev_game_state.piece_board(), new_piece_board);
         let step_value = step_value(prev_game_state.current_step(), new_step);
 
         let hash = self.hash ^ player_to_move_value ^ piece_board_value ^ step_value;
+        let mirrored_hash = self.mirrored_hash.map(|mirrored_hash| {
+            mirrored_hash ^ player_to_move_value ^ mirrored_piece_board_value ^ step_value
+        });
 
-        Zobrist { hash }
+        Zobrist {
+            hash,
+            mirrored_hash,
+        }
     }
 
     pub fn place_piece(
@@ -70,23 +127,27 @@ impl Zobrist {
             0
         };
         let place_piece_value = piece_value(square, piece, place_is_p1);
+        let mirrored_place_piece_value =
+            piece_value(square.mirror_horizontal(), piece, place_is_p1);
         let step_value = if switch_phases { STEP_VALUES[0] } else { 0 };
 
         let hash = self.hash ^ player_to_move_value ^ place_piece_value ^ step_value;
+        let mirrored_hash = self.mirrored_hash.map(|mirrored_hash| {
+            mirrored_hash ^ player_to_move_value ^ mirrored_place_piece_value ^ step_value
+        });
 
-        Zobrist { hash }
+        Zobrist {
+            hash,
+            mirrored_hash,
+        }
     }
 
     pub fn pass(&self, step: usize) -> Self {
-        let hash = self.hash ^ PLAYER_TO_MOVE ^ STEP_VALUES[0] ^ STEP_VALUES[step];
-
-        Zobrist { hash }
+        self.xor(PLAYER_TO_MOVE ^ STEP_VALUES[0] ^ STEP_VALUES[step])
     }
 
     pub fn exclude_step(&self, step: usize) -> Self {
-        let hash = self.hash ^ STEP_VALUES[0] ^ STEP_VALUES[step];
-
-        Zobrist { hash }
+        self.xor(STEP_VALUES[0] ^ STEP_VALUES[step])
     }
 
     pub fn board_state_hash(&self) -> u64 {
@@ -102,14 +163,72 @@ impl Zobrist {
 
         self.hash ^ push_pull_hash
     }
+
+    /// Returns the hash of the state mirrored left to right, or None if it is not known.
+    pub fn mirrored_hash(&self) -> Option<u64> {
+        self.mirrored_hash
+    }
+
+    /// Returns a hash which is the same for the state and the state mirrored left to right.
+    /// Returns None if the mirrored hash is not known, such as for hashes created with `from_hash`.
+    pub fn canonical_hash(&self) -> Option<u64> {
+        self.mirrored_hash
+            .map(|mirrored_hash| self.hash.min(mirrored_hash))
+    }
+
+    /// Returns the canonical hash including the push/pull state, which is mirrored along with the board.
+    /// Returns None if the mirrored hash is not known, such as for hashes created with `from_hash`.
+    pub fn canonical_hash_with_push_pull_state(
+        &self,
+        push_pull_state: PushPullState,
+    ) -> Option<u64> {
+        let (push_pull_hash, mirrored_push_pull_hash) = match push_pull_state {
+            PushPullState::MustCompletePush(square, piece) => (
+                push_piece_value(square, piece),
+                push_piece_value(square.mirror_horizontal(), piece),
+            ),
+            PushPullState::PossiblePull(square, piece) => (
+                pull_piece_value(square, piece),
+                pull_piece_value(square.mirror_horizontal(), piece),
+            ),
+            PushPullState::None => (0, 0),
+        };
+
+        self.mirrored_hash.map(|mirrored_hash| {
+            (self.hash ^ push_pull_hash).min(mirrored_hash ^ mirrored_push_pull_hash)
+        })
+    }
+
+    /// Returns the hash of the position mirrored left to right, which swaps the hash and the mirrored hash.
+    /// Returns None if the mirrored hash is unknown.
+    pub(crate) fn mirror_horizontal(&self) -> Option<Self> {
+        self.mirrored_hash.map(|mirrored_hash| Zobrist {
+            hash: mirrored_hash,
+            mirrored_hash: Some(self.hash),
+        })
+    }
+
+    fn xor(&self, value: u64) -> Self {
+        Zobrist {
+            hash: self.hash ^ value,
+            mirrored_hash: self
+                .mirrored_hash
+                .map(|mirrored_hash| mirrored_hash ^ value),
+        }
+    }
 }
 
 fn step_value(prev_step: usize, new_step: usize) -> u64 {
     STEP_VALUES[prev_step] ^ STEP_VALUES[new_step]
 }
 
-fn piece_board_value(prev_piece_board: &PieceBoardState, new_piece_board: &PieceBoardState) -> u64 {
+/// Returns the values of the pieces which differ between the boards, for the boards as they are and mirrored left to right.
+fn piece_board_value(
+    prev_piece_board: &PieceBoardState,
+    new_piece_board: &PieceBoardState,
+) -> (u64, u64) {
     let mut value = 0;
+    let mut mirrored_value = 0;
 
     for is_p1 in [true, false].iter() {
         for piece in Piece::ALL.iter() {
@@ -117,15 +236,14 @@ fn piece_board_value(prev_piece_board: &PieceBoardState, new_piece_board: &Piece
             let new_piece_bits = new_piece_board.bits_for_piece(*piece, *is_p1);
             let diff_bits = prev_piece_bits ^ new_piece_bits;
 
-            if diff_bits != 0 {
-                for square in map_bit_board_to_squares(diff_bits) {
-                    value ^= piece_value(square, *piece, *is_p1);
-                }
+            for square in diff_bits {
+                value ^= piece_value(square, *piece, *is_p1);
+                mirrored_value ^= piece_value(square.mirror_horizontal(), *piece, *is_p1);
             }
         }
     }
 
-    value
+    (value, mirrored_value)
 }
 
 fn piece_value(square: Square, piece: Piece, is_p1: bool) -> u64 {
@@ -168,3 +286,66 @@ fn pull_piece_value(square: Square, piece: Piece) -> u64 {
 
     POSSIBLE_PULL_VALUES[piece_idx][square.index()]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::{take_actions, GameState};
+    use super::*;
+
+    #[test]
+    fn test_incremental_hash_matches_from_piece_board() {
+        let mut game_state = take_actions!(
+            r, r, r, r, r, r, r, r, h, c, d, m, e, d, c, h, h, c, d, m, e, d, c, h, r, r, r, r, r,
+            r, r, r
+        );
+
+        for i in 0..200 {
+            if game_state.is_terminal().is_some() {
+                break;
+            }
+
+            // The incrementally updated hash of the state at the start of each move is added to the history.
+            if game_state.current_step() == 0 {
+                let expected = Zobrist::from_piece_board(
+                    game_state.piece_board(),
+                    game_state.is_p1_turn_to_move(),
+                    0,
+                );
+                let hash_history = game_state.unwrap_play_phase().hash_history();
+                let actual = hash_history.iter().next().unwrap();
+
+                assert_eq!(actual.mirrored_hash(), expected.mirrored_hash());
+                assert_eq!(*actual, expected);
+            }
+
+            let valid_actions = game_state.valid_actions();
+            game_state = game_state.take_action(&valid_actions[(i * 7) % valid_actions.len()]);
+        }
+    }
+
+    #[test]
+    fn test_from_hash_has_no_canonical_hash() {
+        let zobrist = Zobrist::from_piece_board(take_actions!(r, r, e).piece_board(), true, 0);
+        let from_hash = Zobrist::from_hash(zobrist.board_state_hash());
+
+        assert_eq!(from_hash.board_state_hash(), zobrist.board_state_hash());
+        assert_eq!(from_hash, zobrist);
+        assert!(zobrist.canonical_hash().is_some());
+        assert_eq!(from_hash.mirrored_hash(), None);
+        assert_eq!(from_hash.canonical_hash(), None);
+        assert_eq!(from_hash.pass(1).canonical_hash(), None);
+        assert_eq!(
+            from_hash.canonical_hash_with_push_pull_state(PushPullState::None),
+            None
+        );
+
+        let from_hashes =
+            Zobrist::from_hashes(zobrist.board_state_hash(), zobrist.mirrored_hash().unwrap());
+        assert_eq!(from_hashes, zobrist);
+        assert_eq!(from_hashes.canonical_hash(), zobrist.canonical_hash());
+
+        let piece_board = take_actions!(r, r, e);
+        let recomputed = from_hash.with_mirrored_hash_from(piece_board.piece_board());
+        assert_eq!(recomputed.mirrored_hash(), zobrist.mirrored_hash());
+    }
+}